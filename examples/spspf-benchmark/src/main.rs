@@ -7,13 +7,17 @@ use alloc::vec::Vec;
 use spspf::{
     core::{
         input::{Buttons, InputManager},
-        Vec2, Vec3,
+        FrameTimer, Vec2, Vec3,
     },
-    graphics::{canvas::Canvas, colors::Colors, Drawable, Primitive},
+    debug_text,
+    graphics::{canvas::Canvas, colors::Colors, Drawable, Primitive, SpriteBatch},
 };
 
 psp::module!("SPSPF - Demo", 1, 1);
 
+/// Most rects on screen, whose vertices must all fit in the display list of a frame when batched.
+const MAX_RECTS: usize = 6000;
+
 fn psp_main() {
     let mut running = true;
     let mut canvas = Canvas::new();
@@ -21,9 +25,16 @@ fn psp_main() {
 
     let mut rects: Vec<(Primitive::Rect, Vec2<f32>)> = Vec::new();
 
+    // Draws the rects with a single `SpriteBatch` instead of one draw call each, toggled with Circle.
+    let mut batch = SpriteBatch::new();
+    let mut batched = false;
+
+    let mut timer = FrameTimer::new();
+
     while running {
         canvas.start_frame();
         input_manager.update();
+        timer.tick();
 
         canvas.clear(Colors::BLACK.as_color());
 
@@ -39,10 +50,15 @@ fn psp_main() {
             pos.x += rects[rect_id].1.x;
             pos.y += rects[rect_id].1.y;
             rects[rect_id].0.set_pos(pos);
-            rects[rect_id].0.draw();
+            if batched {
+                batch.draw_rect(pos, size, Colors::BLUE.as_color());
+            } else {
+                rects[rect_id].0.draw();
+            }
         }
+        batch.flush();
 
-        if input_manager.is_key_down_changed(Buttons::Cross) {
+        if input_manager.is_key_down_changed(Buttons::Cross) && rects.len() < MAX_RECTS {
             let new_rect = Primitive::Rect::new(
                 Vec3::new(240.0, 136.0, -1.0),
                 Vec2::new(5.0, 5.0),
@@ -50,6 +66,34 @@ fn psp_main() {
             );
             rects.push((new_rect.clone(), Vec2::new(1.0, 1.0)));
         }
+        if input_manager.is_key_down_changed(Buttons::Square) {
+            for i in 0..100.min(MAX_RECTS - rects.len()) {
+                let new_rect = Primitive::Rect::new(
+                    Vec3::new(240.0, 136.0, -1.0),
+                    Vec2::new(5.0, 5.0),
+                    Colors::BLUE.as_color(),
+                );
+                let speed = 0.5 + (i % 10) as f32 * 0.25;
+                rects.push((new_rect, Vec2::new(speed, 2.5 - speed)));
+            }
+        }
+        if input_manager.is_key_down_changed(Buttons::Circle) {
+            batched = !batched;
+        }
+
+        debug_text!(
+            10,
+            10,
+            Colors::WHITE.as_color(),
+            "{} rects, {:.0} fps, {}",
+            rects.len(),
+            timer.fps(),
+            if batched {
+                alloc::format!("batched ({} draw calls)", batch.draw_calls())
+            } else {
+                alloc::format!("immediate ({} draw calls)", rects.len())
+            }
+        );
 
         running = input_manager.is_key_up(Buttons::Triangle);
 
@@ -2,13 +2,12 @@
 #![no_main]
 
 extern crate alloc;
+use alloc::rc::Rc;
 use psp::Align16;
 use spspf::{
-    core::{
-        input::{Buttons, InputManager},
-        Vec2, Vec3,
-    },
-    graphics::{Canvas, Colors, Drawable, Primitive, Sprite},
+    app::{App, Backend, Game, PspBackend},
+    core::{input::Buttons, Vec2, Vec3},
+    graphics::{Colors, Drawable, Gradient, PixelFormat, Primitive, Sprite, Texture},
 };
 
 psp::module!("SPSPF - Demo", 1, 1);
@@ -16,73 +15,84 @@ psp::module!("SPSPF - Demo", 1, 1);
 pub static FERRIS: Align16<[u8; 128 * 128 * 4 as usize]> =
     Align16(*include_bytes!("../ferris.bin"));
 
-fn psp_main() {
-    let mut canvas = Canvas::new();
-    let mut input_manager = InputManager::new();
-
-    let mut rect = Primitive::Rect::new(
-        Vec3::new(240.0, 136.0, -1.0),
-        Vec2::new(240.0, 136.0),
-        Colors::BLUE.as_color(),
-    );
-
-    let mut triangle = Primitive::Triangle::new(
-        [
-            Vec3::new(0.0, 272.0, -1.0),
-            Vec3::new(0.0, 0.0, -1.0),
-            Vec3::new(480.0, 0.0, -1.0),
-        ],
-        Colors::RED.as_color(),
-    );
-
-    let mut ellipse = Primitive::Ellipse::new(
-        Vec3::new(240.0, 136.0, -1.0),
-        Vec2::new(75.0, 50.0),
-        Colors::WHITE.as_color(),
-    );
-
-    let mut sprite = Sprite::new(
-        Vec3::new(217.5, 113.5, -1.0),
-        0.0,
-        Vec2::new(45.0, 45.0),
-        FERRIS,
-        Colors::WHITE.as_color(),
-    );
-
-    let mut draw_rect = true;
-    let mut draw_triangle = true;
-    let mut draw_ellipse = true;
-
-    loop {
-        canvas.start_frame();
-        input_manager.update();
-
-        canvas.clear(Colors::BLACK.as_color());
+struct Demo {
+    rect: Primitive::Rect,
+    triangle: Primitive::Triangle,
+    ellipse: Primitive::Ellipse,
+    sprite: Sprite,
+    draw_rect: bool,
+    draw_triangle: bool,
+    draw_ellipse: bool,
+}
 
-        if draw_triangle {
-            triangle.draw();
+impl Demo {
+    fn new() -> Self {
+        let mut rect = Primitive::Rect::new(
+            Vec3::new(240.0, 136.0, -1.0),
+            Vec2::new(240.0, 136.0),
+            Colors::BLUE.as_color(),
+        );
+        rect.set_gradient(Gradient::linear(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Colors::BLUE.as_color(),
+            Colors::CYAN.as_color(),
+        ));
+
+        let triangle = Primitive::Triangle::new(
+            [
+                Vec3::new(0.0, 272.0, -1.0),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::new(480.0, 0.0, -1.0),
+            ],
+            Colors::RED.as_color(),
+        );
+
+        let ellipse = Primitive::Ellipse::new(
+            Vec3::new(240.0, 136.0, -1.0),
+            Vec2::new(75.0, 50.0),
+            Colors::WHITE.as_color(),
+        );
+
+        let ferris =
+            Rc::new(Texture::from_static(&FERRIS.0, 128, 128, PixelFormat::Psm8888).unwrap());
+        let sprite = Sprite::new(
+            Vec3::new(217.5, 113.5, -1.0),
+            0.0,
+            Vec2::new(45.0, 45.0),
+            ferris,
+            Colors::WHITE.as_color(),
+        );
+
+        Self {
+            rect,
+            triangle,
+            ellipse,
+            sprite,
+            draw_rect: true,
+            draw_triangle: true,
+            draw_ellipse: true,
         }
-        if draw_rect {
-            rect.draw();
-        }
-        if draw_ellipse {
-            ellipse.draw();
+    }
+}
+
+impl Game<PspBackend> for Demo {
+    fn update(&mut self, backend: &mut PspBackend, _delta: f32) {
+        if backend.input.is_key_down_changed(Buttons::Cross) {
+            backend.exit();
         }
 
-        sprite.draw();
+        let input_manager = &mut backend.input;
+        let sprite = &mut self.sprite;
 
         if input_manager.is_key_down_changed(Buttons::Square) {
-            draw_rect = !draw_rect;
+            self.draw_rect = !self.draw_rect;
         }
         if input_manager.is_key_down_changed(Buttons::Triangle) {
-            draw_triangle = !draw_triangle;
+            self.draw_triangle = !self.draw_triangle;
         }
         if input_manager.is_key_down_changed(Buttons::Circle) {
-            draw_ellipse = !draw_ellipse;
-        }
-
-        if input_manager.is_key_down_changed(Buttons::Cross) {
-            break;
+            self.draw_ellipse = !self.draw_ellipse;
         }
 
         // Scale sprite
@@ -150,9 +160,25 @@ fn psp_main() {
             rot += 2.0;
             sprite.set_rot(rot);
         }
+    }
+
+    fn draw(&mut self, backend: &mut PspBackend) {
+        backend.canvas.clear(Colors::BLACK.as_color());
 
-        canvas.end_frame();
+        if self.draw_triangle {
+            self.triangle.draw();
+        }
+        if self.draw_rect {
+            self.rect.draw();
+        }
+        if self.draw_ellipse {
+            self.ellipse.draw();
+        }
+
+        self.sprite.draw();
     }
+}
 
-    canvas.terminate();
+fn psp_main() {
+    App::new().run(&mut Demo::new());
 }
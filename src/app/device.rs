@@ -0,0 +1,102 @@
+use psp::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use crate::app::{App, AppEvent, Backend};
+use crate::{
+    core::{input::InputManager, time::FrameTimer, Vec2, Vec3},
+    graphics::{camera::Camera2D, canvas::Canvas, colors::Color, primitives::Primitive, Drawable},
+    system::callbacks::{self, SystemEvent},
+};
+
+impl App<PspBackend> {
+    /// Returns an app drawing to a `Canvas::new`. Must be called only once, in the `psp_main` function.
+    pub fn new() -> Self {
+        Self::with_canvas(Canvas::new())
+    }
+
+    /// Returns an app drawing to `canvas`, such as one set up with a `CanvasBuilder`.
+    pub fn with_canvas(canvas: Canvas) -> Self {
+        Self::with_backend(PspBackend::new(canvas))
+    }
+}
+
+/// Runs a game on the PSP, its fields being free to use from the game's hooks.
+pub struct PspBackend {
+    pub canvas: Canvas,
+    pub input: InputManager,
+    pub timer: FrameTimer,
+    exiting: bool,
+}
+
+impl PspBackend {
+    /// Returns a backend drawing to `canvas`. Quitting from the HOME menu is handled by the game, see
+    /// `callbacks::set_handle_exit`, so that `Game::on_exit` is called first.
+    pub fn new(canvas: Canvas) -> Self {
+        callbacks::register();
+        callbacks::set_handle_exit(true);
+        Self {
+            canvas,
+            input: InputManager::new(),
+            timer: FrameTimer::new(),
+            exiting: false,
+        }
+    }
+}
+
+impl Backend for PspBackend {
+    fn start_frame(&mut self) -> f32 {
+        self.canvas.start_frame();
+        self.input.update();
+        self.timer.tick()
+    }
+
+    fn end_frame(&mut self) {
+        self.canvas.end_frame();
+    }
+
+    fn poll_event(&mut self) -> Option<AppEvent> {
+        callbacks::poll().map(|event| match event {
+            SystemEvent::Exit => AppEvent::Exit,
+            SystemEvent::Suspend => AppEvent::Suspend,
+            SystemEvent::Resume => AppEvent::Resume,
+            SystemEvent::BatteryLow => AppEvent::BatteryLow,
+        })
+    }
+
+    fn exit(&mut self) {
+        self.exiting = true;
+    }
+
+    fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    fn terminate(&mut self) {
+        self.canvas.terminate();
+    }
+
+    /// Draws over everything drawn so far, including the drawables submitted to the canvas, and without the
+    /// camera.
+    fn draw_fade(&mut self, opacity: f32) {
+        self.canvas.flush();
+        self.canvas.set_camera(None);
+        let mut cover = Primitive::Rect::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32),
+            Color::new(0, 0, 0, (opacity.clamp(0.0, 1.0) * 255.0) as u8),
+        );
+        cover.draw();
+    }
+
+    /// Moves the view with a camera, replacing the one set with `Canvas::set_camera`.
+    fn set_draw_offset(&mut self, offset: Vec2<f32>) {
+        if offset.x == 0.0 && offset.y == 0.0 {
+            self.canvas.set_camera(None);
+        } else {
+            let camera = Camera2D::new(Vec2::new(
+                SCREEN_WIDTH as f32 / 2.0 - offset.x,
+                SCREEN_HEIGHT as f32 / 2.0 - offset.y,
+            ));
+            self.canvas.set_camera(Some(&camera));
+        }
+    }
+}
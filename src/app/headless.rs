@@ -0,0 +1,229 @@
+extern crate alloc;
+use alloc::collections::VecDeque;
+
+use crate::app::{AppEvent, Backend};
+
+/// A backend without hardware, to run a game's logic on the host. Each frame takes the same `delta`
+/// seconds, and events are sent on the frames they were scheduled for.
+pub struct HeadlessBackend {
+    delta: f32,
+    frames: u64,
+    max_frames: Option<u64>,
+    events: VecDeque<(u64, AppEvent)>,
+    exiting: bool,
+    terminated: bool,
+}
+
+impl HeadlessBackend {
+    /// Returns a backend whose frames each take `delta` seconds, running until the game exits.
+    pub fn new(delta: f32) -> Self {
+        Self {
+            delta,
+            frames: 0,
+            max_frames: None,
+            events: VecDeque::new(),
+            exiting: false,
+            terminated: false,
+        }
+    }
+
+    /// Sends `AppEvent::Exit` once `frames` frames ran, `None` waiting for the game to exit.
+    pub fn set_max_frames(&mut self, frames: Option<u64>) {
+        self.max_frames = frames;
+    }
+
+    /// Schedules `event` to be sent before the frame `frame`, counting from `0`.
+    pub fn push_event(&mut self, frame: u64, event: AppEvent) {
+        let index = self
+            .events
+            .iter()
+            .position(|&(other, _)| other > frame)
+            .unwrap_or(self.events.len());
+        self.events.insert(index, (frame, event));
+    }
+
+    /// Returns the number of frames that ran.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn start_frame(&mut self) -> f32 {
+        self.delta
+    }
+
+    fn end_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    fn poll_event(&mut self) -> Option<AppEvent> {
+        match self.events.front() {
+            Some(&(frame, event)) if frame <= self.frames => {
+                self.events.pop_front();
+                Some(event)
+            }
+            _ if self.max_frames.is_some_and(|max| self.frames >= max) => {
+                // Sent only once, as a user quits only once.
+                self.max_frames = None;
+                Some(AppEvent::Exit)
+            }
+            _ => None,
+        }
+    }
+
+    fn exit(&mut self) {
+        self.exiting = true;
+    }
+
+    fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    fn terminate(&mut self) {
+        self.terminated = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{App, Game};
+    use alloc::vec::Vec;
+
+    /// Records the callbacks it gets, along with the frame they happened in.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<(u64, &'static str)>,
+        exit_on: Option<u64>,
+    }
+
+    impl Game<HeadlessBackend> for Recorder {
+        fn init(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "init"));
+        }
+
+        fn update(&mut self, backend: &mut HeadlessBackend, delta: f32) {
+            assert_eq!(delta, 0.5);
+            self.calls.push((backend.frames(), "update"));
+            if self.exit_on == Some(backend.frames()) {
+                backend.exit();
+            }
+        }
+
+        fn draw(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "draw"));
+        }
+
+        fn on_suspend(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "suspend"));
+        }
+
+        fn on_resume(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "resume"));
+        }
+
+        fn on_battery_low(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "battery_low"));
+        }
+
+        fn on_exit(&mut self, backend: &mut HeadlessBackend) {
+            self.calls.push((backend.frames(), "exit"));
+        }
+    }
+
+    /// Runs `game` on `backend`, returning the number of frames that ran.
+    fn run(backend: HeadlessBackend, game: &mut Recorder) -> u64 {
+        let mut app = App::with_backend(backend);
+        app.run(game);
+        assert!(app.backend().is_terminated());
+        app.backend().frames()
+    }
+
+    #[test]
+    fn max_frames() {
+        let mut backend = HeadlessBackend::new(0.5);
+        backend.set_max_frames(Some(2));
+        let mut game = Recorder::default();
+        assert_eq!(run(backend, &mut game), 2);
+        assert_eq!(
+            game.calls,
+            [
+                (0, "init"),
+                (0, "update"),
+                (0, "draw"),
+                (1, "update"),
+                (1, "draw"),
+                (2, "exit"),
+            ]
+        );
+
+        let mut backend = HeadlessBackend::new(0.5);
+        backend.set_max_frames(Some(0));
+        let mut game = Recorder::default();
+        assert_eq!(run(backend, &mut game), 0);
+        assert_eq!(game.calls, [(0, "init"), (0, "exit")]);
+    }
+
+    #[test]
+    fn scheduled_events() {
+        let mut backend = HeadlessBackend::new(0.5);
+        // Scheduled out of order, and suspending or resuming twice in a row only calls the game once.
+        backend.push_event(2, AppEvent::Resume);
+        backend.push_event(1, AppEvent::Suspend);
+        backend.push_event(0, AppEvent::Resume);
+        backend.push_event(1, AppEvent::Suspend);
+        backend.push_event(1, AppEvent::BatteryLow);
+        backend.push_event(2, AppEvent::Resume);
+        backend.push_event(3, AppEvent::Exit);
+        let mut game = Recorder::default();
+        assert_eq!(run(backend, &mut game), 3);
+
+        let callbacks: Vec<(u64, &str)> = game
+            .calls
+            .into_iter()
+            .filter(|&(_, call)| call != "update" && call != "draw")
+            .collect();
+        assert_eq!(
+            callbacks,
+            [
+                (0, "init"),
+                (1, "suspend"),
+                (1, "battery_low"),
+                (2, "resume"),
+                (3, "exit"),
+            ]
+        );
+    }
+
+    #[test]
+    fn events_come_before_the_frame() {
+        let mut backend = HeadlessBackend::new(0.5);
+        backend.push_event(1, AppEvent::Suspend);
+        backend.set_max_frames(Some(2));
+        let mut game = Recorder::default();
+        run(backend, &mut game);
+        assert_eq!(
+            game.calls[3..6],
+            [(1, "suspend"), (1, "update"), (1, "draw")]
+        );
+    }
+
+    #[test]
+    fn game_exits() {
+        let mut backend = HeadlessBackend::new(0.5);
+        backend.set_max_frames(Some(10));
+        let mut game = Recorder {
+            exit_on: Some(1),
+            ..Default::default()
+        };
+        // The frame the game exits in still ends.
+        assert_eq!(run(backend, &mut game), 2);
+        assert_eq!(game.calls.len(), 6);
+        assert_eq!(game.calls[4..], [(1, "draw"), (2, "exit")]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Application lifecycle: a `Game` is run by an `App`, which owns the main loop and calls the game's hooks
+//! each frame. The loop itself only talks to a `Backend`, so a game can be run headless against
+//! `HeadlessBackend` to test its logic without the hardware.
+
+#[cfg(feature = "graphics")]
+/// The `device` module runs games on the PSP itself.
+pub mod device;
+#[cfg(feature = "graphics")]
+pub use device::PspBackend;
+/// The `headless` module runs games without the hardware, to test them on the host.
+pub mod headless;
+pub use headless::HeadlessBackend;
+/// The `scene` module runs a stack of scenes, with transitions between them.
+pub mod scene;
+pub use scene::{Scene, SceneManager, SlideDirection, Transition, TransitionEffect};
+
+use crate::core::Vec2;
+
+/// Something that happened to the application outside of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppEvent {
+    /// The user chose to quit from the HOME menu.
+    Exit,
+    /// The PSP is about to go to sleep.
+    Suspend,
+    /// The PSP woke up.
+    Resume,
+    /// The battery just ran low.
+    BatteryLow,
+}
+
+/// A game run by `App::run`. Each frame, `Game::update` then `Game::draw` are called, given the backend
+/// to reach the screen, the buttons and the timer.
+pub trait Game<B: Backend> {
+    /// Called once, before the first frame.
+    fn init(&mut self, _backend: &mut B) {}
+
+    /// Advances the game by `delta` seconds.
+    fn update(&mut self, backend: &mut B, delta: f32);
+
+    fn draw(&mut self, backend: &mut B);
+
+    /// Called before the PSP goes to sleep, such as to pause the game or save it.
+    fn on_suspend(&mut self, _backend: &mut B) {}
+
+    fn on_resume(&mut self, _backend: &mut B) {}
+
+    /// Called when the battery runs low, such as to save the game.
+    fn on_battery_low(&mut self, _backend: &mut B) {}
+
+    /// Called once after the last frame, whether the game or the user quit.
+    fn on_exit(&mut self, _backend: &mut B) {}
+}
+
+/// What the main loop of `App` runs on.
+pub trait Backend {
+    /// Prepares a new frame, returning the seconds elapsed since the last one.
+    fn start_frame(&mut self) -> f32;
+
+    /// Shows the frame drawn since `Backend::start_frame`.
+    fn end_frame(&mut self);
+
+    /// Returns the next event that happened since the last call, if any.
+    fn poll_event(&mut self) -> Option<AppEvent>;
+
+    /// Asks the main loop to stop after the current frame.
+    fn exit(&mut self);
+
+    fn is_exiting(&self) -> bool;
+
+    /// Releases the backend once the main loop stopped.
+    fn terminate(&mut self);
+
+    /// Covers what was drawn with black at `opacity`, from `0.0` to `1.0`, for fading transitions. Does
+    /// nothing by default.
+    fn draw_fade(&mut self, _opacity: f32) {}
+
+    /// Moves what is drawn next by `offset` pixels, for sliding transitions. Does nothing by default.
+    fn set_draw_offset(&mut self, _offset: Vec2<f32>) {}
+}
+
+/// Runs a `Game` until it or the user quits.
+pub struct App<B: Backend> {
+    backend: B,
+}
+
+impl<B: Backend> App<B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self { backend }
+    }
+
+    pub fn backend(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Runs the main loop until `Backend::exit` is called or the user quits, then calls `Game::on_exit` and
+    /// terminates the backend. On the PSP, it never returns.
+    pub fn run<G: Game<B>>(&mut self, game: &mut G) {
+        let backend = &mut self.backend;
+        game.init(backend);
+
+        let mut suspended = false;
+        while !backend.is_exiting() {
+            while let Some(event) = backend.poll_event() {
+                match event {
+                    AppEvent::Exit => backend.exit(),
+                    AppEvent::Suspend if !suspended => {
+                        suspended = true;
+                        game.on_suspend(backend);
+                    }
+                    AppEvent::Resume if suspended => {
+                        suspended = false;
+                        game.on_resume(backend);
+                    }
+                    AppEvent::BatteryLow => game.on_battery_low(backend),
+                    _ => {}
+                }
+            }
+            if backend.is_exiting() {
+                break;
+            }
+
+            let delta = backend.start_frame();
+            game.update(backend, delta);
+            game.draw(backend);
+            backend.end_frame();
+        }
+
+        game.on_exit(backend);
+        backend.terminate();
+    }
+}
@@ -0,0 +1,563 @@
+//! A stack of scenes, such as a title screen, a menu, the gameplay and a pause overlay. Transition effects
+//! are drawn through `Backend`, so nothing here touches the hardware.
+
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::app::{Backend, Game};
+use crate::core::Vec2;
+
+/// Size of the screen slides move scenes across, in pixels.
+const SCREEN_SIZE: Vec2<f32> = Vec2 { x: 480.0, y: 272.0 };
+
+/// A screen of the game, updated while it is on top of a `SceneManager` and drawn while it isn't hidden
+/// by the scenes above it.
+pub trait Scene<B: Backend> {
+    /// Called when the scene is added to the stack.
+    fn on_enter(&mut self, _backend: &mut B) {}
+
+    /// Called when the scene is removed from the stack.
+    fn on_exit(&mut self, _backend: &mut B) {}
+
+    /// Called when a scene is pushed on top of this one.
+    fn on_pause(&mut self, _backend: &mut B) {}
+
+    /// Called when this scene is back on top of the stack.
+    fn on_resume(&mut self, _backend: &mut B) {}
+
+    /// Advances the scene by `delta` seconds, returning how the stack changes. Only the top scene is
+    /// updated, so only it reads the buttons.
+    fn update(&mut self, backend: &mut B, delta: f32) -> Transition<B>;
+
+    fn draw(&mut self, backend: &mut B);
+
+    /// Whether the scenes below are drawn too, such as under a pause menu. `false` by default.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// How the stack of a `SceneManager` changes after an update.
+pub enum Transition<B: Backend> {
+    /// Keeps the stack as is.
+    None,
+    /// Adds a scene on top of the current one, which is paused.
+    Push(Box<dyn Scene<B>>),
+    /// Removes the current scene, resuming the one below. Quits when it was the last one.
+    Pop,
+    /// Swaps the current scene for another one.
+    Replace(Box<dyn Scene<B>>),
+    /// Quits the game.
+    Quit,
+}
+
+/// Direction scenes move in during a slide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlideDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl SlideDirection {
+    fn as_vec(&self) -> Vec2<f32> {
+        match self {
+            SlideDirection::Left => Vec2::new(-1.0, 0.0),
+            SlideDirection::Right => Vec2::new(1.0, 0.0),
+            SlideDirection::Up => Vec2::new(0.0, -1.0),
+            SlideDirection::Down => Vec2::new(0.0, 1.0),
+        }
+    }
+}
+
+/// How a `SceneManager` shows the stack changing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransitionEffect {
+    /// Changes scenes at once.
+    None,
+    /// Fades to black, changing scenes once the screen is black, then fades back, over this many seconds.
+    Fade(f32),
+    /// Moves the scenes entering and leaving across the screen, over this many seconds.
+    Slide(f32, SlideDirection),
+}
+
+/// A transition being shown.
+struct Active<B: Backend> {
+    effect: TransitionEffect,
+    elapsed: f32,
+    duration: f32,
+    /// For fades, the change applied once the screen is black.
+    pending: Option<Transition<B>>,
+    /// For slides, the scene being removed, still drawn until it left the screen.
+    leaving: Option<Box<dyn Scene<B>>>,
+    /// For slides, whether the stack grew or shrank, the entering or the leaving scene being on top.
+    popped: bool,
+}
+
+/// Runs a stack of scenes, as a `Game` given to `App::run`. Scenes aren't updated while a transition is
+/// shown.
+pub struct SceneManager<B: Backend> {
+    scenes: Vec<Box<dyn Scene<B>>>,
+    effect: TransitionEffect,
+    active: Option<Active<B>>,
+    started: bool,
+}
+
+impl<B: Backend> SceneManager<B> {
+    /// Returns a manager starting on `scene`, entered when the game starts.
+    pub fn new(scene: Box<dyn Scene<B>>) -> Self {
+        Self {
+            scenes: alloc::vec![scene],
+            effect: TransitionEffect::None,
+            active: None,
+            started: false,
+        }
+    }
+
+    pub fn get_effect(&self) -> TransitionEffect {
+        self.effect
+    }
+
+    /// Sets how the following transitions are shown.
+    pub fn set_effect(&mut self, effect: TransitionEffect) {
+        self.effect = effect;
+    }
+
+    /// Returns the number of scenes in the stack.
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Returns whether a transition is being shown.
+    pub fn is_transitioning(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Changes the stack as if the top scene returned `transition`, such as to open a menu from outside of
+    /// the scenes. Ignored while another transition is shown.
+    pub fn transition(&mut self, backend: &mut B, transition: Transition<B>) {
+        if self.active.is_some() {
+            return;
+        }
+
+        match (transition, self.effect) {
+            (Transition::None, _) => {}
+            (Transition::Quit, _) => backend.exit(),
+            (transition, TransitionEffect::None) => {
+                self.apply(backend, transition);
+            }
+            (transition, TransitionEffect::Fade(duration)) if duration > 0.0 => {
+                self.active = Some(Active {
+                    effect: self.effect,
+                    elapsed: 0.0,
+                    duration,
+                    pending: Some(transition),
+                    leaving: None,
+                    popped: false,
+                });
+            }
+            (transition, TransitionEffect::Slide(duration, _)) if duration > 0.0 => {
+                let popped = matches!(transition, Transition::Pop);
+                let leaving = self.apply(backend, transition);
+                self.active = Some(Active {
+                    effect: self.effect,
+                    elapsed: 0.0,
+                    duration,
+                    pending: None,
+                    leaving,
+                    popped,
+                });
+            }
+            (transition, _) => {
+                self.apply(backend, transition);
+            }
+        }
+    }
+
+    /// Changes the stack right away, returning the scene removed from it, if any.
+    fn apply(&mut self, backend: &mut B, transition: Transition<B>) -> Option<Box<dyn Scene<B>>> {
+        match transition {
+            Transition::None => None,
+            Transition::Quit => {
+                backend.exit();
+                None
+            }
+            Transition::Push(mut scene) => {
+                if let Some(top) = self.scenes.last_mut() {
+                    top.on_pause(backend);
+                }
+                scene.on_enter(backend);
+                self.scenes.push(scene);
+                None
+            }
+            Transition::Pop => {
+                let mut scene = self.scenes.pop()?;
+                scene.on_exit(backend);
+                match self.scenes.last_mut() {
+                    Some(top) => top.on_resume(backend),
+                    None => backend.exit(),
+                }
+                Some(scene)
+            }
+            Transition::Replace(mut scene) => {
+                let mut old = self.scenes.pop();
+                if let Some(old) = &mut old {
+                    old.on_exit(backend);
+                }
+                scene.on_enter(backend);
+                self.scenes.push(scene);
+                old
+            }
+        }
+    }
+}
+
+impl<B: Backend> Active<B> {
+    /// Returns how far the transition is, from `0.0` to `1.0`.
+    fn progress(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Returns how far a scene sliding in `direction` moved once it went `amount` of the screen, eased in and
+/// out.
+fn slide_offset(direction: SlideDirection, amount: f32) -> Vec2<f32> {
+    let eased = amount * amount * (3.0 - 2.0 * amount);
+    let direction = direction.as_vec();
+    Vec2::new(
+        direction.x * SCREEN_SIZE.x * eased,
+        direction.y * SCREEN_SIZE.y * eased,
+    )
+}
+
+impl<B: Backend> Game<B> for SceneManager<B> {
+    fn init(&mut self, backend: &mut B) {
+        if !self.started {
+            self.started = true;
+            for scene in &mut self.scenes {
+                scene.on_enter(backend);
+            }
+        }
+    }
+
+    fn update(&mut self, backend: &mut B, delta: f32) {
+        if let Some(active) = &mut self.active {
+            active.elapsed += delta;
+            let halfway = active.elapsed >= active.duration / 2.0;
+            let done = active.elapsed >= active.duration;
+            if halfway {
+                if let Some(transition) = active.pending.take() {
+                    self.apply(backend, transition);
+                }
+            }
+            if done {
+                self.active = None;
+            }
+            return;
+        }
+
+        let transition = match self.scenes.last_mut() {
+            Some(top) => top.update(backend, delta),
+            None => return,
+        };
+        self.transition(backend, transition);
+    }
+
+    fn draw(&mut self, backend: &mut B) {
+        let top = self.scenes.len().saturating_sub(1);
+        // A scene sliding in doesn't hide the ones below it yet.
+        let sliding_in = matches!(
+            &self.active,
+            Some(Active {
+                effect: TransitionEffect::Slide(..),
+                popped: false,
+                ..
+            })
+        );
+        let hiding = if sliding_in {
+            &self.scenes[..top]
+        } else {
+            &self.scenes[..]
+        };
+        // Drawn from the highest scene that hides the ones below it.
+        let first = hiding
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+
+        for (index, scene) in self.scenes.iter_mut().enumerate().skip(first) {
+            if let Some(active) = &mut self.active {
+                if let TransitionEffect::Slide(_, direction) = active.effect {
+                    if index == top && !active.popped {
+                        // The replaced scene leaves from under the one entering.
+                        let progress = active.progress();
+                        if let Some(leaving) = &mut active.leaving {
+                            backend.set_draw_offset(slide_offset(direction, progress));
+                            leaving.draw(backend);
+                        }
+                        let entering = slide_offset(direction, 1.0 - progress);
+                        backend.set_draw_offset(Vec2::new(-entering.x, -entering.y));
+                        scene.draw(backend);
+                        backend.set_draw_offset(Vec2::new(0.0, 0.0));
+                        continue;
+                    }
+                }
+            }
+            scene.draw(backend);
+        }
+
+        if let Some(active) = &mut self.active {
+            let progress = active.progress();
+            match active.effect {
+                TransitionEffect::Slide(_, direction) if active.popped => {
+                    // The popped scene leaves from over the one below.
+                    if let Some(leaving) = &mut active.leaving {
+                        backend.set_draw_offset(slide_offset(direction, progress));
+                        leaving.draw(backend);
+                        backend.set_draw_offset(Vec2::new(0.0, 0.0));
+                    }
+                }
+                TransitionEffect::Fade(_) => {
+                    let opacity = if progress < 0.5 {
+                        progress * 2.0
+                    } else {
+                        (1.0 - progress) * 2.0
+                    };
+                    backend.draw_fade(opacity);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_exit(&mut self, backend: &mut B) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit(backend);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::HeadlessBackend;
+    use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
+    use core::cell::RefCell;
+
+    type Log = Rc<RefCell<Vec<(&'static str, &'static str)>>>;
+
+    /// A scene recording its callbacks, returning the transitions of `script` from its updates.
+    struct Probe {
+        name: &'static str,
+        log: Log,
+        overlay: bool,
+        script: VecDeque<Transition<HeadlessBackend>>,
+    }
+
+    impl Probe {
+        fn record(&self, call: &'static str) {
+            self.log.borrow_mut().push((self.name, call));
+        }
+    }
+
+    impl Scene<HeadlessBackend> for Probe {
+        fn on_enter(&mut self, _backend: &mut HeadlessBackend) {
+            self.record("enter");
+        }
+
+        fn on_exit(&mut self, _backend: &mut HeadlessBackend) {
+            self.record("exit");
+        }
+
+        fn on_pause(&mut self, _backend: &mut HeadlessBackend) {
+            self.record("pause");
+        }
+
+        fn on_resume(&mut self, _backend: &mut HeadlessBackend) {
+            self.record("resume");
+        }
+
+        fn update(
+            &mut self,
+            _backend: &mut HeadlessBackend,
+            _delta: f32,
+        ) -> Transition<HeadlessBackend> {
+            self.record("update");
+            self.script.pop_front().unwrap_or(Transition::None)
+        }
+
+        fn draw(&mut self, _backend: &mut HeadlessBackend) {
+            self.record("draw");
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    fn probe(name: &'static str, log: &Log) -> Box<Probe> {
+        Box::new(Probe {
+            name,
+            log: log.clone(),
+            overlay: false,
+            script: VecDeque::new(),
+        })
+    }
+
+    fn overlay(name: &'static str, log: &Log) -> Box<Probe> {
+        let mut scene = probe(name, log);
+        scene.overlay = true;
+        scene
+    }
+
+    /// Returns the calls recorded since the last time.
+    fn take(log: &Log) -> Vec<(&'static str, &'static str)> {
+        core::mem::take(&mut *log.borrow_mut())
+    }
+
+    /// Returns the scenes drawn, from the bottom.
+    fn drawn(
+        manager: &mut SceneManager<HeadlessBackend>,
+        backend: &mut HeadlessBackend,
+        log: &Log,
+    ) -> Vec<&'static str> {
+        take(log);
+        manager.draw(backend);
+        take(log).into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn stack_changes() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut manager = SceneManager::new(overlay("a", &log));
+        assert!(take(&log).is_empty());
+        manager.init(&mut backend);
+        manager.init(&mut backend);
+        assert_eq!(take(&log), [("a", "enter")]);
+
+        manager.transition(&mut backend, Transition::Push(overlay("b", &log)));
+        assert_eq!(take(&log), [("a", "pause"), ("b", "enter")]);
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["a", "b"]);
+
+        manager.transition(&mut backend, Transition::Replace(overlay("c", &log)));
+        assert_eq!(take(&log), [("b", "exit"), ("c", "enter")]);
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["a", "c"]);
+
+        manager.transition(&mut backend, Transition::None);
+        assert!(take(&log).is_empty());
+        assert_eq!(manager.len(), 2);
+
+        manager.transition(&mut backend, Transition::Pop);
+        assert_eq!(take(&log), [("c", "exit"), ("a", "resume")]);
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["a"]);
+        assert!(!backend.is_exiting());
+    }
+
+    #[test]
+    fn popping_the_last_scene_quits() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut manager = SceneManager::new(probe("a", &log));
+        manager.init(&mut backend);
+        take(&log);
+
+        manager.transition(&mut backend, Transition::Pop);
+        assert_eq!(take(&log), [("a", "exit")]);
+        assert!(manager.is_empty());
+        assert!(backend.is_exiting());
+
+        // Nothing is left to update, draw or exit.
+        manager.update(&mut backend, 0.1);
+        manager.draw(&mut backend);
+        manager.on_exit(&mut backend);
+        manager.transition(&mut backend, Transition::Pop);
+        assert!(take(&log).is_empty());
+    }
+
+    #[test]
+    fn quit_exits_every_scene() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut manager = SceneManager::new(probe("a", &log));
+        manager.init(&mut backend);
+        manager.transition(&mut backend, Transition::Push(probe("b", &log)));
+        take(&log);
+
+        manager.transition(&mut backend, Transition::Quit);
+        assert!(backend.is_exiting());
+        assert_eq!(manager.len(), 2);
+        manager.on_exit(&mut backend);
+        assert_eq!(take(&log), [("b", "exit"), ("a", "exit")]);
+    }
+
+    #[test]
+    fn top_scene_drives_the_stack() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut a = probe("a", &log);
+        a.script.push_back(Transition::Push(probe("b", &log)));
+        let mut manager = SceneManager::new(a);
+        manager.init(&mut backend);
+        take(&log);
+
+        manager.update(&mut backend, 0.1);
+        assert_eq!(
+            take(&log),
+            [("a", "update"), ("a", "pause"), ("b", "enter")]
+        );
+        // Only the top scene is updated, and it hides the one below.
+        manager.update(&mut backend, 0.1);
+        assert_eq!(take(&log), [("b", "update")]);
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["b"]);
+    }
+
+    #[test]
+    fn fade_changes_scenes_halfway() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut manager = SceneManager::new(probe("a", &log));
+        manager.init(&mut backend);
+        manager.set_effect(TransitionEffect::Fade(1.0));
+        take(&log);
+
+        manager.transition(&mut backend, Transition::Push(probe("b", &log)));
+        // Ignored while the fade is shown.
+        manager.transition(&mut backend, Transition::Push(probe("c", &log)));
+        assert!(manager.is_transitioning());
+        manager.update(&mut backend, 0.25);
+        assert_eq!(manager.len(), 1);
+        manager.update(&mut backend, 0.25);
+        assert_eq!(take(&log), [("a", "pause"), ("b", "enter")]);
+        assert_eq!(manager.len(), 2);
+
+        manager.update(&mut backend, 0.5);
+        assert!(!manager.is_transitioning());
+        assert!(take(&log).is_empty());
+    }
+
+    #[test]
+    fn slide_draws_the_leaving_scene() {
+        let log = Log::default();
+        let mut backend = HeadlessBackend::new(0.1);
+        let mut manager = SceneManager::new(probe("a", &log));
+        manager.init(&mut backend);
+        manager.transition(&mut backend, Transition::Push(probe("b", &log)));
+        manager.set_effect(TransitionEffect::Slide(1.0, SlideDirection::Left));
+        take(&log);
+
+        manager.transition(&mut backend, Transition::Pop);
+        assert_eq!(take(&log), [("b", "exit"), ("a", "resume")]);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["a", "b"]);
+
+        manager.update(&mut backend, 1.0);
+        assert!(!manager.is_transitioning());
+        assert_eq!(drawn(&mut manager, &mut backend, &log), ["a"]);
+    }
+}
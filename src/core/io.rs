@@ -1,4 +1,14 @@
-use psp::sys::{sceIoChdir, sceIoOpen, sceIoRead, IoOpenFlags};
+extern crate alloc;
+use alloc::vec::Vec;
+
+use psp::sys::{
+    sceIoChdir, sceIoClose, sceIoLseek, sceIoMkdir, sceIoOpen, sceIoRead, sceIoRemove, sceIoWrite,
+    IoOpenFlags, IoPermissions, IoWhence,
+};
+
+/// Error code returned by the PSP's IO functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoError(pub i32);
 
 pub struct File {}
 
@@ -10,4 +20,92 @@ impl File {
             sceIoRead(file, buf.as_mut_ptr() as *mut _, buf.len() as u32);
         }
     }
+
+    /// Reads a whole file, such as `ms0:/PSP/GAME/MYGAME/ferris.png` or a path relative to the EBOOT.
+    pub fn read_to_end(path: &str) -> Result<Vec<u8>, IoError> {
+        let path = c_path(path);
+        unsafe {
+            let file = sceIoOpen(path.as_ptr(), IoOpenFlags::RD_ONLY, 0o777);
+            if file.0 < 0 {
+                return Err(IoError(file.0));
+            }
+
+            let size = sceIoLseek(file, 0, IoWhence::End);
+            sceIoLseek(file, 0, IoWhence::Set);
+            if size < 0 {
+                sceIoClose(file);
+                return Err(IoError(size as i32));
+            }
+
+            let mut data = alloc::vec![0; size as usize];
+            let read = sceIoRead(file, data.as_mut_ptr() as *mut _, data.len() as u32);
+            sceIoClose(file);
+            if read < 0 {
+                return Err(IoError(read));
+            }
+            data.truncate(read as usize);
+            Ok(data)
+        }
+    }
+
+    /// Writes `data` to a file, creating it or replacing what it held.
+    pub fn write(path: &str, data: &[u8]) -> Result<(), IoError> {
+        let path = c_path(path);
+        unsafe {
+            let file = sceIoOpen(
+                path.as_ptr(),
+                IoOpenFlags::WR_ONLY | IoOpenFlags::CREAT | IoOpenFlags::TRUNC,
+                0o777,
+            );
+            if file.0 < 0 {
+                return Err(IoError(file.0));
+            }
+
+            let mut written = 0;
+            while written < data.len() {
+                let result = sceIoWrite(
+                    file,
+                    data[written..].as_ptr() as *const _,
+                    data.len() - written,
+                );
+                if result <= 0 {
+                    sceIoClose(file);
+                    return Err(IoError(result));
+                }
+                written += result as usize;
+            }
+            sceIoClose(file);
+            Ok(())
+        }
+    }
+
+    /// Deletes a file.
+    pub fn remove(path: &str) -> Result<(), IoError> {
+        let path = c_path(path);
+        let result = unsafe { sceIoRemove(path.as_ptr()) };
+        if result < 0 {
+            Err(IoError(result))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Creates a directory, failing if it already exists.
+    pub fn create_dir(path: &str) -> Result<(), IoError> {
+        let path = c_path(path);
+        let result = unsafe { sceIoMkdir(path.as_ptr(), IoPermissions::from_bits_truncate(0o777)) };
+        if result < 0 {
+            Err(IoError(result))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns `path` as the NUL-terminated string the PSP's IO functions expect.
+pub(crate) fn c_path(path: &str) -> Vec<u8> {
+    let mut c_path = Vec::with_capacity(path.len() + 1);
+    c_path.extend_from_slice(path.as_bytes());
+    c_path.push(0);
+    c_path
 }
@@ -0,0 +1,421 @@
+//! A small JSON parser, enough to read the data files exported by tools such as TexturePacker and Aseprite.
+//! Nothing here touches the hardware, so it runs the same on the PSP and on the host.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+/// Deepest nesting of arrays and objects accepted, to avoid exhausting the stack on malformed files.
+const MAX_DEPTH: usize = 64;
+
+/// Errors that may happen when parsing JSON, with the byte offset where it was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonError {
+    /// The text ends before the value does.
+    UnexpectedEnd,
+    /// A character that can't appear there.
+    UnexpectedChar(usize),
+    /// A number that can't be represented.
+    InvalidNumber(usize),
+    /// A string escape that isn't valid.
+    InvalidEscape(usize),
+    /// Arrays and objects are nested deeper than supported.
+    TooDeep(usize),
+}
+
+/// A parsed JSON value. Object members keep the order they were written in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the member called `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a whole number that fits in a `u32`.
+    pub fn as_u32(&self) -> Option<u32> {
+        let value = self.as_f64()?;
+        if value >= 0.0 && value <= u32::MAX as f64 && value as u32 as f64 == value {
+            Some(value as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document.
+pub fn parse(text: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        position: 0,
+    };
+    // Skips the byte order mark some editors write.
+    if parser.bytes.starts_with("\u{feff}".as_bytes()) {
+        parser.position = 3;
+    }
+
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < parser.bytes.len() {
+        return Err(JsonError::UnexpectedChar(parser.position));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, JsonError> {
+        let byte = self.peek().ok_or(JsonError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.next()? == byte {
+            Ok(())
+        } else {
+            Err(JsonError::UnexpectedChar(self.position - 1))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(JsonError::TooDeep(self.position));
+        }
+        self.skip_whitespace();
+        match self.peek().ok_or(JsonError::UnexpectedEnd)? {
+            b'{' => self.object(depth),
+            b'[' => self.array(depth),
+            b'"' => self.string().map(Value::String),
+            b't' => self.literal("true", Value::Bool(true)),
+            b'f' => self.literal("false", Value::Bool(false)),
+            b'n' => self.literal("null", Value::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(JsonError::UnexpectedChar(self.position)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        for &byte in word.as_bytes() {
+            self.expect(byte)?;
+        }
+        Ok(value)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            members.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b'}' => return Ok(Value::Object(members)),
+                _ => return Err(JsonError::UnexpectedChar(self.position - 1)),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b']' => return Ok(Value::Array(values)),
+                _ => return Err(JsonError::UnexpectedChar(self.position - 1)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut string = String::new();
+        loop {
+            // Copies everything up to the next quote or escape at once.
+            let start = self.position;
+            while !matches!(self.peek(), Some(b'"' | b'\\') | None) {
+                if self.bytes[self.position] < 0x20 {
+                    return Err(JsonError::UnexpectedChar(self.position));
+                }
+                self.position += 1;
+            }
+            // The input is a `str` and quotes and backslashes are ASCII, so this is valid UTF-8.
+            string.push_str(core::str::from_utf8(&self.bytes[start..self.position]).unwrap());
+
+            match self.next()? {
+                b'"' => return Ok(string),
+                _ => {
+                    let escape = self.position - 1;
+                    let character = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape(escape)?,
+                        _ => return Err(JsonError::InvalidEscape(escape)),
+                    };
+                    string.push(character);
+                }
+            }
+        }
+    }
+
+    /// Reads the digits of a `\u` escape, combining UTF-16 surrogate pairs.
+    fn unicode_escape(&mut self, escape: usize) -> Result<char, JsonError> {
+        let high = self.hex4(escape)?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.next()? != b'\\' || self.next()? != b'u' {
+                return Err(JsonError::InvalidEscape(escape));
+            }
+            let low = self.hex4(escape)?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(JsonError::InvalidEscape(escape));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or(JsonError::InvalidEscape(escape))
+    }
+
+    fn hex4(&mut self, escape: usize) -> Result<u32, JsonError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = (self.next()? as char)
+                .to_digit(16)
+                .ok_or(JsonError::InvalidEscape(escape))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.position;
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        let digits = |parser: &mut Self| -> usize {
+            let start = parser.position;
+            while matches!(parser.peek(), Some(b'0'..=b'9')) {
+                parser.position += 1;
+            }
+            parser.position - start
+        };
+
+        let integer = self.position;
+        let count = digits(self);
+        // No leading zeros, such as in `012`.
+        if count == 0 || (count > 1 && self.bytes[integer] == b'0') {
+            return Err(JsonError::InvalidNumber(start));
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if digits(self) == 0 {
+                return Err(JsonError::InvalidNumber(start));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if digits(self) == 0 {
+                return Err(JsonError::InvalidNumber(start));
+            }
+        }
+
+        core::str::from_utf8(&self.bytes[start..self.position])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .map(Value::Number)
+            .ok_or(JsonError::InvalidNumber(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Value {
+        Value::String(String::from(text))
+    }
+
+    #[test]
+    fn values() {
+        let value = parse(" {\"a\": [1, true, null, \"x\"], \"b\": {}, \"c\": []}\n").unwrap();
+        assert_eq!(
+            value.get("a").unwrap().as_array().unwrap(),
+            [
+                Value::Number(1.0),
+                Value::Bool(true),
+                Value::Null,
+                string("x")
+            ]
+        );
+        assert_eq!(value.get("b"), Some(&Value::Object(Vec::new())));
+        assert_eq!(value.get("c"), Some(&Value::Array(Vec::new())));
+        assert_eq!(value.get("d"), None);
+        // Members keep their order.
+        let names: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        assert_eq!(parse("\u{feff}false"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse(r#""\"\\\/\b\f\n\r\t""#),
+            Ok(string("\"\\/\u{8}\u{c}\n\r\t"))
+        );
+        assert_eq!(parse(r#""caf\u00e9 é""#), Ok(string("café é")));
+        // A surrogate pair, encoding a character outside the basic plane.
+        assert_eq!(parse(r#""\ud83d\ude00""#), Ok(string("\u{1f600}")));
+        assert_eq!(parse(r#""\u00FC""#), Ok(string("\u{fc}")));
+
+        assert_eq!(parse(r#""\x""#), Err(JsonError::InvalidEscape(1)));
+        assert_eq!(parse(r#""a\u12g4""#), Err(JsonError::InvalidEscape(2)));
+        // Lone or reversed surrogates.
+        assert_eq!(parse(r#""\ud83d""#), Err(JsonError::InvalidEscape(1)));
+        assert_eq!(parse(r#""\ud83d\u0041""#), Err(JsonError::InvalidEscape(1)));
+        assert_eq!(parse(r#""\ude00""#), Err(JsonError::InvalidEscape(1)));
+        // Control characters must be escaped.
+        assert_eq!(parse("\"a\nb\""), Err(JsonError::UnexpectedChar(2)));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse("0"), Ok(Value::Number(0.0)));
+        assert_eq!(parse("-12.5"), Ok(Value::Number(-12.5)));
+        assert_eq!(parse("1e3"), Ok(Value::Number(1000.0)));
+        assert_eq!(parse("2.5E-2"), Ok(Value::Number(0.025)));
+        assert_eq!(parse("-4e+2"), Ok(Value::Number(-400.0)));
+
+        assert_eq!(parse("012"), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(parse("-"), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(parse("1."), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(parse("[1e]"), Err(JsonError::InvalidNumber(1)));
+        assert_eq!(parse("1e+"), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(parse("+1"), Err(JsonError::UnexpectedChar(0)));
+    }
+
+    #[test]
+    fn whole_numbers() {
+        assert_eq!(Value::Number(3.0).as_u32(), Some(3));
+        assert_eq!(Value::Number(3.5).as_u32(), None);
+        assert_eq!(Value::Number(-1.0).as_u32(), None);
+        assert_eq!(Value::Number(5e9).as_u32(), None);
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        assert_eq!(parse("{} x"), Err(JsonError::UnexpectedChar(3)));
+        assert_eq!(parse("1 2"), Err(JsonError::UnexpectedChar(2)));
+        assert_eq!(parse("[1,]"), Err(JsonError::UnexpectedChar(3)));
+        assert_eq!(parse("{\"a\" 1}"), Err(JsonError::UnexpectedChar(5)));
+        assert_eq!(parse("tru"), Err(JsonError::UnexpectedEnd));
+        assert_eq!(parse("trap"), Err(JsonError::UnexpectedChar(2)));
+    }
+
+    #[test]
+    fn unterminated() {
+        for text in ["", "  ", "[1, 2", "{\"a\": 1", "{\"a\"", "\"abc", "[\"a\\"] {
+            assert_eq!(parse(text), Err(JsonError::UnexpectedEnd), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn too_deep() {
+        let nested = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(parse(&nested).is_ok());
+        let nested = "[".repeat(MAX_DEPTH + 2) + &"]".repeat(MAX_DEPTH + 2);
+        assert_eq!(parse(&nested), Err(JsonError::TooDeep(MAX_DEPTH + 1)));
+    }
+}
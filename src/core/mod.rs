@@ -5,9 +5,14 @@
 pub mod input;
 pub use input::{Buttons, InputManager};
 
-// The `io` module is a wrapper for the PSP's File Input and Output
-//pub mod io;
+/// The `io` module is a wrapper for the PSP's File Input and Output
+pub mod io;
 //pub mod threads;
+/// The `json` module parses the JSON data files exported by tools such as TexturePacker and Aseprite.
+pub mod json;
+/// The `time` module measures elapsed time and frame times, and paces fixed-timestep updates.
+pub mod time;
+pub use time::{Clock, FrameStats, FrameTimer};
 /// The `utils` module is a set of different functions that serve multiple purposes in the SPSPF project.
 pub mod utils;
 
@@ -36,4 +41,4 @@ impl<T> Vec2<T> {
     pub fn new(x: T, y: T) -> Vec2<T> {
         Vec2 { x, y }
     }
-}
\ No newline at end of file
+}
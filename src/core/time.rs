@@ -0,0 +1,307 @@
+use psp::sys::{sceRtcGetCurrentTick, sceRtcGetTickResolution};
+
+/// Number of frames `FrameStats` are computed over.
+pub const STATS_FRAMES: usize = 60;
+
+/// Longest delta time `FrameTimer::tick` reports by default, in seconds, so a long pause such as loading a
+/// level or the HOME menu doesn't make the game jump ahead.
+pub const DEFAULT_MAX_DELTA: f32 = 0.25;
+
+/// How much of each new frame time goes into the smoothed one, the rest being the previous value.
+const SMOOTHING: f32 = 0.1;
+
+/// Returns the current tick of the real-time clock.
+fn now() -> u64 {
+    let mut tick = 0;
+    unsafe { sceRtcGetCurrentTick(&mut tick) };
+    tick
+}
+
+/// Returns the number of ticks of the real-time clock in a second.
+fn resolution() -> u64 {
+    unsafe { sceRtcGetTickResolution() as u64 }
+}
+
+/// Measures the time elapsed since it was started.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    resolution: u64,
+    start: u64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            resolution: resolution(),
+            start: now(),
+        }
+    }
+
+    /// Returns the seconds elapsed since the clock was started.
+    pub fn elapsed(&self) -> f32 {
+        now().saturating_sub(self.start) as f32 / self.resolution as f32
+    }
+
+    /// Starts the clock over, returning the seconds elapsed until then.
+    pub fn restart(&mut self) -> f32 {
+        let tick = now();
+        let elapsed = tick.saturating_sub(self.start) as f32 / self.resolution as f32;
+        self.start = tick;
+        elapsed
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frame times over the last `STATS_FRAMES` frames, in seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub average: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Measures the time each frame takes, to be ticked once per frame. It also runs fixed-timestep updates,
+/// which keep physics stable whatever the frame rate: after `FrameTimer::tick`, the game steps its world
+/// by `FrameTimer::get_fixed_step` seconds while `FrameTimer::fixed_update` returns `true`, then draws it
+/// interpolated by `FrameTimer::alpha`.
+pub struct FrameTimer {
+    resolution: u64,
+    last_tick: u64,
+    delta: f32,
+    max_delta: f32,
+    smoothed: f32,
+    history: [f32; STATS_FRAMES],
+    frames: u64,
+    fixed_step: f32,
+    accumulator: f32,
+}
+
+impl FrameTimer {
+    /// Returns a timer whose first frame starts now, running fixed updates 60 times a second.
+    pub fn new() -> Self {
+        Self::starting_at(resolution(), now())
+    }
+
+    /// Returns a timer whose first frame starts at `tick`, for a clock ticking `resolution` times a second.
+    fn starting_at(resolution: u64, tick: u64) -> Self {
+        Self {
+            resolution,
+            last_tick: tick,
+            delta: 0.0,
+            max_delta: DEFAULT_MAX_DELTA,
+            smoothed: 0.0,
+            history: [0.0; STATS_FRAMES],
+            frames: 0,
+            fixed_step: 1.0 / 60.0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Ends the current frame and starts the next one, returning the seconds the frame took. Must be called
+    /// once per frame, usually first thing in the main loop.
+    pub fn tick(&mut self) -> f32 {
+        let tick = now();
+        let elapsed = tick.saturating_sub(self.last_tick) as f32 / self.resolution as f32;
+        self.last_tick = tick;
+        self.advance(elapsed)
+    }
+
+    /// Records a frame that took `elapsed` seconds.
+    fn advance(&mut self, elapsed: f32) -> f32 {
+        // Statistics show the real frame times, only the delta given to the game is capped.
+        self.smoothed = if self.frames == 0 {
+            elapsed
+        } else {
+            self.smoothed + (elapsed - self.smoothed) * SMOOTHING
+        };
+        self.history[(self.frames % STATS_FRAMES as u64) as usize] = elapsed;
+        self.frames += 1;
+
+        self.delta = elapsed.min(self.max_delta);
+        self.accumulator += self.delta;
+        self.delta
+    }
+
+    /// Returns the seconds the last frame took, capped to `FrameTimer::get_max_delta`.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Returns the frames per second, smoothed over the last frames so it is readable when shown.
+    pub fn fps(&self) -> f32 {
+        if self.smoothed > 0.0 {
+            1.0 / self.smoothed
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the number of frames ticked since the timer was created.
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    /// Returns the average, shortest and longest frame times of the last `STATS_FRAMES` frames.
+    pub fn stats(&self) -> FrameStats {
+        let count = (self.frames as usize).min(STATS_FRAMES);
+        if count == 0 {
+            return FrameStats::default();
+        }
+        let times = &self.history[..count];
+        FrameStats {
+            average: times.iter().sum::<f32>() / count as f32,
+            min: times.iter().fold(f32::MAX, |min, &time| min.min(time)),
+            max: times.iter().fold(0.0, |max: f32, &time| max.max(time)),
+        }
+    }
+
+    pub fn get_max_delta(&self) -> f32 {
+        self.max_delta
+    }
+
+    pub fn set_max_delta(&mut self, max_delta: f32) {
+        self.max_delta = max_delta.max(0.0);
+    }
+
+    pub fn get_fixed_step(&self) -> f32 {
+        self.fixed_step
+    }
+
+    /// Sets the seconds each fixed update simulates, such as `1.0 / 30.0`.
+    pub fn set_fixed_step(&mut self, step: f32) {
+        if step > 0.0 {
+            self.fixed_step = step;
+        }
+    }
+
+    /// Returns whether a fixed update is due, consuming it. Called in a loop after `FrameTimer::tick`, it runs
+    /// as many fixed updates as fit in the time elapsed, the remainder carrying over to the next frame.
+    pub fn fixed_update(&mut self) -> bool {
+        if self.accumulator >= self.fixed_step {
+            self.accumulator -= self.fixed_step;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns how far between the last two fixed updates the current frame is, from `0.0` to `1.0`, to
+    /// interpolate what is drawn between the last two simulated states.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.fixed_step).min(1.0)
+    }
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer() -> FrameTimer {
+        FrameTimer::starting_at(1_000_000, 0)
+    }
+
+    /// Runs the fixed updates due, returning how many there were.
+    fn fixed_updates(timer: &mut FrameTimer) -> usize {
+        core::iter::from_fn(|| timer.fixed_update().then_some(())).count()
+    }
+
+    #[test]
+    fn fixed_updates_per_tick() {
+        let mut timer = timer();
+        timer.set_fixed_step(0.25);
+        timer.set_max_delta(1.0);
+
+        assert_eq!(timer.advance(0.5), 0.5);
+        assert_eq!(fixed_updates(&mut timer), 2);
+        assert_eq!(timer.alpha(), 0.0);
+
+        // The remainder carries over to the next frame.
+        timer.advance(0.375);
+        assert_eq!(fixed_updates(&mut timer), 1);
+        assert_eq!(timer.alpha(), 0.5);
+        timer.advance(0.125);
+        assert_eq!(fixed_updates(&mut timer), 1);
+        assert_eq!(timer.alpha(), 0.0);
+
+        timer.advance(0.125);
+        assert_eq!(fixed_updates(&mut timer), 0);
+        assert_eq!(timer.alpha(), 0.5);
+    }
+
+    #[test]
+    fn max_delta() {
+        let mut timer = timer();
+        timer.set_fixed_step(0.0625);
+        assert_eq!(timer.advance(2.0), DEFAULT_MAX_DELTA);
+        assert_eq!(timer.delta(), DEFAULT_MAX_DELTA);
+        assert_eq!(fixed_updates(&mut timer), 4);
+        // Statistics still show how long the frame really took.
+        assert_eq!(timer.stats().max, 2.0);
+
+        timer.set_max_delta(-1.0);
+        assert_eq!(timer.advance(0.5), 0.0);
+        assert_eq!(fixed_updates(&mut timer), 0);
+
+        // Steps that can't be simulated are ignored.
+        timer.set_fixed_step(0.0);
+        assert_eq!(timer.get_fixed_step(), 0.0625);
+    }
+
+    #[test]
+    fn alpha_within_a_step() {
+        let mut timer = timer();
+        for frame in 0..1000 {
+            timer.advance(0.001 + (frame % 37) as f32 * 0.0013);
+            fixed_updates(&mut timer);
+            let alpha = timer.alpha();
+            assert!((0.0..1.0).contains(&alpha), "{}", alpha);
+        }
+    }
+
+    #[test]
+    fn fps_smoothing() {
+        let mut timer = timer();
+        timer.advance(0.5);
+        assert_eq!(timer.fps(), 2.0);
+
+        // A single slow or fast frame barely changes it.
+        timer.advance(0.25);
+        assert!((timer.fps() - 1.0 / 0.475).abs() < 1e-3);
+
+        for _ in 0..200 {
+            timer.advance(1.0 / 60.0);
+        }
+        assert!((timer.fps() - 60.0).abs() < 0.01, "{}", timer.fps());
+        // A 10 fps frame only takes a tenth of the way there.
+        timer.advance(0.1);
+        assert!((timer.fps() - 1.0 / 0.025).abs() < 0.01, "{}", timer.fps());
+    }
+
+    #[test]
+    fn stats_over_last_frames() {
+        let mut timer = timer();
+        assert_eq!(timer.stats(), FrameStats::default());
+        timer.advance(0.5);
+        for _ in 0..STATS_FRAMES - 1 {
+            timer.advance(0.25);
+        }
+        assert_eq!(timer.stats().max, 0.5);
+
+        // The first frame is now out of the window.
+        timer.advance(0.125);
+        let stats = timer.stats();
+        assert_eq!((stats.min, stats.max), (0.125, 0.25));
+        assert_eq!(timer.frame_count(), STATS_FRAMES as u64 + 1);
+    }
+}
@@ -1,52 +1,335 @@
 extern crate alloc;
-use core::ops::Range;
+use core::{ops::Range, ptr::addr_of_mut};
 
-use alloc::{borrow::ToOwned, string::String};
-use psp::sys::{
-    sceIoWrite, sceKernelStdout, sceKernelUtilsMt19937Init, sceKernelUtilsMt19937UInt,
-    sceRtcGetCurrentClockLocalTime, SceKernelUtilsMt19937Context, ScePspDateTime,
-};
+use alloc::string::String;
+use psp::sys::{sceIoWrite, sceKernelStdout, sceRtcGetCurrentTick};
+
+/// Most bytes of `stdout` output kept for a debug console, older ones being dropped first.
+const MIRROR_CAPACITY: usize = 4096;
+
+/// Output of `stdout` not yet taken by a debug console, `None` while no console is mirroring it.
+static mut MIRROR: Option<String> = None;
 
 /// Prints a message to the STDOUT, accessible via PPSSPP's debug console or via PSPLink with real hardware.
 pub fn stdout(message: &str) {
     unsafe {
         sceIoWrite(sceKernelStdout(), message.as_ptr() as *mut _, message.len());
+
+        if let Some(mirror) = &mut *addr_of_mut!(MIRROR) {
+            push_mirror(mirror, message);
+        }
+    }
+}
+
+/// Adds `message` to `mirror`, dropping its oldest characters past `MIRROR_CAPACITY` bytes.
+fn push_mirror(mirror: &mut String, message: &str) {
+    mirror.push_str(message);
+    if mirror.len() > MIRROR_CAPACITY {
+        let mut cut = mirror.len() - MIRROR_CAPACITY;
+        while !mirror.is_char_boundary(cut) {
+            cut += 1;
+        }
+        mirror.drain(..cut);
     }
 }
 
-// Based on (rand's uniform distribution implementation)[https://docs.rs/rand/latest/src/rand/distributions/uniform.rs.html]
-//
-// Copyright 2018-2020 Developers of the Rand project.
-// Copyright 2017 The Rust Project Developers.
-//
-// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
-// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
-// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
-// option. This file may not be copied, modified, or distributed
-// except according to those terms.
-/// Returns a random `i32` inside the provided `Range<i32>` using the PSP's MT19937 and uniform distribuition.
-pub fn rnd_range(range: Range<i32>) -> Result<i32, String> {
-    let n_range = range.end.wrapping_sub(range.start).wrapping_add(1) as u32;
-    let zone = (n_range << n_range.leading_zeros()).wrapping_sub(1);
+/// Starts or stops keeping the output of `stdout` for `take_stdout_mirror`.
+pub(crate) fn set_stdout_mirror(enabled: bool) {
+    unsafe {
+        MIRROR = if enabled { Some(String::new()) } else { None };
+    }
+}
 
+/// Returns the output of `stdout` since the last call, if it is being kept.
+pub(crate) fn take_stdout_mirror() -> Option<String> {
     unsafe {
-        let mt_context: *mut SceKernelUtilsMt19937Context =
-            core::mem::MaybeUninit::<SceKernelUtilsMt19937Context>::uninit().as_mut_ptr();
-        let current_time = core::mem::MaybeUninit::<ScePspDateTime>::uninit().as_mut_ptr();
+        match &mut *addr_of_mut!(MIRROR) {
+            Some(mirror) if !mirror.is_empty() => Some(core::mem::take(mirror)),
+            _ => None,
+        }
+    }
+}
+
+/// Pseudo-random number generator, seeded explicitly so the same seed always gives the same numbers, such
+/// as to replay a game or test it on the host. It uses xoshiro128++, which only needs 32-bit arithmetic, so
+/// nothing but `Rng::from_entropy` touches the hardware. It isn't fit for cryptography.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: [u32; 4],
+}
+
+impl Rng {
+    /// Returns a generator seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads the seed over the whole state, which must not be all zeros.
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let (a, b) = (next(), next());
+        Self {
+            state: [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32],
+        }
+    }
+
+    /// Returns a generator seeded from the current tick of the real-time clock, different on each run.
+    pub fn from_entropy() -> Self {
+        let mut tick = 0;
+        unsafe { sceRtcGetCurrentTick(&mut tick) };
+        Self::new(tick)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let [a, b, c, d] = &mut self.state;
+        let result = a.wrapping_add(*d).rotate_left(7).wrapping_add(*a);
+        let t = *b << 9;
+        *c ^= *a;
+        *d ^= *b;
+        *b ^= *c;
+        *a ^= *d;
+        *c ^= t;
+        *d = d.rotate_left(11);
+        result
+    }
+
+    /// Returns a number from `0.0` included to `1.0` excluded.
+    pub fn next_f32(&mut self) -> f32 {
+        // The 24 high bits fill the mantissa, so every value is equally likely.
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
 
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u32() >> 31 == 1
+    }
+
+    /// Returns `true` with a `probability` from `0.0` to `1.0`.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Returns a number below `bound`, which must not be `0`, each one equally likely.
+    fn below(&mut self, bound: u32) -> u32 {
+        // Lemire's method, rejecting the few values that would make the lowest results more likely.
+        let threshold = bound.wrapping_neg() % bound;
         loop {
-            if sceRtcGetCurrentClockLocalTime(current_time) < 0 {
-                return Err("Failed on sceRtcGetCurrentClockLocalTime.".to_owned());
-            }
-            if sceKernelUtilsMt19937Init(mt_context, (*current_time).microseconds) < 0 {
-                return Err("Failed on sceKernelUtilsMt19937Init.".to_owned());
+            let product = self.next_u32() as u64 * bound as u64;
+            if product as u32 >= threshold {
+                return (product >> 32) as u32;
             }
-            let v = sceKernelUtilsMt19937UInt(mt_context);
+        }
+    }
+
+    /// Returns a number inside `range`, each one equally likely, or `range.start` if it is empty.
+    pub fn range_u32(&mut self, range: Range<u32>) -> u32 {
+        if range.start >= range.end {
+            return range.start;
+        }
+        range.start + self.below(range.end - range.start)
+    }
+
+    /// Returns a number inside `range`, each one equally likely, or `range.start` if it is empty.
+    pub fn range_i32(&mut self, range: Range<i32>) -> i32 {
+        if range.start >= range.end {
+            return range.start;
+        }
+        let span = range.end.wrapping_sub(range.start) as u32;
+        range.start.wrapping_add(self.below(span) as i32)
+    }
+
+    /// Returns a number inside `range`, or `range.start` if it is empty.
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        let value = range.start + (range.end - range.start) * self.next_f32();
+        // Rounding may land on the end, which the range excludes.
+        if value < range.end {
+            value
+        } else {
+            range.start
+        }
+    }
+
+    /// Puts the items of `items` in a random order.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        // Fisher-Yates, swapping each item with one of those before it or itself.
+        for index in (1..items.len()).rev() {
+            let other = self.below(index as u32 + 1) as usize;
+            items.swap(index, other);
+        }
+    }
+
+    /// Returns one of `items`, each one equally likely, or `None` if there are none.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
 
-            let (_hi, lo) = v.widening_mul(n_range);
-            if lo <= zone {
-                return Ok(range.start.wrapping_add(lo as i32));
+    /// Returns one of `items`, each one as likely as its weight in `weights`, such as a rare drop weighing
+    /// `1.0` against a common one weighing `9.0`. Negative weights count as `0.0`. Returns `None` if the
+    /// weights add up to `0.0`.
+    pub fn choose_weighted<'a, T>(&mut self, items: &'a [T], weights: &[f32]) -> Option<&'a T> {
+        let total: f32 = weights.iter().take(items.len()).map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut target = self.next_f32() * total;
+        let mut last = None;
+        for (item, &weight) in items.iter().zip(weights) {
+            if weight <= 0.0 {
+                continue;
             }
+            if target < weight {
+                return Some(item);
+            }
+            target -= weight;
+            last = Some(item);
+        }
+        // Rounding may leave a little of the target past the last weight.
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn mirror_keeps_the_newest_output() {
+        let mut mirror = String::new();
+        push_mirror(&mut mirror, "hello\n");
+        assert_eq!(mirror, "hello\n");
+
+        let filler = "x".repeat(MIRROR_CAPACITY - 2);
+        push_mirror(&mut mirror, &filler);
+        push_mirror(&mut mirror, "end");
+        // The first line and one `x` are dropped.
+        assert_eq!(mirror, "x".repeat(MIRROR_CAPACITY - 3) + "end");
+    }
+
+    #[test]
+    fn mirror_cuts_between_characters() {
+        // `é` takes 2 bytes, so the cut can't land in the middle of one.
+        let mut mirror = "é".repeat(MIRROR_CAPACITY / 2);
+        push_mirror(&mut mirror, "a");
+        assert_eq!(mirror.len(), MIRROR_CAPACITY - 1);
+        assert!(mirror.starts_with('é') && mirror.ends_with("éa"));
+    }
+
+    #[test]
+    fn known_outputs() {
+        // The reference implementation's outputs for this state.
+        let mut rng = Rng {
+            state: [1, 2, 3, 4],
+        };
+        let outputs: Vec<u32> = (0..4).map(|_| rng.next_u32()).collect();
+        assert_eq!(outputs, [641, 1573767, 3222811527, 3517856514]);
+
+        // SplitMix64 gives 0xe220a8397b1dcdaf then 0x6e789e6aa1b965f4 for the seed 0.
+        assert_eq!(
+            Rng::new(0).state,
+            [0x7b1d_cdaf, 0xe220_a839, 0xa1b9_65f4, 0x6e78_9e6a]
+        );
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let numbers = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..16).map(|_| rng.next_u32()).collect::<Vec<_>>()
+        };
+        assert_eq!(numbers(42), numbers(42));
+        assert_ne!(numbers(42), numbers(43));
+    }
+
+    #[test]
+    fn ranges() {
+        let mut rng = Rng::new(1);
+        let mut seen = [false; 3];
+        for _ in 0..1000 {
+            let value = rng.range_u32(10..13);
+            assert!((10..13).contains(&value));
+            seen[(value - 10) as usize] = true;
+
+            let value = rng.range_i32(-3..2);
+            assert!((-3..2).contains(&value));
+
+            let value = rng.range_f32(-1.5..0.5);
+            assert!((-1.5..0.5).contains(&value));
+            assert!((0.0..1.0).contains(&rng.next_f32()));
+        }
+        assert_eq!(seen, [true; 3]);
+
+        // Empty ranges give their start.
+        assert_eq!(rng.range_u32(5..5), 5);
+        assert_eq!(rng.range_i32(Range { start: 7, end: -7 }), 7);
+        assert_eq!(rng.range_f32(2.0..1.0), 2.0);
+    }
+
+    #[test]
+    fn widest_ranges() {
+        let mut rng = Rng::new(2);
+        let (mut negative, mut positive) = (false, false);
+        for _ in 0..100 {
+            let value = rng.range_i32(i32::MIN..i32::MAX);
+            assert!(value < i32::MAX);
+            negative |= value < 0;
+            positive |= value > 0;
+            assert!(rng.range_u32(0..u32::MAX) < u32::MAX);
+        }
+        assert!(negative && positive);
+        assert_eq!(rng.range_i32(i32::MAX - 1..i32::MAX), i32::MAX - 1);
+        assert_eq!(rng.range_u32(u32::MAX - 1..u32::MAX), u32::MAX - 1);
+    }
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut rng = Rng::new(3);
+        let original: Vec<u32> = (0..20).collect();
+        let mut items = original.clone();
+        rng.shuffle(&mut items);
+        assert_ne!(items, original);
+        items.sort_unstable();
+        assert_eq!(items, original);
+
+        let mut empty: [u32; 0] = [];
+        rng.shuffle(&mut empty);
+        let mut single = [1];
+        rng.shuffle(&mut single);
+        assert_eq!(single, [1]);
+    }
+
+    #[test]
+    fn choose() {
+        let mut rng = Rng::new(4);
+        assert_eq!(rng.choose::<u32>(&[]), None);
+        for _ in 0..100 {
+            assert!([1, 2, 3].contains(rng.choose(&[1, 2, 3]).unwrap()));
+        }
+    }
+
+    #[test]
+    fn choose_weighted() {
+        let mut rng = Rng::new(5);
+        let items = ['a', 'b', 'c'];
+        assert_eq!(rng.choose_weighted(&items, &[0.0, 0.0, 0.0]), None);
+        assert_eq!(rng.choose_weighted(&items, &[-1.0, 0.0, -2.0]), None);
+        assert_eq!(rng.choose_weighted(&items, &[]), None);
+        assert_eq!(rng.choose_weighted::<char>(&[], &[1.0]), None);
+
+        // Items without a positive weight are never chosen, nor those past the weights.
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            let item = rng.choose_weighted(&items, &[0.0, 1.0, -1.0]).unwrap();
+            assert_eq!(*item, 'b');
+            let item = rng.choose_weighted(&items, &[1.0, 3.0]).unwrap();
+            counts[(*item as u8 - b'a') as usize] += 1;
         }
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > 150 && counts[0] < 350, "{:?}", counts);
     }
 }
@@ -0,0 +1,536 @@
+//! Frame-based animation: clips made of atlas frames and an `Animator` playing them. Timing only depends on
+//! the delta time given to `Animator::update`, so nothing here touches the hardware.
+
+extern crate alloc;
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+
+use crate::core::json::Value;
+
+/// Duration of frames whose data file doesn't specify one, in seconds.
+pub const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+/// Shortest duration of a frame, so a clip can't stall `Animator::update`.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+/// What happens when a clip reaches its last frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Starts over from the first frame.
+    Loop,
+    /// Plays backwards down to the first frame, then forwards again.
+    PingPong,
+    /// Stops on the last frame.
+    Once,
+}
+
+/// A frame of a clip: the index of an atlas frame and how long it is shown, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipFrame {
+    pub frame: usize,
+    pub duration: f32,
+}
+
+/// A named sequence of frames, such as `walk` or `jump`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    pub frames: Vec<ClipFrame>,
+    pub mode: PlayMode,
+    events: Vec<(usize, String)>,
+}
+
+impl Clip {
+    /// Returns a clip showing each of the atlas `frames` for `duration` seconds.
+    pub fn new(name: &str, frames: &[usize], duration: f32, mode: PlayMode) -> Self {
+        Self::from_frames(
+            name,
+            frames
+                .iter()
+                .map(|&frame| ClipFrame { frame, duration })
+                .collect(),
+            mode,
+        )
+    }
+
+    /// Returns a clip whose frames each have their own duration.
+    pub fn from_frames(name: &str, frames: Vec<ClipFrame>, mode: PlayMode) -> Self {
+        Self {
+            name: String::from(name),
+            frames,
+            mode,
+            events: Vec::new(),
+        }
+    }
+
+    /// Fires an `AnimationEvent::Frame` called `name` each time the `position`-th frame of the clip is shown.
+    pub fn add_event(&mut self, position: usize, name: &str) {
+        self.events.push((position, String::from(name)));
+    }
+
+    /// Returns the clip with an event added, see `Clip::add_event`.
+    pub fn with_event(mut self, position: usize, name: &str) -> Self {
+        self.add_event(position, name);
+        self
+    }
+
+    /// Time taken to show every frame once, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// Something that happened while updating an `Animator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A frame with an event was shown.
+    Frame { name: String, position: usize },
+    /// A `Loop` clip started over.
+    Looped,
+    /// A `Once` clip reached its last frame.
+    Finished,
+}
+
+/// Plays clips, keeping track of the frame to show.
+#[derive(Clone, Debug)]
+pub struct Animator {
+    clips: Vec<Clip>,
+    current: Option<usize>,
+    position: usize,
+    elapsed: f32,
+    forward: bool,
+    playing: bool,
+    speed: f32,
+    events: VecDeque<AnimationEvent>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            clips: Vec::new(),
+            current: None,
+            position: 0,
+            elapsed: 0.0,
+            forward: true,
+            playing: false,
+            speed: 1.0,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Adds a clip, replacing any with the same name. Replacing the current clip plays the new one from its
+    /// first frame, or stops if it has no frames.
+    pub fn add_clip(&mut self, clip: Clip) {
+        match self.clips.iter().position(|other| other.name == clip.name) {
+            Some(index) => {
+                self.clips[index] = clip;
+                if self.current == Some(index) {
+                    if self.clips[index].frames.is_empty() {
+                        self.current = None;
+                    }
+                    self.restart();
+                }
+            }
+            None => self.clips.push(clip),
+        }
+    }
+
+    /// Returns the clip called `name`.
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+
+    /// Returns all the clips.
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    /// Plays the clip called `name` from its first frame, unless it is already the current one. Returns
+    /// `false` if there's no such clip, or it has no frames.
+    pub fn play(&mut self, name: &str) -> bool {
+        let index = match self.clips.iter().position(|clip| clip.name == name) {
+            Some(index) if !self.clips[index].frames.is_empty() => index,
+            _ => return false,
+        };
+        if self.current == Some(index) {
+            self.playing = true;
+        } else {
+            self.current = Some(index);
+            self.restart();
+        }
+        true
+    }
+
+    /// Plays the current clip from its first frame.
+    pub fn restart(&mut self) {
+        self.position = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.playing = self.current.is_some();
+        self.fire_frame_events();
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = self.current.is_some();
+    }
+
+    /// Returns whether time passing changes the frame, which stops once a `Once` clip finishes.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Returns the clip being played.
+    pub fn current_clip(&self) -> Option<&Clip> {
+        self.current.map(|index| &self.clips[index])
+    }
+
+    /// Returns the position within the current clip of the frame shown.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the index of the atlas frame to show.
+    pub fn current_frame(&self) -> Option<usize> {
+        self.current_clip()
+            .map(|clip| clip.frames[self.position].frame)
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets how fast time passes for the clips, `1.0` being their normal speed.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Advances the animation by `delta` seconds. When `delta` spans more than two cycles of a repeating clip,
+    /// the cycles in between are skipped: their frame events aren't fired, but a `Looped` event still is for
+    /// each one of a `Loop` clip.
+    pub fn update(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+        let index = match self.current {
+            Some(index) => index,
+            None => return,
+        };
+
+        self.elapsed += delta * self.speed;
+        // Skips whole cycles at once, so a long pause doesn't step through every frame that was missed.
+        if let Some(period) = self.period(index) {
+            if self.elapsed > period * 2.0 {
+                let skipped = (self.elapsed / period) as u32 - 1;
+                self.elapsed -= skipped as f32 * period;
+                if self.clips[index].mode == PlayMode::Loop {
+                    // Each skipped cycle starts over exactly once.
+                    for _ in 0..skipped {
+                        self.events.push_back(AnimationEvent::Looped);
+                    }
+                }
+            }
+        }
+        loop {
+            let duration = self.clips[index].frames[self.position]
+                .duration
+                .max(MIN_FRAME_DURATION);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            if !self.advance(index) {
+                self.elapsed = 0.0;
+                break;
+            }
+            self.fire_frame_events();
+        }
+    }
+
+    /// Returns the oldest event not yet polled.
+    pub fn poll_event(&mut self) -> Option<AnimationEvent> {
+        self.events.pop_front()
+    }
+
+    /// Time after which a repeating clip shows the same frames again, `None` for `Once` clips.
+    fn period(&self, index: usize) -> Option<f32> {
+        let clip = &self.clips[index];
+        let duration = |frame: &ClipFrame| frame.duration.max(MIN_FRAME_DURATION);
+        let total: f32 = clip.frames.iter().map(duration).sum();
+        match clip.mode {
+            PlayMode::Loop => Some(total),
+            PlayMode::PingPong if clip.frames.len() > 1 => Some(
+                // Every frame but the first and last one is shown twice.
+                total * 2.0
+                    - duration(&clip.frames[0])
+                    - duration(&clip.frames[clip.frames.len() - 1]),
+            ),
+            PlayMode::PingPong => Some(total),
+            PlayMode::Once => None,
+        }
+    }
+
+    /// Moves to the next frame, returning `false` once a `Once` clip is over.
+    fn advance(&mut self, index: usize) -> bool {
+        let clip = &self.clips[index];
+        let last = clip.frames.len() - 1;
+        match clip.mode {
+            PlayMode::Loop => {
+                if self.position < last {
+                    self.position += 1;
+                } else {
+                    self.position = 0;
+                    self.events.push_back(AnimationEvent::Looped);
+                }
+            }
+            PlayMode::Once => {
+                if self.position < last {
+                    self.position += 1;
+                } else {
+                    self.playing = false;
+                    self.events.push_back(AnimationEvent::Finished);
+                    return false;
+                }
+            }
+            PlayMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.forward && self.position == last {
+                    self.forward = false;
+                } else if !self.forward && self.position == 0 {
+                    self.forward = true;
+                }
+                if self.forward {
+                    self.position += 1;
+                } else {
+                    self.position -= 1;
+                }
+            }
+        }
+        true
+    }
+
+    fn fire_frame_events(&mut self) {
+        if let Some(clip) = self.current.map(|index| &self.clips[index]) {
+            for (position, name) in &clip.events {
+                if *position == self.position {
+                    self.events.push_back(AnimationEvent::Frame {
+                        name: name.clone(),
+                        position: *position,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds clips from the `frameTags` of an Aseprite JSON file's `meta`, `durations` being those of the atlas
+/// frames, in seconds.
+pub(crate) fn aseprite_clips(tags: &Value, durations: &[f32]) -> Result<Vec<Clip>, &'static str> {
+    let tags = tags.as_array().ok_or("frameTags")?;
+    let mut clips = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let name = tag.get("name").and_then(Value::as_str).ok_or("name")?;
+        let from = tag.get("from").and_then(Value::as_u32).ok_or("from")? as usize;
+        let to = tag.get("to").and_then(Value::as_u32).ok_or("to")? as usize;
+        if from > to || to >= durations.len() {
+            return Err("to");
+        }
+
+        let mut frames: Vec<ClipFrame> = (from..=to)
+            .map(|frame| ClipFrame {
+                frame,
+                duration: durations[frame],
+            })
+            .collect();
+        let direction = tag
+            .get("direction")
+            .and_then(Value::as_str)
+            .unwrap_or("forward");
+        if direction == "reverse" || direction == "pingpong_reverse" {
+            frames.reverse();
+        }
+        // Aseprite writes `repeat` as a string, `"1"` meaning the tag is played once.
+        let once = tag.get("repeat").and_then(Value::as_str) == Some("1");
+        let mode = if direction.starts_with("pingpong") {
+            PlayMode::PingPong
+        } else if once {
+            PlayMode::Once
+        } else {
+            PlayMode::Loop
+        };
+
+        clips.push(Clip::from_frames(name, frames, mode));
+    }
+    Ok(clips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an animator playing a clip of the atlas frames `0..count`, each shown for a quarter second.
+    fn playing(count: usize, mode: PlayMode) -> Animator {
+        let frames: Vec<usize> = (0..count).collect();
+        let mut animator = Animator::new();
+        animator.add_clip(Clip::new("clip", &frames, 0.25, mode));
+        assert!(animator.play("clip"));
+        animator
+    }
+
+    /// Returns the frame shown after each of `steps` updates of a quarter second.
+    fn frames(animator: &mut Animator, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                animator.update(0.25);
+                animator.current_frame().unwrap()
+            })
+            .collect()
+    }
+
+    fn events(animator: &mut Animator) -> Vec<AnimationEvent> {
+        core::iter::from_fn(|| animator.poll_event()).collect()
+    }
+
+    #[test]
+    fn loop_sequence() {
+        let mut animator = playing(3, PlayMode::Loop);
+        assert_eq!(animator.current_frame(), Some(0));
+        assert_eq!(frames(&mut animator, 7), [1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!(
+            events(&mut animator),
+            [AnimationEvent::Looped, AnimationEvent::Looped]
+        );
+        assert!(animator.is_playing());
+    }
+
+    #[test]
+    fn ping_pong_sequence() {
+        let mut animator = playing(3, PlayMode::PingPong);
+        assert_eq!(frames(&mut animator, 8), [1, 2, 1, 0, 1, 2, 1, 0]);
+        assert_eq!(events(&mut animator), []);
+
+        let mut single = playing(1, PlayMode::PingPong);
+        assert_eq!(frames(&mut single, 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn once_sequence() {
+        let mut animator = playing(3, PlayMode::Once);
+        assert_eq!(frames(&mut animator, 4), [1, 2, 2, 2]);
+        assert_eq!(events(&mut animator), [AnimationEvent::Finished]);
+        assert!(!animator.is_playing());
+
+        animator.restart();
+        assert!(animator.is_playing());
+        assert_eq!(animator.current_frame(), Some(0));
+    }
+
+    #[test]
+    fn partial_updates() {
+        let mut animator = playing(3, PlayMode::Loop);
+        animator.update(0.125);
+        assert_eq!(animator.current_frame(), Some(0));
+        animator.update(0.125);
+        assert_eq!(animator.current_frame(), Some(1));
+
+        animator.set_speed(2.0);
+        animator.update(0.125);
+        assert_eq!(animator.current_frame(), Some(2));
+
+        animator.pause();
+        animator.update(1.0);
+        assert_eq!(animator.current_frame(), Some(2));
+    }
+
+    #[test]
+    fn frame_events() {
+        let mut animator = Animator::new();
+        animator.add_clip(
+            Clip::new("walk", &[0, 1, 2], 0.25, PlayMode::Loop)
+                .with_event(0, "start")
+                .with_event(2, "step"),
+        );
+        animator.play("walk");
+        let start = AnimationEvent::Frame {
+            name: String::from("start"),
+            position: 0,
+        };
+        let step = AnimationEvent::Frame {
+            name: String::from("step"),
+            position: 2,
+        };
+        assert_eq!(events(&mut animator), core::slice::from_ref(&start));
+
+        animator.update(0.75);
+        assert_eq!(events(&mut animator), [step, AnimationEvent::Looped, start]);
+    }
+
+    #[test]
+    fn skipped_cycles() {
+        let mut animator = Animator::new();
+        animator.add_clip(
+            Clip::new("walk", &[0, 1, 2, 3], 0.25, PlayMode::Loop).with_event(0, "start"),
+        );
+        animator.play("walk");
+        events(&mut animator);
+
+        // 42 frames: 9 cycles are skipped, then 6 frames are stepped through.
+        animator.update(10.5);
+        assert_eq!(animator.current_frame(), Some(2));
+        let events = events(&mut animator);
+        let looped = events
+            .iter()
+            .filter(|&event| *event == AnimationEvent::Looped)
+            .count();
+        assert_eq!(looped, 10);
+        // Only the stepped cycle fires frame events.
+        assert_eq!(events.len(), 11);
+
+        let mut once = playing(3, PlayMode::Once);
+        once.update(100.0);
+        assert_eq!(once.current_frame(), Some(2));
+        assert_eq!(once.poll_event(), Some(AnimationEvent::Finished));
+        assert_eq!(once.poll_event(), None);
+    }
+
+    #[test]
+    fn play_unknown_or_empty() {
+        let mut animator = Animator::new();
+        animator.add_clip(Clip::from_frames("empty", Vec::new(), PlayMode::Loop));
+        assert!(!animator.play("missing"));
+        assert!(!animator.play("empty"));
+        assert_eq!(animator.current_frame(), None);
+        animator.update(1.0);
+        assert!(!animator.is_playing());
+    }
+
+    #[test]
+    fn replace_current_clip() {
+        let mut animator = playing(3, PlayMode::Loop);
+        animator.update(0.5);
+        assert_eq!(animator.current_frame(), Some(2));
+
+        // The new frames play from the start.
+        animator.add_clip(Clip::new("clip", &[4, 5], 0.25, PlayMode::Loop));
+        assert_eq!(animator.current_frame(), Some(4));
+        assert_eq!(frames(&mut animator, 2), [5, 4]);
+
+        // A clip without frames stops the animation instead.
+        animator.add_clip(Clip::from_frames("clip", Vec::new(), PlayMode::Loop));
+        assert_eq!(animator.current_clip(), None);
+        assert_eq!(animator.current_frame(), None);
+        animator.update(1.0);
+        assert!(!animator.is_playing());
+        assert!(!animator.play("clip"));
+    }
+}
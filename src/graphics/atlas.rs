@@ -0,0 +1,419 @@
+extern crate alloc;
+use alloc::{rc::Rc, string::String, vec::Vec};
+
+use crate::core::{
+    io::{File, IoError},
+    json::{self, JsonError, Value},
+    Vec2, Vec3,
+};
+use crate::graphics::{
+    animation::{self, Clip, DEFAULT_FRAME_DURATION},
+    colors::Color,
+    sprite::Sprite,
+    texture::{Region, Texture},
+};
+
+/// Errors that may happen when creating an `Atlas`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtlasError {
+    /// The data file isn't valid JSON.
+    Json(JsonError),
+    /// A field of the frames or tags is missing or has the wrong type.
+    MissingField(&'static str),
+    /// A frame lies outside of the texture.
+    OutOfBounds,
+    /// The file uses a feature that isn't supported.
+    Unsupported(&'static str),
+    /// The grid cells are empty or larger than the texture.
+    InvalidSize,
+    /// The data file couldn't be read.
+    Io(IoError),
+}
+
+/// A named region of an atlas' texture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub name: String,
+    pub region: Region,
+    /// How long the frame is shown when animated, in milliseconds, if the file says so.
+    pub duration: Option<u32>,
+}
+
+/// Many images packed into a single texture, each a named `Frame`, so they share one texture upload.
+pub struct Atlas {
+    texture: Rc<Texture>,
+    frames: Vec<Frame>,
+    clips: Vec<Clip>,
+}
+
+impl Atlas {
+    /// Returns an atlas reading its frames from a TexturePacker or Aseprite JSON file, in either the hash
+    /// or the array layout. Aseprite tags are read as animation clips.
+    pub fn from_json(texture: Rc<Texture>, data: &str) -> Result<Self, AtlasError> {
+        let root = json::parse(data).map_err(AtlasError::Json)?;
+        let frames = match root.get("frames") {
+            Some(Value::Object(members)) => members
+                .iter()
+                .map(|(name, frame)| parse_frame(name.clone(), frame, &texture))
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|frame| {
+                    let name = frame
+                        .get("filename")
+                        .and_then(Value::as_str)
+                        .ok_or(AtlasError::MissingField("filename"))?;
+                    parse_frame(String::from(name), frame, &texture)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(AtlasError::MissingField("frames")),
+        };
+
+        let clips = match root.get("meta").and_then(|meta| meta.get("frameTags")) {
+            Some(tags) => {
+                let durations: Vec<f32> = frames
+                    .iter()
+                    .map(|frame| {
+                        frame
+                            .duration
+                            .map_or(DEFAULT_FRAME_DURATION, |duration| duration as f32 / 1000.0)
+                    })
+                    .collect();
+                animation::aseprite_clips(tags, &durations).map_err(AtlasError::MissingField)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            texture,
+            frames,
+            clips,
+        })
+    }
+
+    /// Returns an atlas reading its frames from a JSON file on the memory stick.
+    pub fn load_json(texture: Rc<Texture>, path: &str) -> Result<Self, AtlasError> {
+        let data = File::read_to_end(path).map_err(AtlasError::Io)?;
+        let data = core::str::from_utf8(&data)
+            .map_err(|error| AtlasError::Json(JsonError::UnexpectedChar(error.valid_up_to())))?;
+        Self::from_json(texture, data)
+    }
+
+    /// Returns an atlas splitting the texture into cells of `frame_width` by `frame_height` pixels, named
+    /// after their index from left to right and top to bottom.
+    pub fn from_grid(
+        texture: Rc<Texture>,
+        frame_width: u32,
+        frame_height: u32,
+    ) -> Result<Self, AtlasError> {
+        Self::from_grid_spaced(texture, frame_width, frame_height, 0, 0)
+    }
+
+    /// Returns an atlas like `from_grid` for sheets whose cells are `spacing` pixels apart and `margin`
+    /// pixels away from the edges of the texture.
+    pub fn from_grid_spaced(
+        texture: Rc<Texture>,
+        frame_width: u32,
+        frame_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Result<Self, AtlasError> {
+        let width = texture.width().saturating_sub(margin.saturating_mul(2));
+        let height = texture.height().saturating_sub(margin.saturating_mul(2));
+        if frame_width == 0 || frame_height == 0 || frame_width > width || frame_height > height {
+            return Err(AtlasError::InvalidSize);
+        }
+
+        let step_x = frame_width.saturating_add(spacing);
+        let step_y = frame_height.saturating_add(spacing);
+        let columns = (width - frame_width) / step_x + 1;
+        let rows = (height - frame_height) / step_y + 1;
+        let frames = (0..rows * columns)
+            .map(|index| Frame {
+                name: alloc::format!("{}", index),
+                region: Region::new(
+                    margin + (index % columns) * step_x,
+                    margin + (index / columns) * step_y,
+                    frame_width,
+                    frame_height,
+                ),
+                duration: None,
+            })
+            .collect();
+
+        Ok(Self {
+            texture,
+            frames,
+            clips: Vec::new(),
+        })
+    }
+
+    /// Returns the texture the frames are regions of.
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    /// Returns all the frames, in the order they were read.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the `index`-th frame.
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    /// Returns the frame called `name`.
+    pub fn get(&self, name: &str) -> Option<&Frame> {
+        self.frames.iter().find(|frame| frame.name == name)
+    }
+
+    /// Returns the index of the frame called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.frames.iter().position(|frame| frame.name == name)
+    }
+
+    /// Returns the animation clips read from the data file, such as Aseprite tags.
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    /// Returns a sprite drawing the frame called `name` at its original size.
+    pub fn sprite(&self, name: &str, position: Vec3<f32>, color: Color) -> Option<Sprite> {
+        let region = self.get(name)?.region;
+        let mut sprite = Sprite::new(
+            position,
+            0.0,
+            Vec2::new(region.width as f32, region.height as f32),
+            self.texture.clone(),
+            color,
+        );
+        sprite.set_source(region).ok()?;
+        Some(sprite)
+    }
+}
+
+fn parse_frame(name: String, frame: &Value, texture: &Texture) -> Result<Frame, AtlasError> {
+    if frame.get("rotated").and_then(Value::as_bool) == Some(true) {
+        return Err(AtlasError::Unsupported("rotated frame"));
+    }
+
+    let rect = frame
+        .get("frame")
+        .ok_or(AtlasError::MissingField("frame"))?;
+    let field = |key: &'static str| {
+        rect.get(key)
+            .and_then(Value::as_u32)
+            .ok_or(AtlasError::MissingField(key))
+    };
+    let region = Region::new(field("x")?, field("y")?, field("w")?, field("h")?);
+    if !texture.contains(region) {
+        return Err(AtlasError::OutOfBounds);
+    }
+
+    Ok(Frame {
+        name,
+        region,
+        duration: frame.get("duration").and_then(Value::as_u32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{animation::PlayMode, texture::PixelFormat};
+
+    fn texture(width: u32, height: u32) -> Rc<Texture> {
+        Rc::new(Texture::new(width, height, PixelFormat::Psm8888).unwrap())
+    }
+
+    fn regions(atlas: &Atlas) -> Vec<Region> {
+        atlas.frames().iter().map(|frame| frame.region).collect()
+    }
+
+    #[test]
+    fn texture_packer_hash() {
+        let data = r#"{
+            "frames": {
+                "idle.png": {"frame": {"x": 0, "y": 0, "w": 16, "h": 24}, "rotated": false},
+                "jump.png": {"frame": {"x": 16, "y": 8, "w": 32, "h": 24}}
+            },
+            "meta": {"app": "https://www.codeandweb.com/texturepacker", "size": {"w": 64, "h": 32}}
+        }"#;
+        let atlas = Atlas::from_json(texture(64, 32), data).unwrap();
+        assert_eq!(atlas.len(), 2);
+        assert_eq!(atlas.frame(0).unwrap().name, "idle.png");
+        assert_eq!(
+            atlas.get("jump.png"),
+            Some(&Frame {
+                name: String::from("jump.png"),
+                region: Region::new(16, 8, 32, 24),
+                duration: None,
+            })
+        );
+        assert_eq!(atlas.index_of("jump.png"), Some(1));
+        assert_eq!(atlas.get("run.png"), None);
+        assert_eq!(atlas.index_of("run.png"), None);
+        assert!(atlas.clips().is_empty());
+    }
+
+    #[test]
+    fn texture_packer_array() {
+        let data = r#"{"frames": [
+            {"filename": "a", "frame": {"x": 0, "y": 0, "w": 8, "h": 8}},
+            {"filename": "b", "frame": {"x": 8, "y": 0, "w": 8, "h": 8}}
+        ]}"#;
+        let atlas = Atlas::from_json(texture(16, 8), data).unwrap();
+        assert_eq!(
+            regions(&atlas),
+            [Region::new(0, 0, 8, 8), Region::new(8, 0, 8, 8)]
+        );
+        assert_eq!(atlas.index_of("b"), Some(1));
+
+        let nameless = r#"{"frames": [{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}]}"#;
+        assert_eq!(
+            Atlas::from_json(texture(16, 8), nameless).err(),
+            Some(AtlasError::MissingField("filename"))
+        );
+    }
+
+    #[test]
+    fn aseprite() {
+        let data = r#"{
+            "frames": {
+                "hero 0.aseprite": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "duration": 100},
+                "hero 1.aseprite": {"frame": {"x": 8, "y": 0, "w": 8, "h": 8}, "duration": 200},
+                "hero 2.aseprite": {"frame": {"x": 16, "y": 0, "w": 8, "h": 8}}
+            },
+            "meta": {
+                "app": "https://www.aseprite.org/",
+                "frameTags": [
+                    {"name": "walk", "from": 0, "to": 2, "direction": "forward"},
+                    {"name": "back", "from": 0, "to": 1, "direction": "reverse", "repeat": "1"}
+                ]
+            }
+        }"#;
+        let atlas = Atlas::from_json(texture(32, 8), data).unwrap();
+        assert_eq!(atlas.frame(1).unwrap().duration, Some(200));
+
+        let clips = atlas.clips();
+        assert_eq!(clips.len(), 2);
+        assert_eq!(clips[0].name, "walk");
+        assert_eq!(clips[0].mode, PlayMode::Loop);
+        let walk: Vec<(usize, f32)> = clips[0]
+            .frames
+            .iter()
+            .map(|frame| (frame.frame, frame.duration))
+            .collect();
+        assert_eq!(walk, [(0, 0.1), (1, 0.2), (2, DEFAULT_FRAME_DURATION)]);
+        assert_eq!(clips[1].name, "back");
+        assert_eq!(clips[1].mode, PlayMode::Once);
+        assert_eq!(clips[1].frames[0].frame, 1);
+    }
+
+    #[test]
+    fn invalid_frames() {
+        let atlas = |frame: &str| {
+            let data = alloc::format!(r#"{{"frames": {{"a": {}}}}}"#, frame);
+            Atlas::from_json(texture(16, 16), &data).err()
+        };
+        assert_eq!(
+            atlas(r#"{"frame": {"x": 0, "y": 0, "w": 16, "h": 16}}"#),
+            None
+        );
+        assert_eq!(
+            atlas(r#"{"frame": {"x": 8, "y": 0, "w": 16, "h": 16}}"#),
+            Some(AtlasError::OutOfBounds)
+        );
+        assert_eq!(
+            atlas(r#"{"frame": {"x": 0, "y": 17, "w": 1, "h": 1}}"#),
+            Some(AtlasError::OutOfBounds)
+        );
+        assert_eq!(
+            atlas(r#"{"frame": {"x": 0, "y": 0, "w": 8}}"#),
+            Some(AtlasError::MissingField("h"))
+        );
+        assert_eq!(
+            atlas(r#"{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "rotated": true}"#),
+            Some(AtlasError::Unsupported("rotated frame"))
+        );
+        assert_eq!(
+            Atlas::from_json(texture(16, 16), "{\"frames\": 1}").err(),
+            Some(AtlasError::MissingField("frames"))
+        );
+        assert_eq!(
+            Atlas::from_json(texture(16, 16), "{").err(),
+            Some(AtlasError::Json(JsonError::UnexpectedEnd))
+        );
+    }
+
+    #[test]
+    fn grid() {
+        let atlas = Atlas::from_grid(texture(40, 20), 16, 8).unwrap();
+        // Leftover pixels on the right and at the bottom are ignored.
+        assert_eq!(
+            regions(&atlas),
+            [
+                Region::new(0, 0, 16, 8),
+                Region::new(16, 0, 16, 8),
+                Region::new(0, 8, 16, 8),
+                Region::new(16, 8, 16, 8),
+            ]
+        );
+        assert_eq!(atlas.index_of("3"), Some(3));
+
+        for (width, height) in [(0, 8), (16, 0), (41, 8), (16, 21)] {
+            assert_eq!(
+                Atlas::from_grid(texture(40, 20), width, height).err(),
+                Some(AtlasError::InvalidSize)
+            );
+        }
+    }
+
+    #[test]
+    fn grid_margin_and_spacing() {
+        // 2 pixels of margin, cells 1 pixel apart: 2 + 8 + 1 + 8 + 1 + 8 = 28 of 32 pixels wide.
+        let atlas = Atlas::from_grid_spaced(texture(32, 24), 8, 8, 2, 1).unwrap();
+        assert_eq!(
+            regions(&atlas),
+            [
+                Region::new(2, 2, 8, 8),
+                Region::new(11, 2, 8, 8),
+                Region::new(20, 2, 8, 8),
+                Region::new(2, 11, 8, 8),
+                Region::new(11, 11, 8, 8),
+                Region::new(20, 11, 8, 8),
+            ]
+        );
+        assert!(atlas
+            .frames()
+            .iter()
+            .all(|frame| atlas.texture().contains(frame.region)));
+
+        // The last cell fits exactly, with no spacing after it.
+        let atlas = Atlas::from_grid_spaced(texture(20, 12), 8, 8, 2, 0).unwrap();
+        assert_eq!(
+            regions(&atlas),
+            [Region::new(2, 2, 8, 8), Region::new(10, 2, 8, 8)]
+        );
+
+        assert_eq!(
+            Atlas::from_grid_spaced(texture(16, 16), 8, 8, 5, 0).err(),
+            Some(AtlasError::InvalidSize)
+        );
+        assert_eq!(
+            Atlas::from_grid_spaced(texture(16, 16), 8, 8, u32::MAX, u32::MAX).err(),
+            Some(AtlasError::InvalidSize)
+        );
+        let atlas = Atlas::from_grid_spaced(texture(16, 16), 8, 8, 0, u32::MAX).unwrap();
+        assert_eq!(regions(&atlas), [Region::new(0, 0, 8, 8)]);
+    }
+}
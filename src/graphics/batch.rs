@@ -0,0 +1,442 @@
+extern crate alloc;
+use alloc::{rc::Rc, vec::Vec};
+use core::{mem::size_of, ptr};
+
+use psp::{
+    math,
+    sys::{
+        sceGuDisable, sceGuEnable, sceGuGetMemory, sceGuTexFilter, sceGuTexFunc, sceGuTexOffset,
+        sceGuTexScale, sceGuTexWrap, sceGumDrawArray, sceGumLoadIdentity, sceGumMatrixMode,
+        sceGumPopMatrix, sceGumPushMatrix, GuPrimitive, GuState, MatrixMode, TextureColorComponent,
+        VertexType,
+    },
+};
+
+use crate::core::{Vec2, Vec3};
+use crate::graphics::{
+    canvas,
+    colors::Color,
+    sprite::{Filter, Sprite, TextureFunction, Wrap},
+    texture::Texture,
+    Vertex,
+};
+
+/// Most vertices in a single draw call, a multiple of the six each quad takes.
+const MAX_VERTICES: usize = 65532;
+
+/// Bytes of the display list `SpriteBatch::flush` leaves for the commands drawing the rest of the frame.
+const LIST_RESERVE: usize = 0x4000;
+
+/// In which order a `SpriteBatch` draws what it was given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BatchOrder {
+    /// Keeps the order of the draw calls, only merging those that follow each other with the same texture
+    /// and state, so overlapping sprites are drawn as expected.
+    #[default]
+    Submission,
+    /// Merges every sprite with the same texture and state, in as few draw calls as possible. Sprites
+    /// with different textures may be drawn out of order.
+    Texture,
+}
+
+/// Texture and texture state shared by the quads of a draw call.
+#[derive(Clone)]
+pub(crate) struct BatchState {
+    pub(crate) texture: Option<Rc<Texture>>,
+    pub(crate) filter: Filter,
+    pub(crate) wrap: (Wrap, Wrap),
+    pub(crate) function: TextureFunction,
+}
+
+impl PartialEq for BatchState {
+    fn eq(&self, other: &Self) -> bool {
+        let same_texture = match (&self.texture, &other.texture) {
+            (Some(texture), Some(other)) => Rc::ptr_eq(texture, other),
+            (None, None) => true,
+            _ => false,
+        };
+        same_texture
+            && self.filter == other.filter
+            && self.wrap == other.wrap
+            && self.function == other.function
+    }
+}
+
+impl BatchState {
+    unsafe fn apply(&self) {
+        match &self.texture {
+            Some(texture) => {
+                sceGuEnable(GuState::Texture2D);
+                texture.bind();
+                sceGuTexFunc(self.function.as_effect(), TextureColorComponent::Rgba);
+                sceGuTexFilter(self.filter.as_psp(), self.filter.as_psp());
+                sceGuTexScale(1.0, 1.0);
+                sceGuTexOffset(0.0, 0.0);
+                sceGuTexWrap(self.wrap.0.as_psp(), self.wrap.1.as_psp());
+            }
+            None => sceGuDisable(GuState::Texture2D),
+        }
+    }
+}
+
+struct Group {
+    state: BatchState,
+    vertices: Vec<Vertex>,
+}
+
+/// Collects sprites and rects during a frame to draw them with as few draw calls as possible. Their
+/// corners are transformed on the CPU, and quads sharing a texture and state are drawn together from the
+/// display list by `SpriteBatch::flush`, sparing the matrix and texture setup each `Drawable::draw` does.
+pub struct SpriteBatch {
+    groups: Vec<Group>,
+    order: BatchOrder,
+    quads: usize,
+    draw_calls: usize,
+    dropped: usize,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self {
+            groups: Vec::new(),
+            order: BatchOrder::Submission,
+            quads: 0,
+            draw_calls: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn get_order(&self) -> BatchOrder {
+        self.order
+    }
+
+    pub fn set_order(&mut self, order: BatchOrder) {
+        self.order = order;
+    }
+
+    /// Adds `sprite` as it would be drawn by `Drawable::draw`.
+    pub fn draw_sprite(&mut self, sprite: &Sprite) {
+        let (state, corners) = sprite.batch_quad();
+        self.push(state, corners);
+    }
+
+    /// Adds an untextured rect of `size` pixels, `position` being its top-left corner.
+    pub fn draw_rect(&mut self, position: Vec3<f32>, size: Vec2<f32>, color: Color) {
+        let color = color.as_abgr();
+        let corner = |x: f32, y: f32| Vertex {
+            u: 0.0,
+            v: 0.0,
+            color,
+            x: position.x + x,
+            y: position.y + y,
+            z: position.z,
+        };
+        self.push(
+            BatchState {
+                texture: None,
+                filter: Filter::Nearest,
+                wrap: (Wrap::Clamp, Wrap::Clamp),
+                function: TextureFunction::Modulate,
+            },
+            [
+                corner(0.0, 0.0),
+                corner(size.x, 0.0),
+                corner(0.0, size.y),
+                corner(size.x, size.y),
+            ],
+        );
+    }
+
+    /// Returns the number of quads waiting to be drawn.
+    pub fn len(&self) -> usize {
+        self.quads
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads == 0
+    }
+
+    /// Returns the number of draw calls the last `SpriteBatch::flush` took.
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Returns the number of quads the last `SpriteBatch::flush` left out, for lack of display list space.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Drops everything added since the last flush without drawing it.
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.quads = 0;
+    }
+
+    /// Draws everything added since the last flush, as seen by the current camera. The vertices are copied
+    /// into the display list, `24` bytes for each of the six vertices of a quad, and the quads that don't
+    /// fit in what is left of it are left out, see `SpriteBatch::dropped`.
+    pub fn flush(&mut self) {
+        self.draw_calls = 0;
+        self.dropped = 0;
+        if self.groups.is_empty() {
+            return;
+        }
+
+        let mut drawn = 0;
+        unsafe {
+            sceGumMatrixMode(MatrixMode::Model);
+            sceGumPushMatrix();
+            sceGumLoadIdentity();
+
+            'groups: for group in &self.groups {
+                group.state.apply();
+                let mut vertices = &group.vertices[..];
+                while !vertices.is_empty() {
+                    let space = canvas::display_list_space().saturating_sub(LIST_RESERVE)
+                        / size_of::<Vertex>();
+                    let count = vertices.len().min(MAX_VERTICES).min(space / 6 * 6);
+                    if count == 0 {
+                        break 'groups;
+                    }
+                    let (chunk, rest) = vertices.split_at(count);
+                    vertices = rest;
+
+                    let memory =
+                        sceGuGetMemory((chunk.len() * size_of::<Vertex>()) as i32) as *mut Vertex;
+                    ptr::copy_nonoverlapping(chunk.as_ptr(), memory, chunk.len());
+
+                    sceGumDrawArray(
+                        GuPrimitive::Triangles,
+                        VertexType::TEXTURE_32BITF
+                            | VertexType::COLOR_8888
+                            | VertexType::VERTEX_32BITF
+                            | VertexType::TRANSFORM_3D,
+                        chunk.len() as i32,
+                        ptr::null(),
+                        memory as *const _,
+                    );
+                    self.draw_calls += 1;
+                    drawn += count / 6;
+                }
+            }
+
+            sceGumPopMatrix();
+        }
+        self.dropped = self.quads - drawn;
+        self.clear();
+    }
+
+    /// Adds a quad whose corners are in the order top-left, top-right, bottom-left and bottom-right.
+    pub(crate) fn push(&mut self, state: BatchState, corners: [Vertex; 4]) {
+        let index = match self.order {
+            BatchOrder::Submission => self
+                .groups
+                .last()
+                .filter(|group| group.state == state)
+                .map(|_| self.groups.len() - 1),
+            BatchOrder::Texture => self.groups.iter().position(|group| group.state == state),
+        };
+        let group = match index {
+            Some(index) => &mut self.groups[index],
+            None => {
+                self.groups.push(Group {
+                    state,
+                    vertices: Vec::new(),
+                });
+                self.groups.last_mut().unwrap()
+            }
+        };
+        group.vertices.extend_from_slice(&[
+            corners[0], corners[1], corners[2], corners[2], corners[1], corners[3],
+        ]);
+        self.quads += 1;
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Applies to `corners` the scale, rotation (in radians) and translation `Drawable::draw` would.
+pub(crate) fn transform(
+    corners: [Vertex; 4],
+    position: Vec3<f32>,
+    rotation: f32,
+    scale: Vec2<f32>,
+) -> [Vertex; 4] {
+    let (sin, cos) = if rotation == 0.0 {
+        (0.0, 1.0)
+    } else {
+        (
+            math::sin(rotation as f64) as f32,
+            math::cos(rotation as f64) as f32,
+        )
+    };
+    corners.map(|corner| {
+        let x = corner.x * scale.x;
+        let y = corner.y * scale.y;
+        Vertex {
+            x: position.x + cos * x - sin * y,
+            y: position.y + sin * x + cos * y,
+            z: position.z + corner.z,
+            ..corner
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::texture::PixelFormat;
+
+    fn state(texture: Option<&Rc<Texture>>, filter: Filter) -> BatchState {
+        BatchState {
+            texture: texture.cloned(),
+            filter,
+            wrap: (Wrap::Clamp, Wrap::Clamp),
+            function: TextureFunction::Modulate,
+        }
+    }
+
+    /// Returns the corners of a quad whose vertices are told apart by their `u`, from `id` to `id + 3`.
+    fn quad(id: f32) -> [Vertex; 4] {
+        [0.0, 1.0, 2.0, 3.0].map(|corner| Vertex {
+            u: id + corner,
+            ..Vertex::default()
+        })
+    }
+
+    /// Returns the state index and quad count of each group, `states` being the states to tell apart.
+    fn groups(batch: &SpriteBatch, states: &[BatchState]) -> Vec<(usize, usize)> {
+        batch
+            .groups
+            .iter()
+            .map(|group| {
+                let state = states
+                    .iter()
+                    .position(|state| *state == group.state)
+                    .unwrap();
+                (state, group.vertices.len() / 6)
+            })
+            .collect()
+    }
+
+    fn batch(order: BatchOrder) -> (SpriteBatch, Vec<BatchState>) {
+        let texture = Rc::new(Texture::new(8, 8, PixelFormat::Psm8888).unwrap());
+        let states = alloc::vec![
+            state(None, Filter::Nearest),
+            state(Some(&texture), Filter::Nearest),
+            state(Some(&texture), Filter::Linear),
+        ];
+        let mut batch = SpriteBatch::new();
+        batch.set_order(order);
+        for state in [0, 0, 1, 0, 2, 1, 1] {
+            batch.push(states[state].clone(), quad(0.0));
+        }
+        assert_eq!(batch.len(), 7);
+        (batch, states)
+    }
+
+    #[test]
+    fn submission_order() {
+        let (batch, states) = batch(BatchOrder::Submission);
+        // Only neighbours with the same state are merged.
+        assert_eq!(
+            groups(&batch, &states),
+            [(0, 2), (1, 1), (0, 1), (2, 1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn texture_order() {
+        let (mut batch, states) = batch(BatchOrder::Texture);
+        assert_eq!(groups(&batch, &states), [(0, 3), (1, 3), (2, 1)]);
+
+        batch.clear();
+        assert!(batch.is_empty());
+        assert!(batch.groups.is_empty());
+    }
+
+    #[test]
+    fn states_compare_textures_by_identity() {
+        let texture = Rc::new(Texture::new(8, 8, PixelFormat::Psm8888).unwrap());
+        let copy = Rc::new(Texture::new(8, 8, PixelFormat::Psm8888).unwrap());
+        assert!(
+            state(Some(&texture), Filter::Nearest)
+                == state(Some(&texture.clone()), Filter::Nearest)
+        );
+        assert!(state(Some(&texture), Filter::Nearest) != state(Some(&copy), Filter::Nearest));
+        assert!(state(Some(&texture), Filter::Nearest) != state(None, Filter::Nearest));
+    }
+
+    #[test]
+    fn quads_as_two_triangles() {
+        let mut batch = SpriteBatch::new();
+        batch.push(state(None, Filter::Nearest), quad(0.0));
+        batch.push(state(None, Filter::Nearest), quad(10.0));
+        let order: Vec<f32> = batch.groups[0]
+            .vertices
+            .iter()
+            .map(|vertex| vertex.u)
+            .collect();
+        assert_eq!(
+            order,
+            [0.0, 1.0, 2.0, 2.0, 1.0, 3.0, 10.0, 11.0, 12.0, 12.0, 11.0, 13.0]
+        );
+    }
+
+    fn positions(corners: [Vertex; 4]) -> [(f32, f32, f32); 4] {
+        corners.map(|corner| (corner.x, corner.y, corner.z))
+    }
+
+    fn corners() -> [Vertex; 4] {
+        [(0.0, 0.0), (4.0, 0.0), (0.0, 2.0), (4.0, 2.0)].map(|(x, y)| Vertex {
+            u: x / 4.0,
+            v: y / 2.0,
+            color: 0xff00_00ff,
+            x,
+            y,
+            z: 1.0,
+        })
+    }
+
+    #[test]
+    fn scales_then_translates() {
+        let transformed = transform(
+            corners(),
+            Vec3::new(10.0, 20.0, -3.0),
+            0.0,
+            Vec2::new(2.0, 0.5),
+        );
+        assert_eq!(
+            positions(transformed),
+            [
+                (10.0, 20.0, -2.0),
+                (18.0, 20.0, -2.0),
+                (10.0, 21.0, -2.0),
+                (18.0, 21.0, -2.0),
+            ]
+        );
+        // Texture coordinates and colors are kept.
+        assert_eq!((transformed[3].u, transformed[3].v), (1.0, 1.0));
+        assert!(transformed.iter().all(|corner| corner.color == 0xff00_00ff));
+    }
+
+    #[test]
+    fn rotates_around_the_position() {
+        let transformed = transform(
+            corners(),
+            Vec3::new(10.0, 20.0, 0.0),
+            core::f32::consts::FRAC_PI_2,
+            Vec2::new(1.0, 1.0),
+        );
+        // A quarter turn takes the x axis to the y axis, and the y axis to minus the x axis.
+        let expected = [(10.0, 20.0), (10.0, 24.0), (8.0, 20.0), (8.0, 24.0)];
+        for (corner, (x, y)) in transformed.iter().zip(expected) {
+            assert!((corner.x - x).abs() < 1e-5, "{} != {}", corner.x, x);
+            assert!((corner.y - y).abs() < 1e-5, "{} != {}", corner.y, y);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! A 2D camera: which part of the world is shown and how. It only computes the view matrix and converts
+//! between screen and world coordinates, `Canvas::set_camera` being what hands the matrix to the GU.
+
+use psp::math;
+
+use crate::core::Vec2;
+use crate::graphics::PI;
+
+/// Smallest zoom, so the view matrix can always be inverted.
+const MIN_ZOOM: f32 = 0.01;
+
+/// A camera looking at a 2D world, with `position` being the world point at the center of the screen.
+#[derive(Clone, Debug)]
+pub struct Camera2D {
+    position: Vec2<f32>,
+    zoom: f32,
+    rotation: f32,
+    viewport: Vec2<f32>,
+
+    target: Option<Vec2<f32>>,
+    smoothing: Option<f32>,
+    bounds: Option<(Vec2<f32>, Vec2<f32>)>,
+
+    shake_intensity: f32,
+    shake_duration: f32,
+    shake_remaining: f32,
+    shake_offset: Vec2<f32>,
+    seed: u32,
+}
+
+impl Camera2D {
+    /// Returns a camera centered on `position`, showing a `480`x`272` viewport at `1.0` zoom.
+    pub fn new(position: Vec2<f32>) -> Self {
+        Self {
+            position,
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport: Vec2::new(480.0, 272.0),
+            target: None,
+            smoothing: None,
+            bounds: None,
+            shake_intensity: 0.0,
+            shake_duration: 0.0,
+            shake_remaining: 0.0,
+            shake_offset: Vec2::new(0.0, 0.0),
+            seed: 0x9e37_79b9,
+        }
+    }
+
+    pub fn get_pos(&self) -> Vec2<f32> {
+        self.position
+    }
+
+    /// Centers the camera on `position`, within its bounds.
+    pub fn set_pos(&mut self, position: Vec2<f32>) {
+        self.position = position;
+        self.clamp();
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets how many screen pixels a world unit takes, `2.0` showing everything twice as big.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(MIN_ZOOM);
+        self.clamp();
+    }
+
+    /// Returns the rotation of the camera, in degrees.
+    pub fn get_rot(&self) -> f32 {
+        self.rotation * (180.0 / PI)
+    }
+
+    /// Sets the rotation of the camera in degrees, the world turning the other way on screen.
+    pub fn set_rot(&mut self, rotation: f32) {
+        self.rotation = rotation * (PI / 180.0);
+        self.clamp();
+    }
+
+    pub fn get_viewport(&self) -> Vec2<f32> {
+        self.viewport
+    }
+
+    /// Sets the size of the area the camera draws to, in pixels, such as that of a render target.
+    pub fn set_viewport(&mut self, size: Vec2<f32>) {
+        self.viewport = size;
+        self.clamp();
+    }
+
+    /// Makes `update` move the camera towards `target`, `None` leaving it where it is.
+    pub fn follow(&mut self, target: Option<Vec2<f32>>) {
+        self.target = target;
+    }
+
+    /// Sets how fast the camera catches up with its target, `5.0` covering most of the distance within a
+    /// second. `None` centers it on the target right away.
+    pub fn set_smoothing(&mut self, speed: Option<f32>) {
+        self.smoothing = speed.map(|speed| speed.max(0.0));
+    }
+
+    /// Keeps the view within the `min` and `max` corners of the world, centering it when the world is
+    /// smaller than the view. `None` lets the camera go anywhere.
+    pub fn set_bounds(&mut self, bounds: Option<(Vec2<f32>, Vec2<f32>)>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
+    /// Shakes the view up to `intensity` screen pixels away, calming down over `duration` seconds.
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.shake_intensity = intensity;
+        self.shake_duration = duration.max(0.0);
+        self.shake_remaining = self.shake_duration;
+    }
+
+    /// Follows the target and shakes the view, `delta` being the seconds elapsed since the last update.
+    pub fn update(&mut self, delta: f32) {
+        if let Some(target) = self.target {
+            let amount = match self.smoothing {
+                // Never overshoots, whatever the frame rate.
+                Some(speed) => 1.0 - 1.0 / (1.0 + speed * delta),
+                None => 1.0,
+            };
+            self.position.x += (target.x - self.position.x) * amount;
+            self.position.y += (target.y - self.position.y) * amount;
+            self.clamp();
+        }
+
+        if self.shake_remaining > 0.0 {
+            self.shake_remaining = (self.shake_remaining - delta).max(0.0);
+            let strength = self.shake_intensity * self.shake_remaining / self.shake_duration;
+            self.shake_offset = Vec2::new(self.random() * strength, self.random() * strength);
+        } else {
+            self.shake_offset = Vec2::new(0.0, 0.0);
+        }
+    }
+
+    /// Returns where the world point `point` is drawn on screen.
+    pub fn world_to_screen(&self, point: Vec2<f32>) -> Vec2<f32> {
+        let (sin, cos) = self.sin_cos();
+        let x = point.x - self.position.x;
+        let y = point.y - self.position.y;
+        Vec2::new(
+            (cos * x + sin * y) * self.zoom + self.viewport.x / 2.0 + self.shake_offset.x,
+            (cos * y - sin * x) * self.zoom + self.viewport.y / 2.0 + self.shake_offset.y,
+        )
+    }
+
+    /// Returns the world point drawn at `point` on screen, such as the one under a cursor.
+    pub fn screen_to_world(&self, point: Vec2<f32>) -> Vec2<f32> {
+        let (sin, cos) = self.sin_cos();
+        let x = (point.x - self.viewport.x / 2.0 - self.shake_offset.x) / self.zoom;
+        let y = (point.y - self.viewport.y / 2.0 - self.shake_offset.y) / self.zoom;
+        Vec2::new(
+            cos * x - sin * y + self.position.x,
+            sin * x + cos * y + self.position.y,
+        )
+    }
+
+    /// Returns the view matrix, in the column-major order of `ScePspFMatrix4`.
+    pub fn view_matrix(&self) -> [f32; 16] {
+        let (sin, cos) = self.sin_cos();
+        let origin = self.world_to_screen(Vec2::new(0.0, 0.0));
+        #[rustfmt::skip]
+        let matrix = [
+            cos * self.zoom, -sin * self.zoom, 0.0, 0.0,
+            sin * self.zoom, cos * self.zoom, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            origin.x, origin.y, 0.0, 1.0,
+        ];
+        matrix
+    }
+
+    fn sin_cos(&self) -> (f32, f32) {
+        (
+            math::sin(self.rotation as f64) as f32,
+            math::cos(self.rotation as f64) as f32,
+        )
+    }
+
+    /// Moves the camera back within its bounds, taking the rotated view's extent into account.
+    fn clamp(&mut self) {
+        let (min, max) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (sin, cos) = self.sin_cos();
+        let (sin, cos) = (abs(sin), abs(cos));
+        let half = Vec2::new(
+            (cos * self.viewport.x + sin * self.viewport.y) / (2.0 * self.zoom),
+            (sin * self.viewport.x + cos * self.viewport.y) / (2.0 * self.zoom),
+        );
+
+        let axis = |position: f32, half: f32, min: f32, max: f32| {
+            if max - min <= half * 2.0 {
+                (min + max) / 2.0
+            } else {
+                position.max(min + half).min(max - half)
+            }
+        };
+        self.position.x = axis(self.position.x, half.x, min.x, max.x);
+        self.position.y = axis(self.position.y, half.y, min.y, max.y);
+    }
+
+    /// Returns a pseudo-random value between `-1.0` and `1.0` (xorshift32), for the shake.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+fn abs(value: f32) -> f32 {
+    if value < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec2<f32>, b: Vec2<f32>) {
+        assert!(
+            abs(a.x - b.x) < 1e-3 && abs(a.y - b.y) < 1e-3,
+            "{:?} != {:?}",
+            (a.x, a.y),
+            (b.x, b.y)
+        );
+    }
+
+    fn camera() -> Camera2D {
+        let mut camera = Camera2D::new(Vec2::new(120.0, -40.0));
+        camera.set_zoom(2.5);
+        camera.set_rot(30.0);
+        camera
+    }
+
+    /// Applies the column-major `matrix` to `point`.
+    fn transform(matrix: &[f32; 16], point: Vec2<f32>) -> Vec2<f32> {
+        Vec2::new(
+            matrix[0] * point.x + matrix[4] * point.y + matrix[12],
+            matrix[1] * point.x + matrix[5] * point.y + matrix[13],
+        )
+    }
+
+    const POINTS: [(f32, f32); 4] = [(0.0, 0.0), (120.0, -40.0), (-75.5, 310.0), (1000.0, 2.0)];
+
+    #[test]
+    fn centered_on_position() {
+        let camera = camera();
+        assert_near(
+            camera.world_to_screen(Vec2::new(120.0, -40.0)),
+            Vec2::new(240.0, 136.0),
+        );
+        assert!(abs(camera.get_rot() - 30.0) < 1e-4);
+
+        // A point right of the center is drawn further away and turned the other way.
+        let mut camera = Camera2D::new(Vec2::new(0.0, 0.0));
+        camera.set_zoom(2.0);
+        camera.set_rot(90.0);
+        assert_near(
+            camera.world_to_screen(Vec2::new(10.0, 0.0)),
+            Vec2::new(240.0, 116.0),
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut camera = camera();
+        camera.shake(8.0, 1.0);
+        camera.update(0.25);
+        for (x, y) in POINTS {
+            let point = Vec2::new(x, y);
+            assert_near(camera.screen_to_world(camera.world_to_screen(point)), point);
+            assert_near(camera.world_to_screen(camera.screen_to_world(point)), point);
+        }
+    }
+
+    #[test]
+    fn view_matrix_matches_world_to_screen() {
+        let mut camera = camera();
+        camera.set_viewport(Vec2::new(256.0, 128.0));
+        camera.shake(8.0, 1.0);
+        camera.update(0.25);
+        let matrix = camera.view_matrix();
+        for (x, y) in POINTS {
+            let point = Vec2::new(x, y);
+            assert_near(transform(&matrix, point), camera.world_to_screen(point));
+        }
+    }
+
+    #[test]
+    fn clamps_to_bounds() {
+        let mut camera = Camera2D::new(Vec2::new(0.0, 0.0));
+        camera.set_bounds(Some((Vec2::new(0.0, 0.0), Vec2::new(1000.0, 500.0))));
+        assert_near(camera.get_pos(), Vec2::new(240.0, 136.0));
+        camera.set_pos(Vec2::new(2000.0, 300.0));
+        assert_near(camera.get_pos(), Vec2::new(760.0, 300.0));
+
+        // Zooming in shows less of the world, so the camera may get closer to the edges.
+        camera.set_zoom(2.0);
+        camera.set_pos(Vec2::new(0.0, 0.0));
+        assert_near(camera.get_pos(), Vec2::new(120.0, 68.0));
+
+        // Turned a quarter, the view is as wide as the screen is high.
+        camera.set_zoom(1.0);
+        camera.set_rot(90.0);
+        camera.set_pos(Vec2::new(0.0, 0.0));
+        assert_near(camera.get_pos(), Vec2::new(136.0, 240.0));
+    }
+
+    #[test]
+    fn centers_in_small_bounds() {
+        let mut camera = Camera2D::new(Vec2::new(0.0, 0.0));
+        camera.set_bounds(Some((Vec2::new(0.0, 0.0), Vec2::new(100.0, 50.0))));
+        assert_near(camera.get_pos(), Vec2::new(50.0, 25.0));
+        camera.set_pos(Vec2::new(-300.0, 900.0));
+        assert_near(camera.get_pos(), Vec2::new(50.0, 25.0));
+
+        // Only the axis that doesn't fit is centered.
+        camera.set_bounds(Some((Vec2::new(0.0, 0.0), Vec2::new(100.0, 1000.0))));
+        camera.set_pos(Vec2::new(0.0, 0.0));
+        assert_near(camera.get_pos(), Vec2::new(50.0, 136.0));
+    }
+
+    #[test]
+    fn follows_target() {
+        let mut camera = Camera2D::new(Vec2::new(0.0, 0.0));
+        camera.follow(Some(Vec2::new(100.0, 50.0)));
+        camera.set_smoothing(Some(1.0));
+        camera.update(1.0);
+        assert_near(camera.get_pos(), Vec2::new(50.0, 25.0));
+
+        camera.set_smoothing(None);
+        camera.update(0.0);
+        assert_near(camera.get_pos(), Vec2::new(100.0, 50.0));
+    }
+}
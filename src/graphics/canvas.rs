@@ -1,57 +1,195 @@
+extern crate alloc;
+use alloc::vec::Vec;
 use core::ffi::c_void;
 
 use psp::{
     sys::{
-        sceDisplayWaitVblankStart, sceGuAlphaFunc, sceGuBlendFunc, sceGuClear, sceGuClearColor,
-        sceGuClearDepth, sceGuDebugFlush, sceGuDebugPrint, sceGuDepthBuffer, sceGuDepthRange,
-        sceGuDispBuffer, sceGuDisplay, sceGuDrawBuffer, sceGuEnable, sceGuFinish, sceGuFrontFace,
-        sceGuInit, sceGuOffset, sceGuScissor, sceGuShadeModel, sceGuStart, sceGuSwapBuffers,
-        sceGuSync, sceGuTerm, sceGuViewport, sceGumLoadIdentity, sceGumMatrixMode, sceGumOrtho,
+        sceDisplayGetVcount, sceDisplayWaitVblankStart, sceGuAlphaFunc, sceGuBlendFunc,
+        sceGuCheckList, sceGuClear, sceGuClearColor, sceGuClearDepth, sceGuDebugFlush,
+        sceGuDepthBuffer, sceGuDepthRange, sceGuDisable, sceGuDispBuffer, sceGuDisplay,
+        sceGuDrawBuffer, sceGuDrawBufferList, sceGuEnable, sceGuFinish, sceGuFrontFace, sceGuInit,
+        sceGuOffset, sceGuScissor, sceGuSetDither, sceGuShadeModel, sceGuStart, sceGuStencilFunc,
+        sceGuStencilOp, sceGuSwapBuffers, sceGuSync, sceGuTerm, sceGuTexFlush, sceGuViewport,
+        sceGumLoadIdentity, sceGumLoadMatrix, sceGumMatrixMode, sceGumOrtho,
         sceKernelDcacheWritebackInvalidateAll, sceKernelExitGame, AlphaFunc, BlendFactor, BlendOp,
-        ClearBuffer, DisplayPixelFormat, FrontFaceDirection, GuContextType, GuState,
-        GuSyncBehavior, GuSyncMode, MatrixMode, ShadingModel, TexturePixelFormat,
+        ClearBuffer, FrontFaceDirection, GuContextType, GuState, GuSyncBehavior, GuSyncMode,
+        MatrixMode, ScePspFMatrix4, ScePspFVector4, ScePspIMatrix4, ScePspIVector4, ShadingModel,
+        StencilFunction, StencilOperation,
     },
-    vram_alloc::get_vram_allocator,
     BUF_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
 };
 
-use crate::graphics::colors::{Color, Colors};
+use crate::core::io::{File, IoError};
+use crate::graphics::{
+    camera::Camera2D,
+    colors::{Color, Colors},
+    debug,
+    image::{Image, ImageFormat},
+    queue::{Opacity, RenderQueue},
+    target::RenderTarget,
+    texture::{convert::unpack_16bit, PixelFormat},
+    vram::{self, VramBlock, VramError},
+    Drawable,
+};
+use crate::system::callbacks;
 
-static mut LIST: psp::Align16<[u32; 0x40000]> = psp::Align16([0; 0x40000]);
+/// Number of frames ended by `Canvas::end_frame`, telling apart the display lists of successive frames.
+static mut FRAMES: u32 = 0;
 
-/// This modules describes and gives access to the PSP screen (width: `480`, height: `272`).
-pub struct Canvas {}
+/// Returns the number of the frame being drawn.
+pub(crate) fn frame() -> u32 {
+    unsafe { FRAMES }
+}
 
-impl Canvas {
-    /// This method must be called only once and at the start of the project, in the `psp_main` function.
-    /// It initiates the graphical functions of the PSP.
+/// Size in bytes of the display list of the `Canvas`, `0` until one is built.
+static mut DISPLAY_LIST_SIZE: usize = 0;
+
+/// Returns how many bytes are left in the display list of the frame being drawn.
+pub(crate) fn display_list_space() -> usize {
+    unsafe { DISPLAY_LIST_SIZE.saturating_sub(sceGuCheckList() as usize) }
+}
+
+/// Size of the display list `Canvas::new` gives the GE, in bytes.
+pub const DEFAULT_DISPLAY_LIST_SIZE: usize = 0x100000;
+
+/// Errors that may happen when building a `Canvas`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanvasError {
+    /// The GE can't draw in `Clut` formats.
+    UnsupportedFormat(PixelFormat),
+    /// The display list can't be empty.
+    InvalidDisplayListSize,
+    /// There isn't enough VRAM left for the framebuffers or the depth buffer.
+    Vram(VramError),
+}
+
+/// How what is drawn is combined with what is already on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replaces what is on screen, ignoring alpha.
+    None,
+    /// Blends according to the alpha of what is drawn.
+    Alpha,
+    /// Adds what is drawn, weighted by its alpha, to what is on screen. Useful for lights and particles.
+    Additive,
+    /// Multiplies what is on screen by what is drawn. Useful for shadows and tinting.
+    Multiply,
+}
+
+/// Winding order of the triangles facing the screen, the others being culled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontFace {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Chooses how a `Canvas` is set up, the defaults matching `Canvas::new`.
+#[derive(Clone, Copy, Debug)]
+pub struct CanvasBuilder {
+    format: PixelFormat,
+    depth_buffer: bool,
+    display_list_size: usize,
+    culling: Option<FrontFace>,
+    blend_mode: BlendMode,
+    dithering: bool,
+}
+
+impl CanvasBuilder {
     pub fn new() -> Self {
-        psp::enable_home_button();
-
-        let allocator = get_vram_allocator().unwrap();
-        let fbp0 = allocator
-            .alloc_texture_pixels(BUF_WIDTH, SCREEN_HEIGHT, TexturePixelFormat::Psm8888)
-            .as_mut_ptr_from_zero();
-        let fbp1 = allocator
-            .alloc_texture_pixels(BUF_WIDTH, SCREEN_HEIGHT, TexturePixelFormat::Psm8888)
-            .as_mut_ptr_from_zero();
-        let zbp = allocator
-            .alloc_texture_pixels(BUF_WIDTH, SCREEN_HEIGHT, TexturePixelFormat::Psm4444)
-            .as_mut_ptr_from_zero();
+        Self {
+            format: PixelFormat::Psm8888,
+            depth_buffer: true,
+            display_list_size: DEFAULT_DISPLAY_LIST_SIZE,
+            culling: Some(FrontFace::Clockwise),
+            blend_mode: BlendMode::Alpha,
+            dithering: false,
+        }
+    }
+
+    /// Sets the format of the framebuffers. The 16-bit ones take half the VRAM at the cost of precision,
+    /// which dithering hides.
+    pub fn with_format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether a depth buffer is allocated. Without one, `RenderQueue` only sorts by depth, drawing
+    /// everything back to front.
+    pub fn with_depth_buffer(mut self, depth_buffer: bool) -> Self {
+        self.depth_buffer = depth_buffer;
+        self
+    }
+
+    /// Sets the size in bytes of the display list, which must hold the commands and vertices of a whole frame.
+    pub fn with_display_list_size(mut self, size: usize) -> Self {
+        self.display_list_size = size;
+        self
+    }
+
+    /// Sets the winding order of the triangles facing the screen, `None` drawing triangles of both.
+    pub fn with_culling(mut self, front_face: Option<FrontFace>) -> Self {
+        self.culling = front_face;
+        self
+    }
+
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets whether colors are dithered when drawn to 16-bit framebuffers, trading banding for noise.
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Initiates the graphical functions of the PSP. Must be called only once, in the `psp_main` function.
+    pub fn build(self) -> Result<Canvas, CanvasError> {
+        let display_format = self
+            .format
+            .as_display()
+            .ok_or(CanvasError::UnsupportedFormat(self.format))?;
+        if self.display_list_size == 0 {
+            return Err(CanvasError::InvalidDisplayListSize);
+        }
+
+        let framebuffer_size = self.format.bytes_for(BUF_WIDTH * SCREEN_HEIGHT) as u32;
+        let framebuffers = [
+            vram::alloc(framebuffer_size).map_err(CanvasError::Vram)?,
+            vram::alloc(framebuffer_size).map_err(CanvasError::Vram)?,
+        ];
+        let depth_buffer = if self.depth_buffer {
+            let block = vram::alloc(BUF_WIDTH * SCREEN_HEIGHT * 2).map_err(CanvasError::Vram)?;
+            Some(block.from_zero as *mut c_void)
+        } else {
+            None
+        };
+        let fbp0 = framebuffers[0].from_zero;
+        let fbp1 = framebuffers[1].from_zero;
+
+        // Rounded up to whole 16-byte blocks, which keeps the list aligned as the GE requires.
+        let mut list = Vec::new();
+        list.resize_with(self.display_list_size.div_ceil(16), || psp::Align16([0; 4]));
+
+        // Quits right away from the HOME menu, unless the game handles it with `callbacks::set_handle_exit`.
+        callbacks::register();
 
         unsafe {
+            DISPLAY_LIST_SIZE = list.len() * 16;
             sceGumLoadIdentity();
             sceGuInit();
 
-            sceGuStart(GuContextType::Direct, &mut LIST as *mut _ as *mut c_void);
-            sceGuDrawBuffer(DisplayPixelFormat::Psm8888, fbp0 as _, BUF_WIDTH as i32);
+            sceGuStart(GuContextType::Direct, list.as_mut_ptr() as *mut c_void);
+            sceGuDrawBuffer(display_format, fbp0 as _, BUF_WIDTH as i32);
             sceGuDispBuffer(
                 SCREEN_WIDTH as i32,
                 SCREEN_HEIGHT as i32,
                 fbp1 as _,
                 BUF_WIDTH as i32,
             );
-            sceGuDepthBuffer(zbp as _, BUF_WIDTH as i32);
+            if let Some(zbp) = depth_buffer {
+                sceGuDepthBuffer(zbp, BUF_WIDTH as i32);
+            }
 
             sceGuOffset(2048 - (SCREEN_WIDTH / 2), 2048 - (SCREEN_HEIGHT / 2));
             sceGuViewport(2048, 2048, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
@@ -60,20 +198,35 @@ impl Canvas {
             sceGuEnable(GuState::ScissorTest);
 
             sceGuEnable(GuState::Texture2D);
-            sceGuEnable(GuState::Blend);
-            sceGuBlendFunc(
-                BlendOp::Add,
-                BlendFactor::SrcAlpha,
-                BlendFactor::OneMinusSrcAlpha,
-                0,
-                0,
-            );
+            apply_blend_mode(self.blend_mode);
             sceGuAlphaFunc(AlphaFunc::Greater, 0, 0xff);
             sceGuEnable(GuState::AlphaTest);
             sceGuShadeModel(ShadingModel::Smooth);
 
-            sceGuEnable(GuState::CullFace);
-            sceGuFrontFace(FrontFaceDirection::Clockwise);
+            match self.culling {
+                Some(front_face) => {
+                    sceGuEnable(GuState::CullFace);
+                    sceGuFrontFace(match front_face {
+                        FrontFace::Clockwise => FrontFaceDirection::Clockwise,
+                        FrontFace::CounterClockwise => FrontFaceDirection::CounterClockwise,
+                    });
+                }
+                None => sceGuDisable(GuState::CullFace),
+            }
+
+            if self.dithering {
+                // The usual 4x4 ordered dither, offsets going from -4 to 3.
+                let row = |x, y, z, w| ScePspIVector4 { x, y, z, w };
+                sceGuSetDither(&ScePspIMatrix4 {
+                    x: row(-4, 0, -3, 1),
+                    y: row(2, -2, 3, -1),
+                    z: row(-3, 1, -4, 0),
+                    w: row(3, -1, 2, -2),
+                });
+                sceGuEnable(GuState::Dither);
+            } else {
+                sceGuDisable(GuState::Dither);
+            }
 
             sceGuFinish();
             sceGuSync(GuSyncMode::Finish, GuSyncBehavior::Wait);
@@ -92,40 +245,137 @@ impl Canvas {
             sceGumLoadIdentity();
         }
 
-        Canvas {}
+        let mut queue = RenderQueue::new();
+        queue.set_depth_buffer(depth_buffer.is_some());
+        queue.set_view(IDENTITY);
+
+        Ok(Canvas {
+            queue,
+            list,
+            format: self.format,
+            framebuffers,
+            draw_buffer: fbp0 as *mut c_void,
+            depth_buffer,
+            target_bound: false,
+            vsync_interval: 1,
+            last_vblank: unsafe { sceDisplayGetVcount() },
+            dialog_update: None,
+        })
+    }
+}
+
+impl Default for CanvasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// This modules describes and gives access to the PSP screen (width: `480`, height: `272`).
+pub struct Canvas {
+    queue: RenderQueue,
+    /// Display list the GE reads the commands of each frame from.
+    list: Vec<psp::Align16<[u32; 4]>>,
+    format: PixelFormat,
+    /// Both framebuffers, one displayed while the other is drawn into.
+    framebuffers: [VramBlock; 2],
+    /// Framebuffer being drawn into, which changes each time the buffers are swapped.
+    draw_buffer: *mut c_void,
+    depth_buffer: Option<*mut c_void>,
+    target_bound: bool,
+    /// Vblanks each frame is shown for, `0` not waiting for any.
+    vsync_interval: u32,
+    /// Vblank count when the last frame was shown.
+    last_vblank: u32,
+    /// Update of the utility dialog open, drawing it over the frame once the GE finished it.
+    dialog_update: Option<fn()>,
+}
+
+impl Canvas {
+    /// This method must be called only once and at the start of the project, in the `psp_main` function.
+    /// It initiates the graphical functions of the PSP with 8888 framebuffers, a depth buffer, alpha
+    /// blending and clockwise triangles facing the screen. See `CanvasBuilder` to choose otherwise.
+    ///
+    /// Panics if there isn't enough VRAM left.
+    pub fn new() -> Self {
+        CanvasBuilder::new().build().unwrap()
+    }
+
+    /// Returns a `CanvasBuilder`, to choose how the canvas is set up.
+    pub fn builder() -> CanvasBuilder {
+        CanvasBuilder::new()
     }
 
     /// Must be called at the start of each frame to prepare the screen for drawing.
     pub fn start_frame(&mut self) {
         unsafe {
-            sceGuStart(GuContextType::Direct, &mut LIST as *mut _ as *mut c_void);
+            sceGuStart(GuContextType::Direct, self.list.as_mut_ptr() as *mut c_void);
         }
     }
 
     /// Must be called at the end of each frame to actually display the frame to screen.
     pub fn end_frame(&mut self) {
+        // Draws what was submitted since a target was bound into it, then the rest onto the screen.
+        self.set_target(None);
+        self.queue.flush();
         unsafe {
+            // Draws the debug text queued during the frame, on top of everything else.
+            sceGuDebugFlush();
             sceGuFinish();
             sceGuSync(GuSyncMode::Finish, GuSyncBehavior::Wait);
-            sceDisplayWaitVblankStart();
+            if let Some(update) = self.dialog_update.take() {
+                update();
+            }
+            if self.vsync_interval > 0 {
+                // Only waits for the vblanks the frame didn't already take, so a slow frame isn't delayed
+                // further.
+                let target = self.last_vblank.wrapping_add(self.vsync_interval);
+                while (sceDisplayGetVcount().wrapping_sub(target) as i32) < 0 {
+                    sceDisplayWaitVblankStart();
+                }
+            }
+            self.last_vblank = sceDisplayGetVcount();
             sceKernelDcacheWritebackInvalidateAll();
-            sceGuSwapBuffers();
+            self.draw_buffer = sceGuSwapBuffers();
+            FRAMES = FRAMES.wrapping_add(1);
         }
     }
 
+    /// Runs `update` once the current frame is drawn, before it is shown. Utility dialogs such as the on-screen
+    /// keyboard draw themselves over the frame this way, and must be updated in every frame while open.
+    pub(crate) fn set_dialog_update(&mut self, update: fn()) {
+        self.dialog_update = Some(update);
+    }
+
+    pub fn get_vsync_interval(&self) -> u32 {
+        self.vsync_interval
+    }
+
+    /// Sets how many vblanks of the 60 Hz screen each frame lasts at least: `1` (the default) caps the
+    /// game at 60 fps, `2` at 30 fps, and `0` doesn't wait at all, swapping buffers as soon as a frame is
+    /// drawn at the cost of tearing.
+    pub fn set_vsync_interval(&mut self, interval: u32) {
+        self.vsync_interval = interval;
+    }
+
     /// Clears the screen to a specified color.
     pub fn clear(&mut self, color: Color) {
         unsafe {
             sceGuClearColor(color.as_abgr());
             sceGuClearDepth(0);
-            sceGuClear(
-                ClearBuffer::COLOR_BUFFER_BIT
-                    | ClearBuffer::DEPTH_BUFFER_BIT
-                    | ClearBuffer::STENCIL_BUFFER_BIT,
-            );
+            let mut buffers = ClearBuffer::COLOR_BUFFER_BIT | ClearBuffer::STENCIL_BUFFER_BIT;
+            // Without a depth buffer, clearing it would write over the start of VRAM.
+            if self.target_bound || self.depth_buffer.is_some() {
+                buffers |= ClearBuffer::DEPTH_BUFFER_BIT;
+            }
+            sceGuClear(buffers);
         }
     }
 
+    /// Sets how what follows is combined with what is already drawn.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        unsafe { apply_blend_mode(blend_mode) };
+    }
+
     /// Terminates the script, must only be called once at the end of the project.
     pub fn terminate(&mut self) {
         unsafe {
@@ -134,11 +384,185 @@ impl Canvas {
         }
     }
 
-    /// Prints a debug text to screen.
-    pub fn debug_print(&mut self, text: *const u8) {
+    /// Queues `drawable` to be drawn by `Canvas::end_frame`, or by `Canvas::set_target` when switching targets,
+    /// sorted by `layer` and depth along with the other submitted drawables, see `RenderQueue`. Drawables drawn
+    /// directly show under the submitted ones.
+    pub fn submit<D: Drawable + 'static>(&mut self, drawable: D, layer: i32, opacity: Opacity) {
+        self.queue.submit(drawable, layer, opacity);
+    }
+
+    /// Draws the drawables submitted so far, rather than when the frame ends, so what follows is drawn over
+    /// them.
+    pub fn flush(&mut self) {
+        self.queue.flush();
+    }
+
+    /// Draws what follows into `target` instead of the screen, `None` going back to the screen. Drawing into a
+    /// target gives its pixels full alpha, the rest staying as cleared, so `Canvas::clear` with a transparent
+    /// color leaves the undrawn parts transparent. The target must not be bound while its texture is drawn.
+    ///
+    /// The drawables submitted so far are drawn first, into the target they were submitted for.
+    pub fn set_target(&mut self, target: Option<&RenderTarget>) {
+        if target.is_some() || self.target_bound {
+            self.queue.flush();
+        }
         unsafe {
-            sceGuDebugPrint(10, 10, Colors::WHITE.as_color().as_abgr(), text);
-            sceGuDebugFlush();
+            match target {
+                Some(target) => {
+                    sceGuDrawBufferList(target.format(), target.pixels(), target.stride() as i32);
+                    sceGuDepthBuffer(target.depth(), target.stride() as i32);
+                    set_viewport(target.width(), target.height());
+
+                    // The alpha of the framebuffer is the stencil value, so it is set wherever something is drawn.
+                    sceGuEnable(GuState::StencilTest);
+                    sceGuStencilFunc(StencilFunction::Always, 0xff, 0xff);
+                    sceGuStencilOp(
+                        StencilOperation::Keep,
+                        StencilOperation::Keep,
+                        StencilOperation::Replace,
+                    );
+                    self.target_bound = true;
+                }
+                None if self.target_bound => {
+                    if let Some(format) = self.format.as_display() {
+                        sceGuDrawBufferList(format, self.draw_buffer, BUF_WIDTH as i32);
+                    }
+                    if let Some(depth_buffer) = self.depth_buffer {
+                        sceGuDepthBuffer(depth_buffer, BUF_WIDTH as i32);
+                    }
+                    set_viewport(SCREEN_WIDTH, SCREEN_HEIGHT);
+                    sceGuDisable(GuState::StencilTest);
+                    // The GE may still hold texels of the target from before it was drawn into.
+                    sceGuTexFlush();
+                    self.target_bound = false;
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Draws what follows as seen by `camera`, `None` going back to screen coordinates, such as for a HUD
+    /// drawn over the world. Must be set again after `Canvas::start_frame` for the camera's changes to show.
+    /// Submitted drawables keep the camera set when they were submitted.
+    pub fn set_camera(&mut self, camera: Option<&Camera2D>) {
+        let view = camera.map_or(IDENTITY, Camera2D::view_matrix);
+        unsafe { load_view(&view) };
+        self.queue.set_view(view);
+    }
+
+    /// Reads back the frame currently on screen, the last one ended with `Canvas::end_frame`, as an opaque
+    /// `480`x`272` image.
+    pub fn screenshot(&self) -> Image {
+        let displayed = self
+            .framebuffers
+            .iter()
+            .find(|buffer| buffer.from_zero as *mut c_void != self.draw_buffer)
+            .unwrap_or(&self.framebuffers[0]);
+
+        let mut pixels = Vec::with_capacity((SCREEN_WIDTH * SCREEN_HEIGHT * 4) as usize);
+        unsafe {
+            // The GE writes to VRAM behind the data cache, which may still hold older pixels.
+            sceKernelDcacheWritebackInvalidateAll();
+            for y in 0..SCREEN_HEIGHT {
+                // Rows are `BUF_WIDTH` pixels apart, `Psm8888` pixels being stored as R, G, B and A bytes.
+                let row = displayed.direct.add(self.format.bytes_for(y * BUF_WIDTH));
+                for x in 0..SCREEN_WIDTH as usize {
+                    // The alpha of the framebuffer is whatever the stencil held, not a transparency.
+                    let [r, g, b, _] = match self.format {
+                        PixelFormat::Psm8888 => {
+                            let pixel = row.add(x * 4);
+                            [*pixel, *pixel.add(1), *pixel.add(2), 255]
+                        }
+                        format => unpack_16bit(*(row as *const u16).add(x), format),
+                    };
+                    pixels.extend_from_slice(&[r, g, b, 255]);
+                }
+            }
+        }
+
+        Image {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            pixels,
+        }
+    }
+
+    /// Encodes the frame on screen in `format` and writes it to `path`, such as `ms0:/PICTURE/shot.png`.
+    pub fn save_screenshot(&self, path: &str, format: ImageFormat) -> Result<(), IoError> {
+        File::write(path, &self.screenshot().encode(format))
+    }
+
+    /// Prints a debug text to screen, drawn when the frame ends. See `graphics::debug` and the `debug_text!`
+    /// macro to choose its position and color or format it.
+    pub fn debug_print(&mut self, text: &str) {
+        debug::print(10, 10, Colors::WHITE.as_color(), text);
+    }
+}
+
+unsafe fn apply_blend_mode(blend_mode: BlendMode) {
+    match blend_mode {
+        BlendMode::None => {
+            sceGuDisable(GuState::Blend);
+            return;
+        }
+        BlendMode::Alpha => sceGuBlendFunc(
+            BlendOp::Add,
+            BlendFactor::SrcAlpha,
+            BlendFactor::OneMinusSrcAlpha,
+            0,
+            0,
+        ),
+        BlendMode::Additive => sceGuBlendFunc(
+            BlendOp::Add,
+            BlendFactor::SrcAlpha,
+            BlendFactor::Fix,
+            0,
+            0xffffff,
+        ),
+        // As the source factor, `Color` is the color already on screen.
+        BlendMode::Multiply => {
+            sceGuBlendFunc(BlendOp::Add, BlendFactor::Color, BlendFactor::Fix, 0, 0)
         }
     }
+    sceGuEnable(GuState::Blend);
+}
+
+/// View matrix drawing in screen coordinates.
+#[rustfmt::skip]
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+/// Loads `view`, in the column-major order of `Camera2D::view_matrix`, as the view matrix.
+pub(crate) unsafe fn load_view(view: &[f32; 16]) {
+    let column = |i: usize| ScePspFVector4 {
+        x: view[i * 4],
+        y: view[i * 4 + 1],
+        z: view[i * 4 + 2],
+        w: view[i * 4 + 3],
+    };
+    sceGumMatrixMode(MatrixMode::View);
+    sceGumLoadMatrix(&ScePspFMatrix4 {
+        x: column(0),
+        y: column(1),
+        z: column(2),
+        w: column(3),
+    });
+    sceGumMatrixMode(MatrixMode::Model);
+}
+
+/// Maps drawing coordinates to a `width` by `height` area of the framebuffer, `(0, 0)` being its top-left
+/// corner.
+unsafe fn set_viewport(width: u32, height: u32) {
+    sceGuOffset(2048 - (width / 2), 2048 - (height / 2));
+    sceGuViewport(2048, 2048, width as i32, height as i32);
+    sceGuScissor(0, 0, width as i32, height as i32);
+
+    sceGumMatrixMode(MatrixMode::Projection);
+    sceGumLoadIdentity();
+    sceGumOrtho(0.0, width as f32, height as f32, 0.0, -10.0, 10.0);
+    sceGumMatrixMode(MatrixMode::Model);
 }
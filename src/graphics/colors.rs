@@ -1,3 +1,7 @@
+use psp::math;
+
+use crate::core::Vec2;
+
 /// List of some preset colors, to be used when `Color` is requested, you must utilize it the following way:
 /// `Colors::RED.as_color()`.
 pub enum Colors {
@@ -35,7 +39,7 @@ impl Colors {
 }
 
 /// Defines what a color is in RGBA8.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     r: u8,
     g: u8,
@@ -54,7 +58,173 @@ impl Color {
         }
     }
 
+    /// Returns the color between `self` and `other` at `t`, where `0.0` is `self` and `1.0` is `other`.
+    pub fn lerp(&self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8;
+        Color::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
     pub(crate) fn as_abgr(&self) -> u32 {
         (self.a as u32) << 24 | (self.b as u32) << 16 | (self.g as u32) << 8 | (self.r as u32)
     }
 }
+
+/// Describes how colors are spread over the vertices of a drawable. Points are relative to the drawable's
+/// bounds, `(0.0, 0.0)` being its top-left corner and `(1.0, 1.0)` its bottom-right corner.
+///
+/// Colors are only evaluated at each vertex and smoothly shaded in between, so a `Rect` (4 vertices) shows
+/// linear gradients well but needs an `Ellipse` for a proper radial one.
+#[derive(Clone, Copy, Debug)]
+pub enum Gradient {
+    /// Goes from `start_color` at `start` to `end_color` at `end`.
+    Linear {
+        start: Vec2<f32>,
+        end: Vec2<f32>,
+        start_color: Color,
+        end_color: Color,
+    },
+    /// Goes from `inner_color` at `center` to `outer_color` at `radius` distance from it.
+    Radial {
+        center: Vec2<f32>,
+        radius: f32,
+        inner_color: Color,
+        outer_color: Color,
+    },
+}
+
+impl Gradient {
+    /// Returns a linear gradient from `start` to `end`.
+    pub fn linear(start: Vec2<f32>, end: Vec2<f32>, start_color: Color, end_color: Color) -> Self {
+        Gradient::Linear {
+            start,
+            end,
+            start_color,
+            end_color,
+        }
+    }
+
+    /// Returns a radial gradient centered in `center`.
+    pub fn radial(center: Vec2<f32>, radius: f32, inner_color: Color, outer_color: Color) -> Self {
+        Gradient::Radial {
+            center,
+            radius,
+            inner_color,
+            outer_color,
+        }
+    }
+
+    /// Returns the color of the gradient at `point`, relative to the drawable's bounds.
+    pub fn color_at(&self, point: Vec2<f32>) -> Color {
+        match *self {
+            Gradient::Linear {
+                start,
+                end,
+                start_color,
+                end_color,
+            } => {
+                let direction = Vec2::new(end.x - start.x, end.y - start.y);
+                let length = direction.x * direction.x + direction.y * direction.y;
+                if length == 0.0 {
+                    return start_color;
+                }
+                let t = ((point.x - start.x) * direction.x + (point.y - start.y) * direction.y)
+                    / length;
+                start_color.lerp(end_color, t)
+            }
+            Gradient::Radial {
+                center,
+                radius,
+                inner_color,
+                outer_color,
+            } => {
+                if radius <= 0.0 {
+                    return outer_color;
+                }
+                let (dx, dy) = (point.x - center.x, point.y - center.y);
+                inner_color.lerp(outer_color, math::sqrtf(dx * dx + dy * dy) / radius)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    const WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    };
+
+    fn gray(level: u8) -> Color {
+        Color::new(level, level, level, 255)
+    }
+
+    #[test]
+    fn lerp() {
+        let clear = Color::new(255, 0, 100, 0);
+        assert_eq!(BLACK.lerp(clear, 0.0), BLACK);
+        assert_eq!(BLACK.lerp(clear, 1.0), clear);
+        assert_eq!(BLACK.lerp(clear, 0.5), Color::new(128, 0, 50, 128));
+        assert_eq!(BLACK.lerp(clear, -1.0), BLACK);
+        assert_eq!(BLACK.lerp(clear, 2.0), clear);
+    }
+
+    #[test]
+    fn linear() {
+        let gradient = Gradient::linear(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), BLACK, WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(0.0, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(1.0, 0.0)), WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 0.0)), gray(128));
+        // Only the distance along the gradient matters.
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 1.0)), gray(128));
+        assert_eq!(gradient.color_at(Vec2::new(0.25, -3.0)), gray(64));
+
+        // Diagonal, from the bottom-right corner to the top-left one.
+        let gradient = Gradient::linear(Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.0), BLACK, WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(1.0, 0.0)), gray(128));
+        assert_eq!(gradient.color_at(Vec2::new(0.0, 0.0)), WHITE);
+    }
+
+    #[test]
+    fn linear_clamps_outside() {
+        let gradient = Gradient::linear(Vec2::new(0.25, 0.0), Vec2::new(0.75, 0.0), BLACK, WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(0.0, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(-5.0, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(1.0, 0.0)), WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(5.0, 0.0)), WHITE);
+
+        // Without a direction, everything is the start color.
+        let gradient = Gradient::linear(Vec2::new(0.5, 0.5), Vec2::new(0.5, 0.5), BLACK, WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(1.0, 1.0)), BLACK);
+    }
+
+    #[test]
+    fn radial() {
+        let gradient = Gradient::radial(Vec2::new(0.5, 0.5), 0.5, WHITE, BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 0.5)), WHITE);
+        assert_eq!(gradient.color_at(Vec2::new(1.0, 0.5)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 0.75)), gray(128));
+        // Corners are further than the radius.
+        assert_eq!(gradient.color_at(Vec2::new(0.0, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(3.0, -2.0)), BLACK);
+
+        let gradient = Gradient::radial(Vec2::new(0.5, 0.5), 0.0, WHITE, BLACK);
+        assert_eq!(gradient.color_at(Vec2::new(0.5, 0.5)), BLACK);
+    }
+}
@@ -0,0 +1,298 @@
+extern crate alloc;
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+use core::fmt;
+
+use psp::sys::sceGuDebugPrint;
+
+use crate::core::{
+    input::{Buttons, InputManager},
+    utils, Vec2, Vec3,
+};
+use crate::graphics::{colors::Color, primitives::Primitive::Rect, Drawable};
+
+/// Width and height of the characters of the debug font, in pixels.
+pub const CHAR_SIZE: i32 = 8;
+
+/// Characters handed to the PSP at once, the rest of a line following in further calls.
+const CHUNK: usize = 64;
+
+/// Queues formatted text to be drawn with the debug font, as `debug::print` does.
+///
+/// `debug_text!(10, 10, Colors::WHITE.as_color(), "fps {}", fps);`
+#[macro_export]
+macro_rules! debug_text {
+    ($x:expr, $y:expr, $color:expr, $($arg:tt)*) => {
+        $crate::graphics::debug::print_fmt($x, $y, $color, ::core::format_args!($($arg)*))
+    };
+}
+
+/// Queues `text` to be drawn with the debug font at `x`, `y`. Everything queued during a frame is drawn
+/// at once by `Canvas::end_frame`, on top of the rest. Lines are split on `\n`, and characters the font
+/// doesn't have are shown as `?`.
+pub fn print(x: i32, y: i32, color: Color, text: &str) {
+    let color = color.as_abgr();
+    for (row, line) in text.split('\n').enumerate() {
+        let y = y + row as i32 * CHAR_SIZE;
+        // The PSP expects NUL-terminated strings, so lines are copied in chunks.
+        let mut buffer = [0u8; CHUNK + 1];
+        let mut length = 0;
+        let mut column = 0;
+        for character in line.chars().filter(|&c| c != '\r') {
+            buffer[length] = if character.is_ascii() && !character.is_ascii_control() {
+                character as u8
+            } else {
+                b'?'
+            };
+            length += 1;
+            if length == CHUNK {
+                unsafe { sceGuDebugPrint(x + column * CHAR_SIZE, y, color, buffer.as_ptr()) };
+                column += CHUNK as i32;
+                length = 0;
+            }
+        }
+        if length > 0 {
+            buffer[length] = 0;
+            unsafe { sceGuDebugPrint(x + column * CHAR_SIZE, y, color, buffer.as_ptr()) };
+        }
+    }
+}
+
+/// Queues formatted text to be drawn with the debug font, see `debug::print` and the `debug_text!` macro.
+pub fn print_fmt(x: i32, y: i32, color: Color, args: fmt::Arguments) {
+    match args.as_str() {
+        Some(text) => print(x, y, color, text),
+        None => print(x, y, color, &alloc::fmt::format(args)),
+    }
+}
+
+/// A scrolling log drawn with the debug font, showing the lines written to it and the output of
+/// `utils::stdout`. Only one console should exist at a time, as the newest one takes the output.
+pub struct Console {
+    lines: VecDeque<String>,
+    /// Whether the last line is still being written, not having ended with `\n` yet.
+    open: bool,
+    position: Vec2<i32>,
+    columns: usize,
+    rows: usize,
+    color: Color,
+    background: Option<Rect>,
+    visible: bool,
+    chord: Vec<Buttons>,
+}
+
+impl Console {
+    /// Returns a visible console filling `size` pixels from `position`, its top-left corner. It is toggled
+    /// by pressing `L`, `R` and `Select` together.
+    pub fn new(position: Vec2<i32>, size: Vec2<i32>, color: Color) -> Self {
+        utils::set_stdout_mirror(true);
+        Self {
+            lines: VecDeque::new(),
+            open: false,
+            position,
+            columns: (size.x / CHAR_SIZE).max(1) as usize,
+            rows: (size.y / CHAR_SIZE).max(1) as usize,
+            color,
+            background: None,
+            visible: true,
+            chord: alloc::vec![Buttons::LTrigger, Buttons::RTrigger, Buttons::Select],
+        }
+    }
+
+    /// Adds `text` to the log, lines longer than the console being wrapped.
+    pub fn print(&mut self, text: &str) {
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.open = false;
+            }
+            if !self.open {
+                self.lines.push_back(String::new());
+                self.open = true;
+            }
+            for character in line.chars().filter(|&c| c != '\r') {
+                if self.lines.back().map_or(0, |last| last.chars().count()) >= self.columns {
+                    self.lines.push_back(String::new());
+                }
+                if let Some(last) = self.lines.back_mut() {
+                    last.push(character);
+                }
+            }
+        }
+        // A trailing `\n` ends the line without starting the next one yet.
+        if text.ends_with('\n') {
+            if let Some(last) = self.lines.back() {
+                if last.is_empty() {
+                    self.lines.pop_back();
+                }
+            }
+            self.open = false;
+        }
+        while self.lines.len() > self.rows {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.open = false;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Sets the buttons that toggle the console when held together, none disabling the toggle.
+    pub fn set_toggle_chord(&mut self, buttons: &[Buttons]) {
+        self.chord = buttons.to_vec();
+    }
+
+    pub fn get_color(&self) -> Color {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Fills the console with `color` behind the text, `None` leaving it transparent.
+    pub fn set_background(&mut self, color: Option<Color>) {
+        self.background = color.map(|color| {
+            Rect::new(
+                Vec3::new(self.position.x as f32, self.position.y as f32, -1.0),
+                Vec2::new(
+                    (self.columns as i32 * CHAR_SIZE) as f32,
+                    (self.rows as i32 * CHAR_SIZE) as f32,
+                ),
+                color,
+            )
+        });
+    }
+
+    /// Takes the output of `utils::stdout` and toggles the console if its button chord was just pressed.
+    /// Must be called every frame, after `InputManager::update`.
+    pub fn update(&mut self, input: &mut InputManager) {
+        self.mirror();
+
+        if self.chord.is_empty() {
+            return;
+        }
+        let held = self
+            .chord
+            .iter()
+            .all(|button| input.is_key_down(button.clone()));
+        let pressed = self
+            .chord
+            .iter()
+            .any(|button| input.is_key_down_changed(button.clone()));
+        if held && pressed {
+            self.toggle();
+        }
+    }
+
+    /// Queues the last lines of the log to be drawn, if the console is visible.
+    pub fn draw(&mut self) {
+        self.mirror();
+        if !self.visible {
+            return;
+        }
+
+        if let Some(background) = &mut self.background {
+            background.draw();
+        }
+        for (row, line) in self.lines.iter().enumerate() {
+            print(
+                self.position.x,
+                self.position.y + row as i32 * CHAR_SIZE,
+                self.color,
+                line,
+            );
+        }
+    }
+
+    fn mirror(&mut self) {
+        if let Some(output) = utils::take_stdout_mirror() {
+            self.print(&output);
+        }
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.print(text);
+        Ok(())
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        utils::set_stdout_mirror(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a console of 4 columns and 3 rows.
+    fn console() -> Console {
+        Console::new(
+            Vec2::new(0, 0),
+            Vec2::new(4 * CHAR_SIZE, 3 * CHAR_SIZE),
+            Color::new(255, 255, 255, 255),
+        )
+    }
+
+    fn lines(console: &Console) -> Vec<&str> {
+        console.lines.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let mut console = console();
+        console.print("abcdefghij");
+        assert_eq!(lines(&console), ["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn continues_open_lines() {
+        let mut console = console();
+        console.print("ab");
+        console.print("c\r\nd");
+        console.print("e\n");
+        assert_eq!(lines(&console), ["abc", "de"]);
+
+        // A trailing `\n` doesn't start an empty line, but empty lines in between are kept.
+        console.print("\nf\n");
+        assert_eq!(lines(&console), ["de", "", "f"]);
+    }
+
+    #[test]
+    fn scrolls_old_lines_away() {
+        let mut console = console();
+        for line in ["1", "2", "3", "4", "5"] {
+            console.print(line);
+            console.print("\n");
+        }
+        assert_eq!(lines(&console), ["3", "4", "5"]);
+        console.print("abcdefgh");
+        assert_eq!(lines(&console), ["5", "abcd", "efgh"]);
+
+        console.clear();
+        console.print("x");
+        assert_eq!(lines(&console), ["x"]);
+    }
+
+    #[test]
+    fn keeps_multibyte_characters() {
+        let mut console = console();
+        console.print("ééééé");
+        assert_eq!(lines(&console), ["éééé", "é"]);
+    }
+}
@@ -0,0 +1,274 @@
+//! BMP decoding and encoding. Decoding supports uncompressed 1, 4, 8, 16, 24 and 32-bit images, including
+//! `BI_BITFIELDS` masks and top-down images; RLE compression is reported as unsupported. Encoding writes
+//! 32-bit images keeping the alpha channel.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::{check_dimensions, Image, ImageError};
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Decodes a BMP file into an RGBA8 image.
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(b"BM") {
+        return Err(ImageError::UnknownFormat);
+    }
+    if data.len() < 18 {
+        return Err(ImageError::Truncated);
+    }
+    let pixel_offset = read_u32(data, 10) as usize;
+    let info_size = read_u32(data, 14) as usize;
+    if info_size < 40 {
+        return Err(ImageError::Unsupported("BMP core header"));
+    }
+    // Sizes and offsets come from the file, so they may overflow on the PSP's 32-bit `usize`.
+    let info_end = info_size.checked_add(14).ok_or(ImageError::Truncated)?;
+    let info = data.get(14..info_end).ok_or(ImageError::Truncated)?;
+
+    let width = read_u32(info, 4) as i32;
+    let height = read_u32(info, 8) as i32;
+    let bit_count = u16::from_le_bytes([info[14], info[15]]);
+    let compression = read_u32(info, 16);
+    let colors_used = read_u32(info, 32) as usize;
+
+    // A negative height means rows are stored from the top instead of from the bottom.
+    let top_down = height < 0;
+    let (width, height) = (width.unsigned_abs(), height.unsigned_abs());
+    check_dimensions(width, height)?;
+
+    let masks = match (compression, bit_count) {
+        (BI_RGB, 16) => [0x7c00, 0x03e0, 0x001f, 0],
+        (BI_RGB, 24 | 32) => [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0],
+        (BI_RGB, 1 | 4 | 8) => [0; 4],
+        (BI_BITFIELDS | BI_ALPHABITFIELDS, 16 | 32) => {
+            // Masks right after the 40-byte header, either following it or as part of a bigger one.
+            let masks_at = 14 + 40;
+            let alpha = if info_size >= 56 || compression == BI_ALPHABITFIELDS {
+                read_u32(
+                    data.get(masks_at..masks_at + 16)
+                        .ok_or(ImageError::Truncated)?,
+                    12,
+                )
+            } else {
+                0
+            };
+            let colors = data
+                .get(masks_at..masks_at + 12)
+                .ok_or(ImageError::Truncated)?;
+            [
+                read_u32(colors, 0),
+                read_u32(colors, 4),
+                read_u32(colors, 8),
+                alpha,
+            ]
+        }
+        (BI_RGB | BI_BITFIELDS, _) => return Err(ImageError::Corrupt),
+        _ => return Err(ImageError::Unsupported("compressed BMP")),
+    };
+
+    let palette: Vec<[u8; 4]> = if bit_count <= 8 {
+        let count = if colors_used == 0 {
+            1 << bit_count
+        } else {
+            colors_used.min(256)
+        };
+        let end = count
+            .checked_mul(4)
+            .and_then(|size| info_end.checked_add(size))
+            .ok_or(ImageError::Truncated)?;
+        data.get(info_end..end)
+            .ok_or(ImageError::Truncated)?
+            .chunks_exact(4)
+            .map(|color| [color[2], color[1], color[0], 255])
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Rows are padded to a multiple of 4 bytes.
+    let row_bytes = (width as usize * bit_count as usize).div_ceil(32) * 4;
+    let rows_end = row_bytes
+        .checked_mul(height as usize)
+        .and_then(|size| pixel_offset.checked_add(size))
+        .ok_or(ImageError::Truncated)?;
+    let rows = data
+        .get(pixel_offset..rows_end)
+        .ok_or(ImageError::Truncated)?;
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height as usize {
+        let source_y = if top_down { y } else { height as usize - 1 - y };
+        let row = &rows[source_y * row_bytes..(source_y + 1) * row_bytes];
+        for x in 0..width as usize {
+            let rgba = match bit_count {
+                1 | 4 | 8 => {
+                    let bit = x * bit_count as usize;
+                    let index = (row[bit / 8] >> (8 - bit_count as usize - bit % 8))
+                        & ((1u16 << bit_count) - 1) as u8;
+                    *palette.get(index as usize).ok_or(ImageError::Corrupt)?
+                }
+                16 => from_masks(
+                    u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32,
+                    &masks,
+                ),
+                24 => [row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 255],
+                _ => from_masks(read_u32(row, x * 4), &masks),
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Encodes an RGBA8 image into a 32-bit BMP file with a `BITMAPV4HEADER`, so the alpha channel is kept.
+pub fn encode(image: &Image) -> Vec<u8> {
+    const HEADERS_SIZE: u32 = 14 + 108;
+    let pixels_size = image.width * image.height * 4;
+
+    let mut data = Vec::with_capacity((HEADERS_SIZE + pixels_size) as usize);
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&(HEADERS_SIZE + pixels_size).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&HEADERS_SIZE.to_le_bytes());
+
+    for value in [
+        108,
+        image.width,
+        image.height,
+        // Planes and bits per pixel.
+        1 | 32 << 16,
+        BI_BITFIELDS,
+        pixels_size,
+        // 72 DPI, in pixels per meter.
+        2835,
+        2835,
+        0,
+        0,
+        0x00ff_0000,
+        0x0000_ff00,
+        0x0000_00ff,
+        0xff00_0000,
+        // `LCS_sRGB`.
+        0x7352_4742,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    // Unused endpoints and gamma.
+    data.resize(HEADERS_SIZE as usize, 0);
+
+    // Rows are stored from the bottom, as BGRA.
+    let row = image.width as usize * 4;
+    for pixels in image
+        .pixels
+        .chunks_exact(row)
+        .take(image.height as usize)
+        .rev()
+    {
+        for pixel in pixels.chunks_exact(4) {
+            data.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+    data
+}
+
+/// Extracts each channel of `pixel` with its mask, scaling it to 8 bits. A zero alpha mask means opaque.
+fn from_masks(pixel: u32, masks: &[u32; 4]) -> [u8; 4] {
+    let mut rgba = [0, 0, 0, 255];
+    for (channel, &mask) in rgba.iter_mut().zip(masks) {
+        if mask == 0 {
+            continue;
+        }
+        let value = ((pixel & mask) >> mask.trailing_zeros()) as u64;
+        let max = (mask >> mask.trailing_zeros()) as u64;
+        *channel = ((value * 255 + max / 2) / max) as u8;
+    }
+    rgba
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a `width` by `height` image whose pixels all differ, alpha included.
+    fn pattern(width: u32, height: u32) -> Image {
+        let pixels = (0..width * height)
+            .flat_map(|i| [i as u8, (i >> 8) as u8, (i * 7) as u8, (i * 13 + 1) as u8])
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn bottom_up_24_bit() {
+        // Rows of 9 bytes padded to 12, stored from the bottom.
+        let image = decode(include_bytes!("fixtures/rgb24.bmp")).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255,
+            10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255,
+        ]);
+    }
+
+    #[test]
+    fn top_down_32_bit_bitfields() {
+        // Masks for RGBA byte order instead of the usual BGRA.
+        let image = decode(include_bytes!("fixtures/bitfields32.bmp")).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            1, 2, 3, 4, 250, 251, 252, 253,
+            0, 128, 255, 128, 9, 8, 7, 0,
+        ]);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = include_bytes!("fixtures/rgb24.bmp");
+        for len in [2, 20, data.len() - 1] {
+            assert_eq!(decode(&data[..len]).unwrap_err(), ImageError::Truncated);
+        }
+    }
+
+    #[test]
+    fn offsets_past_the_end() {
+        let data = include_bytes!("fixtures/rgb24.bmp");
+        for offset in [10, 14] {
+            let mut data = data.to_vec();
+            data[offset..offset + 4].copy_from_slice(&0xffff_ffffu32.to_le_bytes());
+            assert_eq!(decode(&data).unwrap_err(), ImageError::Truncated);
+        }
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for (width, height) in [(1, 1), (5, 3), (7, 1), (33, 17)] {
+            let image = pattern(width, height);
+            let data = encode(&image);
+            assert_eq!(read_u32(&data, 2) as usize, data.len());
+            let decoded = decode(&data).unwrap();
+            assert_eq!((decoded.width, decoded.height), (width, height));
+            assert_eq!(decoded.pixels, image.pixels);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Decompression of DEFLATE streams (RFC 1951) and their zlib wrapper (RFC 1950), as used by PNG. Decoding
+//! follows zlib's `puff`, trading speed for a small and easy to check implementation.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::ImageError;
+
+const MAX_BITS: usize = 15;
+const MAX_LITERAL_CODES: usize = 288;
+const MAX_DISTANCE_CODES: usize = 30;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which the code lengths of the code length alphabet are stored in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `count` bits (up to 16), least significant first.
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        while self.count < count {
+            let byte = *self.data.get(self.position).ok_or(ImageError::Truncated)?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << count) - 1);
+        self.buffer >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code, described by how many codes have each length and the symbols sorted by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: [u16; MAX_LITERAL_CODES],
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut huffman = Huffman {
+            counts: [0; MAX_BITS + 1],
+            symbols: [0; MAX_LITERAL_CODES],
+        };
+        for &length in lengths {
+            huffman.counts[length as usize] += 1;
+        }
+        if huffman.counts[0] as usize == lengths.len() {
+            // No codes at all, only valid for a distance code that is never used.
+            return Ok(huffman);
+        }
+
+        // Checks the code isn't over-subscribed. Incomplete codes are allowed, as zlib does.
+        let mut left: i32 = 1;
+        for length in 1..=MAX_BITS {
+            left <<= 1;
+            left -= huffman.counts[length] as i32;
+            if left < 0 {
+                return Err(ImageError::Corrupt);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + huffman.counts[length];
+        }
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                huffman.symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(huffman)
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(ImageError::Corrupt)
+    }
+}
+
+/// Decompresses a raw DEFLATE stream.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::with_capacity(data.len() * 4);
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored(&mut reader, &mut output)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                codes(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                codes(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(ImageError::Corrupt),
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+/// Decompresses a zlib stream, checking its header and Adler-32 checksum.
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 6 {
+        return Err(ImageError::Truncated);
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(ImageError::Corrupt);
+    }
+    if flg & 0x20 != 0 {
+        return Err(ImageError::Unsupported("zlib preset dictionary"));
+    }
+
+    let output = inflate(&data[2..])?;
+    let expected = u32::from_be_bytes([
+        data[data.len() - 4],
+        data[data.len() - 3],
+        data[data.len() - 2],
+        data[data.len() - 1],
+    ]);
+    if adler32(&output) != expected {
+        return Err(ImageError::Corrupt);
+    }
+    Ok(output)
+}
+
+/// Computes the Adler-32 checksum used by zlib.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that can't overflow before taking the modulo.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn stored(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), ImageError> {
+    reader.align();
+    let start = reader.position;
+    let header = reader
+        .data
+        .get(start..start + 4)
+        .ok_or(ImageError::Truncated)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if length != !complement {
+        return Err(ImageError::Corrupt);
+    }
+
+    let block = reader
+        .data
+        .get(start + 4..start + 4 + length as usize)
+        .ok_or(ImageError::Truncated)?;
+    output.extend_from_slice(block);
+    reader.position = start + 4 + length as usize;
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), ImageError> {
+    let mut lengths = [0u8; MAX_LITERAL_CODES];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    Ok((
+        Huffman::new(&lengths)?,
+        Huffman::new(&[5; MAX_DISTANCE_CODES])?,
+    ))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > MAX_DISTANCE_CODES {
+        return Err(ImageError::Corrupt);
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = [0u8; 286 + MAX_DISTANCE_CODES];
+    let total = literal_count + distance_count;
+    let mut id = 0;
+    while id < total {
+        let symbol = code_length_code.decode(reader)?;
+        if symbol < 16 {
+            lengths[id] = symbol as u8;
+            id += 1;
+            continue;
+        }
+
+        let (value, repeat) = match symbol {
+            16 => {
+                if id == 0 {
+                    return Err(ImageError::Corrupt);
+                }
+                (lengths[id - 1], 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        if id + repeat as usize > total {
+            return Err(ImageError::Corrupt);
+        }
+        for _ in 0..repeat {
+            lengths[id] = value;
+            id += 1;
+        }
+    }
+    if lengths[256] == 0 {
+        // Without an end-of-block code the block could never finish.
+        return Err(ImageError::Corrupt);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..total])?,
+    ))
+}
+
+fn codes(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                if symbol >= LENGTH_BASE.len() {
+                    return Err(ImageError::Corrupt);
+                }
+                let length = LENGTH_BASE[symbol] as usize
+                    + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = distances.decode(reader)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(ImageError::Corrupt);
+                }
+                let distance = DISTANCE_BASE[symbol] as usize
+                    + reader.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(ImageError::Corrupt);
+                }
+
+                // Copies byte by byte, as the match may overlap the bytes it produces.
+                let start = output.len() - distance;
+                for id in 0..length {
+                    output.push(output[start + id]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Decoding of PNG, BMP and TGA files into RGBA8 images, and encoding of RGBA8 images into PNG and BMP
+//! files. Nothing here touches the hardware, so it runs the same on the PSP and on the host.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// The `bmp` module decodes Windows bitmaps.
+pub mod bmp;
+/// The `inflate` module decompresses the DEFLATE/zlib data PNG images are stored with.
+pub mod inflate;
+/// The `png` module decodes Portable Network Graphics images.
+pub mod png;
+/// The `tga` module decodes Truevision TGA images.
+pub mod tga;
+
+/// Largest width and height an image may have, to avoid exhausting memory on corrupt headers.
+pub const MAX_IMAGE_SIZE: u32 = 4096;
+
+/// Errors that may happen when decoding an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageError {
+    /// The data isn't a PNG, BMP or TGA file.
+    UnknownFormat,
+    /// The data ends before the image does.
+    Truncated,
+    /// The data is malformed, or a checksum doesn't match.
+    Corrupt,
+    /// The file is valid, but uses a variant of the format that isn't supported.
+    Unsupported(&'static str),
+    /// Width or height is zero or larger than `MAX_IMAGE_SIZE`.
+    InvalidSize,
+}
+
+/// A decoded image, its pixels being tightly packed RGBA8 rows from top to bottom.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Formats an image can be encoded to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// Returns the usual file extension of the format, without a dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Bmp => "bmp",
+        }
+    }
+}
+
+impl Image {
+    /// Encodes the image into a file of the given format.
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Png => png::encode(self),
+            ImageFormat::Bmp => bmp::encode(self),
+        }
+    }
+}
+
+/// Decodes a PNG, BMP or TGA file, detecting the format from its contents.
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if data.starts_with(&png::SIGNATURE) {
+        png::decode(data)
+    } else if data.starts_with(b"BM") {
+        bmp::decode(data)
+    } else {
+        tga::decode(data)
+    }
+}
+
+pub(crate) fn check_dimensions(width: u32, height: u32) -> Result<(), ImageError> {
+    if width == 0 || height == 0 || width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        Err(ImageError::InvalidSize)
+    } else {
+        Ok(())
+    }
+}
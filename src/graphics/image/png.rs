@@ -0,0 +1,473 @@
+//! PNG decoding and encoding. Decoding supports every non-interlaced color type and bit depth, including
+//! palettes and `tRNS` transparency; 16-bit channels are reduced to 8 bits. Encoding writes RGBA8 images
+//! without compression, which is fast and simple but makes big files.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::{check_dimensions, inflate, Image, ImageError};
+
+/// The 8 bytes every PNG file starts with.
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const COLOR_GRAY: u8 = 0;
+const COLOR_RGB: u8 = 2;
+const COLOR_PALETTE: u8 = 3;
+const COLOR_GRAY_ALPHA: u8 = 4;
+const COLOR_RGBA: u8 = 6;
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_RGB => 3,
+            COLOR_GRAY_ALPHA => 2,
+            COLOR_RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    /// Bytes in a row of unfiltered data, without the filter type byte.
+    fn row_bytes(&self) -> usize {
+        (self.width as usize * self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+}
+
+/// Decodes a PNG file into an RGBA8 image.
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let mut header = None;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut transparency: Option<&[u8]> = None;
+    let mut compressed = Vec::new();
+
+    let mut position = SIGNATURE.len();
+    loop {
+        let chunk_header = data
+            .get(position..position + 8)
+            .ok_or(ImageError::Truncated)?;
+        let length = read_u32(chunk_header, 0) as usize;
+        let kind = &chunk_header[4..8];
+        // On the PSP a huge length would wrap around the end of the chunk, so it is checked.
+        let end = (position + 12)
+            .checked_add(length)
+            .ok_or(ImageError::Corrupt)?;
+        let body = data
+            .get(position + 8..end - 4)
+            .ok_or(ImageError::Truncated)?;
+        let crc = data.get(end - 4..end).ok_or(ImageError::Truncated)?;
+        if crc32(&data[position + 4..end - 4]) != read_u32(crc, 0) {
+            return Err(ImageError::Corrupt);
+        }
+        position = end;
+
+        match kind {
+            b"IHDR" => {
+                if body.len() < 13 {
+                    return Err(ImageError::Corrupt);
+                }
+                if body[10] != 0 || body[11] != 0 {
+                    return Err(ImageError::Corrupt);
+                }
+                if body[12] != 0 {
+                    return Err(ImageError::Unsupported("interlaced PNG"));
+                }
+                let parsed = Header {
+                    width: read_u32(body, 0),
+                    height: read_u32(body, 4),
+                    bit_depth: body[8],
+                    color_type: body[9],
+                };
+                let valid_depth = match parsed.color_type {
+                    COLOR_GRAY => matches!(parsed.bit_depth, 1 | 2 | 4 | 8 | 16),
+                    COLOR_PALETTE => matches!(parsed.bit_depth, 1 | 2 | 4 | 8),
+                    COLOR_RGB | COLOR_GRAY_ALPHA | COLOR_RGBA => {
+                        matches!(parsed.bit_depth, 8 | 16)
+                    }
+                    _ => false,
+                };
+                if !valid_depth {
+                    return Err(ImageError::Corrupt);
+                }
+                check_dimensions(parsed.width, parsed.height)?;
+                header = Some(parsed);
+            }
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|color| [color[0], color[1], color[2], 255])
+                    .collect();
+            }
+            b"tRNS" => transparency = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {
+                // Ancillary chunks (lowercase first letter) can be skipped, critical ones can't.
+                if kind[0] & 0x20 == 0 {
+                    return Err(ImageError::Unsupported("critical PNG chunk"));
+                }
+            }
+        }
+    }
+
+    let header = header.ok_or(ImageError::Corrupt)?;
+    if header.color_type == COLOR_PALETTE {
+        if palette.is_empty() {
+            return Err(ImageError::Corrupt);
+        }
+        if let Some(alpha) = transparency {
+            for (color, &alpha) in palette.iter_mut().zip(alpha) {
+                color[3] = alpha;
+            }
+        }
+    }
+
+    let mut raw = inflate::zlib_decompress(&compressed)?;
+    unfilter(&header, &mut raw)?;
+    Ok(Image {
+        width: header.width,
+        height: header.height,
+        pixels: to_rgba(&header, &raw, &palette, transparency),
+    })
+}
+
+/// Reverses the per-row filters in place, leaving the rows (each still preceded by its filter byte) as
+/// plain samples.
+fn unfilter(header: &Header, raw: &mut [u8]) -> Result<(), ImageError> {
+    let row_bytes = header.row_bytes();
+    let stride = row_bytes + 1;
+    if raw.len() < stride * header.height as usize {
+        return Err(ImageError::Truncated);
+    }
+    // Distance to the corresponding byte of the previous pixel, at least one byte.
+    let bpp = ((header.channels() * header.bit_depth as usize) / 8).max(1);
+
+    for y in 0..header.height as usize {
+        let (previous, current) = raw.split_at_mut(y * stride);
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&previous[(y - 1) * stride + 1..y * stride])
+        };
+        let filter = current[0];
+        let row = &mut current[1..stride];
+
+        for x in 0..row_bytes {
+            let left = if x >= bpp { row[x - bpp] as i16 } else { 0 };
+            let up = previous.map_or(0, |previous| previous[x] as i16);
+            let up_left = match previous {
+                Some(previous) if x >= bpp => previous[x - bpp] as i16,
+                _ => 0,
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => (left + up) / 2,
+                4 => paeth(left, up, up_left),
+                _ => return Err(ImageError::Corrupt),
+            };
+            row[x] = row[x].wrapping_add(predictor as u8);
+        }
+    }
+    Ok(())
+}
+
+fn paeth(left: i16, up: i16, up_left: i16) -> i16 {
+    let estimate = left + up - up_left;
+    let distance_left = (estimate - left).abs();
+    let distance_up = (estimate - up).abs();
+    let distance_up_left = (estimate - up_left).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn to_rgba(
+    header: &Header,
+    raw: &[u8],
+    palette: &[[u8; 4]],
+    transparency: Option<&[u8]>,
+) -> Vec<u8> {
+    let row_bytes = header.row_bytes();
+    let depth = header.bit_depth as usize;
+    let channels = header.channels();
+    let mut pixels = Vec::with_capacity((header.width * header.height * 4) as usize);
+
+    // Color key from `tRNS` for gray and RGB images, compared at full precision.
+    let key: Option<[u16; 3]> = transparency.and_then(|key| match header.color_type {
+        COLOR_GRAY if key.len() >= 2 => {
+            let gray = read_u16(key, 0);
+            Some([gray, gray, gray])
+        }
+        COLOR_RGB if key.len() >= 6 => Some([read_u16(key, 0), read_u16(key, 2), read_u16(key, 4)]),
+        _ => None,
+    });
+
+    for y in 0..header.height as usize {
+        let row = &raw[y * (row_bytes + 1) + 1..(y + 1) * (row_bytes + 1)];
+        for x in 0..header.width as usize {
+            // Reads the `channel`-th sample of this pixel at full precision.
+            let sample = |channel: usize| -> u16 {
+                let bit = (x * channels + channel) * depth;
+                match depth {
+                    16 => read_u16(row, bit / 8),
+                    8 => row[bit / 8] as u16,
+                    _ => ((row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1)) as u16,
+                }
+            };
+            // Scales a sample to 8 bits.
+            let to_8bit = |value: u16| -> u8 {
+                match depth {
+                    16 => (value >> 8) as u8,
+                    8 => value as u8,
+                    _ => (value * 255 / ((1 << depth) - 1)) as u8,
+                }
+            };
+
+            let rgba = match header.color_type {
+                COLOR_GRAY => {
+                    let gray = sample(0);
+                    let alpha = if key == Some([gray, gray, gray]) {
+                        0
+                    } else {
+                        255
+                    };
+                    let gray = to_8bit(gray);
+                    [gray, gray, gray, alpha]
+                }
+                COLOR_RGB => {
+                    let color = [sample(0), sample(1), sample(2)];
+                    let alpha = if key == Some(color) { 0 } else { 255 };
+                    [
+                        to_8bit(color[0]),
+                        to_8bit(color[1]),
+                        to_8bit(color[2]),
+                        alpha,
+                    ]
+                }
+                COLOR_PALETTE => palette
+                    .get(sample(0) as usize)
+                    .copied()
+                    .unwrap_or([0, 0, 0, 255]),
+                COLOR_GRAY_ALPHA => {
+                    let gray = to_8bit(sample(0));
+                    [gray, gray, gray, to_8bit(sample(1))]
+                }
+                _ => [
+                    to_8bit(sample(0)),
+                    to_8bit(sample(1)),
+                    to_8bit(sample(2)),
+                    to_8bit(sample(3)),
+                ],
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+    }
+    pixels
+}
+
+/// Encodes an RGBA8 image into a PNG file, its pixel data being stored without compression.
+pub fn encode(image: &Image) -> Vec<u8> {
+    let row = image.width as usize * 4;
+    let mut raw = Vec::with_capacity((row + 1) * image.height as usize);
+    for pixels in image.pixels.chunks_exact(row).take(image.height as usize) {
+        // Rows are stored without a filter.
+        raw.push(0);
+        raw.extend_from_slice(pixels);
+    }
+
+    // A zlib stream made of stored DEFLATE blocks, each holding up to 65535 bytes.
+    let mut zlib = Vec::with_capacity(raw.len() + raw.len() / 65535 * 5 + 11);
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = raw.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&length.to_le_bytes());
+        zlib.extend_from_slice(&(!length).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&inflate::adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width.to_be_bytes());
+    header.extend_from_slice(&image.height.to_be_bytes());
+    header.extend_from_slice(&[8, COLOR_RGBA, 0, 0, 0]);
+
+    let mut data = Vec::with_capacity(zlib.len() + 57);
+    data.extend_from_slice(&SIGNATURE);
+    write_chunk(&mut data, b"IHDR", &header);
+    write_chunk(&mut data, b"IDAT", &zlib);
+    write_chunk(&mut data, b"IEND", &[]);
+    data
+}
+
+fn write_chunk(data: &mut Vec<u8>, kind: &[u8; 4], contents: &[u8]) {
+    data.extend_from_slice(&(contents.len() as u32).to_be_bytes());
+    let start = data.len();
+    data.extend_from_slice(kind);
+    data.extend_from_slice(contents);
+    let crc = crc32(&data[start..]);
+    data.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/// Computes the CRC-32 that protects every PNG chunk.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a `width` by `height` image whose pixels all differ, alpha included.
+    fn pattern(width: u32, height: u32) -> Image {
+        let pixels = (0..width * height)
+            .flat_map(|i| [i as u8, (i >> 8) as u8, (i * 7) as u8, (i * 13 + 1) as u8])
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn palette_with_transparency() {
+        let image = decode(include_bytes!("fixtures/palette.png")).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            255, 0, 0, 0, 0, 255, 0, 128, 0, 0, 255, 255, 255, 255, 255, 255,
+            255, 255, 255, 255, 0, 0, 255, 255, 0, 255, 0, 128, 255, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn gray_alpha() {
+        let image = decode(include_bytes!("fixtures/gray_alpha.png")).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            0, 0, 0, 255, 128, 128, 128, 64, 255, 255, 255, 0,
+            10, 10, 10, 20, 30, 30, 30, 40, 50, 50, 50, 60,
+        ]);
+    }
+
+    #[test]
+    fn sixteen_bit_channels() {
+        let image = decode(include_bytes!("fixtures/rgba16.png")).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            255, 0, 18, 128, 0, 255, 171, 255,
+            127, 1, 254, 0, 0, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn every_filter_type() {
+        // Each of the five rows uses the filter type of its index.
+        let image = decode(include_bytes!("fixtures/filters.png")).unwrap();
+        assert_eq!((image.width, image.height), (3, 5));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            0, 7, 255, 255, 70, 27, 215, 255, 140, 47, 175, 255,
+            30, 97, 230, 255, 100, 117, 190, 255, 170, 137, 150, 255,
+            60, 187, 205, 255, 130, 207, 165, 255, 200, 227, 125, 255,
+            90, 21, 180, 255, 160, 41, 140, 255, 230, 61, 100, 255,
+            120, 111, 155, 255, 190, 131, 115, 255, 4, 151, 75, 255,
+        ]);
+    }
+
+    #[test]
+    fn interlaced_is_unsupported() {
+        assert_eq!(
+            decode(include_bytes!("fixtures/interlaced.png")).unwrap_err(),
+            ImageError::Unsupported("interlaced PNG")
+        );
+    }
+
+    #[test]
+    fn bad_crc() {
+        assert_eq!(
+            decode(include_bytes!("fixtures/bad_crc.png")).unwrap_err(),
+            ImageError::Corrupt
+        );
+    }
+
+    #[test]
+    fn truncated_idat() {
+        assert_eq!(
+            decode(include_bytes!("fixtures/truncated_idat.png")).unwrap_err(),
+            ImageError::Truncated
+        );
+    }
+
+    #[test]
+    fn truncated_file() {
+        let data = include_bytes!("fixtures/palette.png");
+        for len in [SIGNATURE.len(), 20, data.len() - 20, data.len() - 1] {
+            assert_eq!(decode(&data[..len]).unwrap_err(), ImageError::Truncated);
+        }
+    }
+
+    #[test]
+    fn huge_chunk_length() {
+        let mut data = include_bytes!("fixtures/palette.png").to_vec();
+        data[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        // Odd widths, and an image whose pixel data takes several stored blocks.
+        for (width, height) in [(1, 1), (5, 3), (7, 1), (129, 128)] {
+            let image = pattern(width, height);
+            let decoded = decode(&encode(&image)).unwrap();
+            assert_eq!((decoded.width, decoded.height), (width, height));
+            assert_eq!(decoded.pixels, image.pixels);
+        }
+    }
+}
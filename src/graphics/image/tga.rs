@@ -0,0 +1,214 @@
+//! TGA decoding. Supports color-mapped, true-color and grayscale images, raw or RLE compressed, in any
+//! origin corner.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::{check_dimensions, Image, ImageError};
+
+const HEADER_SIZE: usize = 18;
+
+/// Decodes a TGA file into an RGBA8 image. TGA has no signature, so anything with an invalid header is
+/// reported as `ImageError::UnknownFormat`.
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    let header = data.get(..HEADER_SIZE).ok_or(ImageError::UnknownFormat)?;
+    let id_length = header[0] as usize;
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let map_first = u16::from_le_bytes([header[3], header[4]]) as usize;
+    let map_length = u16::from_le_bytes([header[5], header[6]]) as usize;
+    let map_depth = header[7];
+    let width = u16::from_le_bytes([header[12], header[13]]) as u32;
+    let height = u16::from_le_bytes([header[14], header[15]]) as u32;
+    let depth = header[16];
+    let descriptor = header[17];
+
+    let (color_mapped, gray, rle) = match image_type {
+        1 => (true, false, false),
+        2 => (false, false, false),
+        3 => (false, true, false),
+        9 => (true, false, true),
+        10 => (false, false, true),
+        11 => (false, true, true),
+        _ => return Err(ImageError::UnknownFormat),
+    };
+    if color_map_type > 1 || (color_mapped && color_map_type != 1) {
+        return Err(ImageError::UnknownFormat);
+    }
+    let valid_depth = if color_mapped {
+        matches!(depth, 8 | 16) && matches!(map_depth, 15 | 16 | 24 | 32)
+    } else if gray {
+        matches!(depth, 8 | 16)
+    } else {
+        matches!(depth, 15 | 16 | 24 | 32)
+    };
+    if !valid_depth {
+        return Err(ImageError::Unsupported("TGA pixel depth"));
+    }
+    check_dimensions(width, height)?;
+    // 16-bit pixels only carry alpha in their attribute bit when the descriptor says so.
+    let attribute_alpha = descriptor & 0x0f != 0;
+
+    let mut position = HEADER_SIZE + id_length;
+    let mut color_map = Vec::new();
+    if color_map_type == 1 {
+        let entry_size = (map_depth as usize).div_ceil(8);
+        let map = data
+            .get(position..position + map_length * entry_size)
+            .ok_or(ImageError::Truncated)?;
+        color_map = map
+            .chunks_exact(entry_size)
+            .map(|entry| read_color(entry, map_depth, false, attribute_alpha))
+            .collect();
+        position += map_length * entry_size;
+    }
+
+    let pixel_size = (depth as usize).div_ceil(8);
+    let count = (width * height) as usize;
+    let mut decoded = Vec::with_capacity(count * 4);
+    let mut read_pixel = |raw: &[u8]| -> Result<(), ImageError> {
+        let rgba = if color_mapped {
+            let index = if pixel_size == 2 {
+                u16::from_le_bytes([raw[0], raw[1]]) as usize
+            } else {
+                raw[0] as usize
+            };
+            *index
+                .checked_sub(map_first)
+                .and_then(|index| color_map.get(index))
+                .ok_or(ImageError::Corrupt)?
+        } else {
+            read_color(raw, depth, gray, attribute_alpha)
+        };
+        decoded.extend_from_slice(&rgba);
+        Ok(())
+    };
+
+    if rle {
+        let mut pixels = 0;
+        while pixels < count {
+            let packet = *data.get(position).ok_or(ImageError::Truncated)?;
+            position += 1;
+            let length = (packet & 0x7f) as usize + 1;
+            if pixels + length > count {
+                return Err(ImageError::Corrupt);
+            }
+
+            if packet & 0x80 != 0 {
+                // Run-length packet: one pixel repeated.
+                let raw = data
+                    .get(position..position + pixel_size)
+                    .ok_or(ImageError::Truncated)?;
+                for _ in 0..length {
+                    read_pixel(raw)?;
+                }
+                position += pixel_size;
+            } else {
+                let raw = data
+                    .get(position..position + pixel_size * length)
+                    .ok_or(ImageError::Truncated)?;
+                for pixel in raw.chunks_exact(pixel_size) {
+                    read_pixel(pixel)?;
+                }
+                position += pixel_size * length;
+            }
+            pixels += length;
+        }
+    } else {
+        let raw = data
+            .get(position..position + pixel_size * count)
+            .ok_or(ImageError::Truncated)?;
+        for pixel in raw.chunks_exact(pixel_size) {
+            read_pixel(pixel)?;
+        }
+    }
+
+    // Pixels are stored from the bottom-left corner unless the descriptor says otherwise.
+    let right_to_left = descriptor & 0x10 != 0;
+    let top_down = descriptor & 0x20 != 0;
+    let mut pixels = alloc::vec![0; count * 4];
+    for y in 0..height as usize {
+        let target_y = if top_down { y } else { height as usize - 1 - y };
+        for x in 0..width as usize {
+            let target_x = if right_to_left {
+                width as usize - 1 - x
+            } else {
+                x
+            };
+            let source = (y * width as usize + x) * 4;
+            let target = (target_y * width as usize + target_x) * 4;
+            pixels[target..target + 4].copy_from_slice(&decoded[source..source + 4]);
+        }
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Reads a little endian BGR(A) pixel of `depth` bits, or a gray one (with alpha when 16 bits).
+fn read_color(raw: &[u8], depth: u8, gray: bool, attribute_alpha: bool) -> [u8; 4] {
+    if gray {
+        let alpha = if depth == 16 { raw[1] } else { 255 };
+        return [raw[0], raw[0], raw[0], alpha];
+    }
+    match depth {
+        15 | 16 => {
+            let pixel = u16::from_le_bytes([raw[0], raw[1]]);
+            let expand = |value: u16| ((value & 0x1f) << 3 | (value & 0x1f) >> 2) as u8;
+            let alpha = if depth == 16 && attribute_alpha && pixel & 0x8000 == 0 {
+                0
+            } else {
+                255
+            };
+            [
+                expand(pixel >> 10),
+                expand(pixel >> 5),
+                expand(pixel),
+                alpha,
+            ]
+        }
+        24 => [raw[2], raw[1], raw[0], 255],
+        _ => [raw[2], raw[1], raw[0], raw[3]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_bottom_left() {
+        let image = decode(include_bytes!("fixtures/raw24.tga")).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255,
+            10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255,
+        ]);
+    }
+
+    #[test]
+    fn rle_top_left() {
+        // A run of three, three raw pixels spanning both rows, then a run of two.
+        let image = decode(include_bytes!("fixtures/rle32.tga")).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.pixels, [
+            1, 2, 3, 255, 1, 2, 3, 255, 1, 2, 3, 255, 10, 20, 30, 40,
+            50, 60, 70, 80, 90, 100, 110, 120, 5, 5, 5, 5, 5, 5, 5, 5,
+        ]);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = include_bytes!("fixtures/rle32.tga");
+        assert_eq!(
+            decode(&data[..data.len() - 1]).unwrap_err(),
+            ImageError::Truncated
+        );
+        assert_eq!(decode(&data[..10]).unwrap_err(), ImageError::UnknownFormat);
+    }
+}
@@ -3,23 +3,62 @@
 
 pub(crate) const PI: f32 = 3.1415926536;
 
+/// This module plays frame-based animations, driven by the time elapsed between frames.
+pub mod animation;
+pub use crate::graphics::animation::{AnimationEvent, Animator, Clip, PlayMode};
+#[allow(dead_code)]
+/// This module defines a texture atlas, many named frames packed into a single texture.
+pub mod atlas;
+pub use crate::graphics::atlas::{Atlas, AtlasError, Frame};
+/// This module draws many sprites and rects in a few draw calls, transforming them on the CPU.
+pub mod batch;
+pub use crate::graphics::batch::{BatchOrder, SpriteBatch};
+/// This module defines a 2D camera, panning, zooming and rotating the view of the world.
+pub mod camera;
+pub use crate::graphics::camera::Camera2D;
 #[allow(dead_code)]
 /// This modules describes and gives access to the PSP screen (width: `480`, height: `272`).
 pub mod canvas;
 mod utils;
-pub use crate::graphics::canvas::Canvas;
+pub use crate::graphics::canvas::{BlendMode, Canvas, CanvasBuilder, CanvasError, FrontFace};
+/// This module draws text with the PSP's debug font, either at a given spot or as a scrolling console.
+pub mod debug;
+pub use crate::graphics::debug::Console;
 #[allow(dead_code)]
 /// This module defines some preset colors for ease of use and allows the user to manually input a color (`R,G,B,A`).
 pub mod colors;
-pub use crate::graphics::colors::{Color, Colors};
+pub use crate::graphics::colors::{Color, Colors, Gradient};
+/// This module decodes PNG, BMP and TGA images, to be turned into a `Texture`.
+pub mod image;
+pub use crate::graphics::image::{Image, ImageError, ImageFormat};
 #[allow(dead_code)]
 /// This module defines basic primitives (Rect, Triangle, Ellipse) to allow it to be drawn easily.
 pub mod primitives;
 pub use crate::graphics::primitives::Primitive;
+/// This module sorts drawables submitted during a frame by layer and depth before drawing them.
+pub mod queue;
+pub use crate::graphics::queue::{Opacity, RenderQueue};
+#[allow(dead_code)]
+/// This module saves screenshots to the memory stick when a button chord is pressed.
+pub mod screenshot;
+pub use crate::graphics::screenshot::ScreenshotTrigger;
 #[allow(dead_code)]
-/// This module defines a 2D sprite, based on a 16-bit aligned image with matching W and H.
+/// This module defines a 2D sprite, drawing a shared `Texture`, and a sprite animated from an `Atlas`.
 pub mod sprite;
-pub use crate::graphics::sprite::Sprite;
+pub use crate::graphics::sprite::{AnimatedSprite, Filter, Sprite, TextureFunction, Wrap};
+/// This module defines offscreen render targets, drawn into and then used as textures.
+pub mod target;
+pub use crate::graphics::target::{RenderTarget, RenderTargetError};
+#[allow(dead_code)]
+/// This module defines text drawn with bitmap fonts, BMFont or fixed-grid ones.
+pub mod text;
+pub use crate::graphics::text::{Align, Font, Text};
+#[allow(dead_code)]
+/// This module defines a texture, a 16-byte aligned image of any size up to `512`x`512` that sprites draw.
+pub mod texture;
+pub use crate::graphics::texture::{Dither, PixelFormat, Region, Texture};
+mod vram;
+pub use crate::graphics::vram::VramError;
 
 use crate::core::{Vec2, Vec3};
 
@@ -38,6 +77,9 @@ pub trait Drawable {
 
     fn get_rot(&mut self) -> f32;
     fn set_rot(&mut self, new_rotation: f32);
+
+    fn get_color(&mut self) -> Color;
+    fn set_color(&mut self, new_color: Color);
 }
 
 /// Defines a vertex used by the drawable functions.
@@ -1,6 +1,9 @@
 #[allow(non_snake_case)]
 pub mod Primitive {
-    use crate::graphics::{utils::sort_vertices, Color, Drawable, Vertex, PI};
+    use crate::graphics::{
+        utils::{gradient_colors, sort_vertices},
+        Color, Drawable, Gradient, Vertex, PI,
+    };
     use core::ptr;
     extern crate alloc;
     use crate::core::{Vec2, Vec3};
@@ -29,54 +32,78 @@ pub mod Primitive {
         rotation: f32,
 
         color: Color,
+        colors: [Color; 4],
     }
 
     impl Rect {
         pub fn new(position: Vec3<f32>, size: Vec2<f32>, color: Color) -> Self {
             Self {
-                vertices: Self::generate_vertices(size, color.clone()),
+                vertices: Self::generate_vertices(size, [color; 4]),
                 indices: Align16([0, 1, 2, 2, 1, 3]),
                 position,
                 rotation: 0.0,
                 size,
                 scale: Vec2::new(1.0, 1.0),
                 color,
+                colors: [color; 4],
             }
         }
 
-        pub(crate) fn generate_vertices(size: Vec2<f32>, color: Color) -> Align16<[Vertex; 4]> {
+        /// Sets the color of each corner, in the order top-left, top-right, bottom-left and bottom-right.
+        pub fn set_vertex_colors(&mut self, colors: [Color; 4]) {
+            self.colors = colors;
+            self.vertices = Self::generate_vertices(self.size, self.colors);
+        }
+
+        /// Fills the rect with a gradient, evaluated at each corner.
+        pub fn set_gradient(&mut self, gradient: Gradient) {
+            self.set_vertex_colors(gradient_colors(
+                [
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(self.size.x, 0.0),
+                    Vec2::new(0.0, self.size.y),
+                    Vec2::new(self.size.x, self.size.y),
+                ],
+                &gradient,
+            ));
+        }
+
+        pub(crate) fn generate_vertices(
+            size: Vec2<f32>,
+            colors: [Color; 4],
+        ) -> Align16<[Vertex; 4]> {
             Align16([
                 Vertex {
                     u: 0.0,
                     v: 0.0,
-                    color: color.as_abgr(),
+                    color: colors[0].as_abgr(),
                     x: 0.0,
                     y: 0.0,
-                    z: -1.0,
+                    z: 0.0,
                 },
                 Vertex {
                     u: 0.0,
                     v: 0.0,
-                    color: color.as_abgr(),
+                    color: colors[1].as_abgr(),
                     x: size.x,
                     y: 0.0,
-                    z: -1.0,
+                    z: 0.0,
                 },
                 Vertex {
                     u: 0.0,
                     v: 0.0,
-                    color: color.as_abgr(),
+                    color: colors[2].as_abgr(),
                     x: 0.0,
                     y: size.y,
-                    z: -1.0,
+                    z: 0.0,
                 },
                 Vertex {
                     u: 0.0,
                     v: 0.0,
-                    color: color.as_abgr(),
+                    color: colors[3].as_abgr(),
                     x: size.x,
                     y: size.y,
-                    z: -1.0,
+                    z: 0.0,
                 },
             ])
         }
@@ -95,7 +122,7 @@ pub mod Primitive {
                 sceGumTranslate(&ScePspFVector3 {
                     x: self.position.x,
                     y: self.position.y,
-                    z: -1.0,
+                    z: self.position.z,
                 });
                 sceGumRotateZ(self.rotation);
                 sceGumScale(&ScePspFVector3 {
@@ -126,7 +153,7 @@ pub mod Primitive {
 
         fn set_size(&mut self, new_size: Vec2<f32>) {
             self.size = new_size;
-            self.vertices = Self::generate_vertices(self.size, self.color.clone());
+            self.vertices = Self::generate_vertices(self.size, self.colors);
         }
 
         fn get_scale(&mut self) -> Vec2<f32> {
@@ -152,59 +179,86 @@ pub mod Primitive {
         fn set_rot(&mut self, new_rotation: f32) {
             self.rotation = new_rotation * (PI / 180.0);
         }
+
+        fn get_color(&mut self) -> Color {
+            self.color
+        }
+
+        fn set_color(&mut self, new_color: Color) {
+            self.color = new_color;
+            self.set_vertex_colors([new_color; 4]);
+        }
     }
 
     #[derive(Clone)]
     pub struct Triangle {
         vertices: Align16<[Vertex; 3]>,
+        points: [Vec3<f32>; 3],
 
         position: Vec3<f32>,
         rotation: f32,
         scale: Vec2<f32>,
 
         color: Color,
+        colors: [Color; 3],
     }
 
     impl Triangle {
         pub fn new(vertices: [Vec3<f32>; 3], color: Color) -> Self {
             Self {
-                vertices: Self::generate_vertices(vertices, color),
+                vertices: Self::generate_vertices(vertices, [color; 3]),
+                points: vertices,
                 position: Vec3::new(vertices[1].x, vertices[1].y, vertices[1].z),
                 rotation: 0.0,
                 scale: Vec2::new(1.0, 1.0),
                 color,
+                colors: [color; 3],
             }
         }
 
+        /// Sets the color of each vertex, in the same order the vertices were given to `Triangle::new`.
+        pub fn set_vertex_colors(&mut self, colors: [Color; 3]) {
+            self.colors = colors;
+            self.vertices = Self::generate_vertices(self.points, self.colors);
+        }
+
+        /// Fills the triangle with a gradient, evaluated at each vertex.
+        pub fn set_gradient(&mut self, gradient: Gradient) {
+            self.set_vertex_colors(gradient_colors(
+                self.points.map(|point| Vec2::new(point.x, point.y)),
+                &gradient,
+            ));
+        }
+
         pub(crate) fn generate_vertices(
             vertex_pos: [Vec3<f32>; 3],
-            color: Color,
+            colors: [Color; 3],
         ) -> Align16<[Vertex; 3]> {
             Align16(sort_vertices(
                 [
                     Vertex {
                         u: 0.0,
                         v: 0.0,
-                        color: color.as_abgr(),
+                        color: colors[0].as_abgr(),
                         x: vertex_pos[0].x,
                         y: vertex_pos[0].y,
-                        z: -1.0,
+                        z: vertex_pos[0].z,
                     },
                     Vertex {
                         u: 0.0,
                         v: 0.0,
-                        color: color.as_abgr(),
+                        color: colors[1].as_abgr(),
                         x: vertex_pos[1].x,
                         y: vertex_pos[1].y,
-                        z: -1.0,
+                        z: vertex_pos[1].z,
                     },
                     Vertex {
                         u: 0.0,
                         v: 0.0,
-                        color: color.as_abgr(),
+                        color: colors[2].as_abgr(),
                         x: vertex_pos[2].x,
                         y: vertex_pos[2].y,
-                        z: -1.0,
+                        z: vertex_pos[2].z,
                     },
                 ],
                 true,
@@ -280,12 +334,11 @@ pub mod Primitive {
             // Gets smallest and largest X vertex
             let mut min_x = f32::MAX;
             let mut min_x_id: Vec<usize> = Vec::new();
-            for i in 0..self.vertices.0.len() {
-                if self.vertices.0[i].x < min_x {
-                    min_x = self.vertices.0[i].x;
+            for i in 0..self.points.len() {
+                if self.points[i].x < min_x {
+                    min_x = self.points[i].x;
                     min_x_id = alloc::vec![i];
-                }
-                if self.vertices.0[i].x == min_x {
+                } else if self.points[i].x == min_x {
                     min_x_id.push(i)
                 }
             }
@@ -293,34 +346,25 @@ pub mod Primitive {
             // Gets smallest and largest Y vertex
             let mut min_y = f32::MAX;
             let mut min_y_id: Vec<usize> = Vec::new();
-            for i in 0..self.vertices.0.len() {
-                if self.vertices.0[i].y < min_y {
-                    min_y = self.vertices.0[i].y;
+            for i in 0..self.points.len() {
+                if self.points[i].y < min_y {
+                    min_y = self.points[i].y;
                     min_y_id = alloc::vec![i];
-                }
-                if self.vertices.0[i].x == min_x {
-                    min_x_id.push(i)
+                } else if self.points[i].y == min_y {
+                    min_y_id.push(i)
                 }
             }
 
             // Moves all axis except the smallest ones on each axis by the difference
-            let mut vertices = self.vertices;
-            for i in 0..vertices.0.len() {
+            for i in 0..self.points.len() {
                 if !min_x_id.contains(&i) {
-                    vertices.0[i].x += difference.x
+                    self.points[i].x += difference.x
                 }
                 if !min_y_id.contains(&i) {
-                    vertices.0[i].y += difference.y
+                    self.points[i].y += difference.y
                 }
             }
-            self.vertices = Self::generate_vertices(
-                [
-                    Vec3::new(vertices.0[0].x, vertices.0[0].y, -vertices.0[0].z),
-                    Vec3::new(vertices.0[1].x, vertices.0[1].y, -vertices.0[1].z),
-                    Vec3::new(vertices.0[2].x, vertices.0[2].y, -vertices.0[2].z),
-                ],
-                self.color,
-            );
+            self.vertices = Self::generate_vertices(self.points, self.colors);
         }
 
         fn get_scale(&mut self) -> Vec2<f32> {
@@ -346,11 +390,20 @@ pub mod Primitive {
         fn set_rot(&mut self, new_rotation: f32) {
             self.rotation = new_rotation * (PI / 180.0);
         }
+
+        fn get_color(&mut self) -> Color {
+            self.color
+        }
+
+        fn set_color(&mut self, new_color: Color) {
+            self.color = new_color;
+            self.set_vertex_colors([new_color; 3]);
+        }
     }
 
     #[derive(Clone)]
     pub struct Ellipse {
-        vertices: Align16<[Vertex; (STEPS + 1) as usize]>,
+        vertices: Align16<[Vertex; (STEPS + 2) as usize]>,
 
         position: Vec3<f32>,
         rotation: f32,
@@ -362,22 +415,35 @@ pub mod Primitive {
 
     impl Ellipse {
         pub fn new(center: Vec3<f32>, radius: Vec2<f32>, color: Color) -> Self {
-            let mut vertices: Align16<[Vertex; (STEPS + 1) as usize]> =
-                Align16([Vertex::default(); (STEPS + 1) as usize]);
-
-            for i in 0..STEPS + 1 {
-                vertices.0[i as usize].u = 0.0;
-                vertices.0[i as usize].v = 0.0;
-                vertices.0[i as usize].color = color.as_abgr();
-                vertices.0[i as usize].x =
-                    radius.x * math::sin(ANGLE as f64 * (i - 1) as f64) as f32;
-                vertices.0[i as usize].y =
-                    radius.y * math::cos(ANGLE as f64 * (i - 1) as f64) as f32;
-                vertices.0[i as usize].z = -1.0;
+            let mut outline: [Vertex; STEPS as usize] = [Vertex::default(); STEPS as usize];
+
+            for i in 0..STEPS {
+                outline[i as usize].u = 0.0;
+                outline[i as usize].v = 0.0;
+                outline[i as usize].color = color.as_abgr();
+                outline[i as usize].x = radius.x * math::sin(ANGLE as f64 * i as f64) as f32;
+                outline[i as usize].y = radius.y * math::cos(ANGLE as f64 * i as f64) as f32;
+                outline[i as usize].z = 0.0;
             }
+            let outline = sort_vertices(outline, true);
+
+            // The fan starts at the center so colors can differ between it and the outline, and ends
+            // on the first outline vertex to close the shape.
+            let mut vertices: Align16<[Vertex; (STEPS + 2) as usize]> =
+                Align16([Vertex::default(); (STEPS + 2) as usize]);
+            vertices.0[0] = Vertex {
+                u: 0.0,
+                v: 0.0,
+                color: color.as_abgr(),
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            };
+            vertices.0[1..=STEPS as usize].copy_from_slice(&outline);
+            vertices.0[(STEPS + 1) as usize] = outline[0];
 
             Self {
-                vertices: Align16(sort_vertices(vertices.0, true)),
+                vertices,
                 radius,
                 scale: Vec2::new(1.0, 1.0),
                 position: Vec3::new(center.x, center.y, center.z),
@@ -385,6 +451,38 @@ pub mod Primitive {
                 color,
             }
         }
+
+        /// Sets the color of the center and of the outline. The `outline` colors are spread evenly around
+        /// the edge, clockwise from the rightmost point, and blended in between.
+        pub fn set_vertex_colors(&mut self, center: Color, outline: &[Color]) {
+            self.vertices.0[0].color = center.as_abgr();
+            if outline.is_empty() {
+                return;
+            }
+
+            for vertex in self.vertices.0[1..].iter_mut() {
+                let mut turn = math::atan2f(vertex.y, vertex.x) / (PI * 2.0);
+                if turn < 0.0 {
+                    turn += 1.0;
+                }
+                let position = turn * outline.len() as f32;
+                let id = position as usize % outline.len();
+                vertex.color = outline[id]
+                    .lerp(outline[(id + 1) % outline.len()], position - id as f32)
+                    .as_abgr();
+            }
+        }
+
+        /// Fills the ellipse with a gradient, evaluated at the center and at each outline vertex.
+        pub fn set_gradient(&mut self, gradient: Gradient) {
+            let colors = gradient_colors(
+                self.vertices.0.map(|vertex| Vec2::new(vertex.x, vertex.y)),
+                &gradient,
+            );
+            for (vertex, color) in self.vertices.0.iter_mut().zip(colors) {
+                vertex.color = color.as_abgr();
+            }
+        }
     }
 
     impl Drawable for Ellipse {
@@ -400,7 +498,7 @@ pub mod Primitive {
                 sceGumTranslate(&ScePspFVector3 {
                     x: self.position.x,
                     y: self.position.y,
-                    z: -1.0,
+                    z: self.position.z,
                 });
                 sceGumRotateZ(self.rotation);
                 sceGumScale(&ScePspFVector3 {
@@ -415,7 +513,7 @@ pub mod Primitive {
                         | VertexType::COLOR_8888
                         | VertexType::VERTEX_32BITF
                         | VertexType::TRANSFORM_3D,
-                    STEPS + 1,
+                    STEPS + 2,
                     ptr::null_mut(),
                     &self.vertices as *const Align16<_> as *const _,
                 );
@@ -455,5 +553,16 @@ pub mod Primitive {
         fn set_rot(&mut self, new_rotation: f32) {
             self.rotation = new_rotation * (PI / 180.0);
         }
+
+        fn get_color(&mut self) -> Color {
+            self.color
+        }
+
+        fn set_color(&mut self, new_color: Color) {
+            self.color = new_color;
+            for vertex in self.vertices.0.iter_mut() {
+                vertex.color = new_color.as_abgr();
+            }
+        }
     }
 }
@@ -0,0 +1,369 @@
+extern crate alloc;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{cell::RefCell, cmp::Ordering};
+
+use psp::sys::{
+    sceGuClear, sceGuClearDepth, sceGuDepthFunc, sceGuDepthMask, sceGuDisable, sceGuEnable,
+    ClearBuffer, DepthFunc, GuState,
+};
+
+use crate::core::{Vec2, Vec3};
+use crate::graphics::{canvas::load_view, colors::Color, Drawable};
+
+/// Whether a drawable covers what is behind it, which decides the order it is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opacity {
+    /// Fully covers what is behind it. Drawn first, front to back, hiding what it covers with the depth
+    /// buffer.
+    Opaque,
+    /// Blends with what is behind it, such as sprites with soft edges. Drawn after opaque drawables, back
+    /// to front.
+    Transparent,
+}
+
+struct Entry {
+    drawable: Box<dyn Drawable>,
+    layer: i32,
+    opacity: Opacity,
+    depth: f32,
+    /// View matrix set when the drawable was submitted.
+    view: Option<[f32; 16]>,
+}
+
+/// Drawables submitted during a frame, drawn together sorted by layer and depth. Layers are drawn from
+/// the lowest to the highest, each one over the previous ones whatever their depth. Within a layer, the
+/// bigger `position.z` is, the closer a drawable is, and drawables with the same depth keep the order they
+/// were submitted in.
+///
+/// The queue of a `Canvas` draws each drawable with the camera set when it was submitted, see
+/// `Canvas::set_camera`. Other queues draw with whatever camera is set when they are flushed.
+pub struct RenderQueue {
+    entries: Vec<Entry>,
+    /// Whether there is a depth buffer to hide what opaque drawables cover.
+    depth_buffer: bool,
+    /// View matrix loaded for what is drawn directly, `None` if the queue doesn't keep track of it.
+    view: Option<[f32; 16]>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            depth_buffer: true,
+            view: None,
+        }
+    }
+
+    /// Without a depth buffer, every drawable of a layer is drawn back to front.
+    pub(crate) fn set_depth_buffer(&mut self, depth_buffer: bool) {
+        self.depth_buffer = depth_buffer;
+    }
+
+    /// Sets the view matrix loaded from now on, which drawables submitted next are drawn with.
+    pub(crate) fn set_view(&mut self, view: [f32; 16]) {
+        self.view = Some(view);
+    }
+
+    /// Adds `drawable` to be drawn on `layer`, at the depth of its position when submitted.
+    pub fn submit<D: Drawable + 'static>(&mut self, mut drawable: D, layer: i32, opacity: Opacity) {
+        let depth = drawable.get_pos().z;
+        self.entries.push(Entry {
+            drawable: Box::new(drawable),
+            layer,
+            opacity,
+            depth,
+            view: self.view,
+        });
+    }
+
+    /// Returns the number of drawables waiting to be drawn.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops the submitted drawables without drawing them.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Draws the submitted drawables in order, then drops them.
+    pub fn flush(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        // The view matrix currently loaded.
+        let mut loaded = self.view;
+        // Stable sorts, so equal keys keep their submission order.
+        if !self.depth_buffer {
+            // Nothing can hide what is drawn later, so closer drawables must come last.
+            self.entries.sort_by(compare_back_to_front);
+            for entry in &mut self.entries {
+                switch_view(&mut loaded, entry.view);
+                entry.drawable.draw();
+            }
+            switch_view(&mut loaded, self.view);
+            self.entries.clear();
+            return;
+        }
+        self.entries.sort_by(compare);
+
+        unsafe {
+            sceGuDepthFunc(DepthFunc::GreaterOrEqual);
+            let mut layer = None;
+            for entry in &mut self.entries {
+                if layer != Some(entry.layer) {
+                    layer = Some(entry.layer);
+                    // Each layer starts with an empty depth buffer, so it covers the previous ones.
+                    sceGuClearDepth(0);
+                    sceGuClear(ClearBuffer::DEPTH_BUFFER_BIT);
+                    sceGuEnable(GuState::DepthTest);
+                }
+                // Transparent drawables are hidden by closer opaque ones, without hiding anything.
+                sceGuDepthMask(match entry.opacity {
+                    Opacity::Opaque => 0,
+                    Opacity::Transparent => 1,
+                });
+                switch_view(&mut loaded, entry.view);
+                entry.drawable.draw();
+            }
+            sceGuDepthMask(0);
+            sceGuDisable(GuState::DepthTest);
+        }
+        switch_view(&mut loaded, self.view);
+        self.entries.clear();
+    }
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads `view` unless it is already `loaded`, or isn't known.
+fn switch_view(loaded: &mut Option<[f32; 16]>, view: Option<[f32; 16]>) {
+    if let Some(view) = view {
+        if *loaded != Some(view) {
+            unsafe { load_view(&view) };
+            *loaded = Some(view);
+        }
+    }
+}
+
+fn compare(a: &Entry, b: &Entry) -> Ordering {
+    let depth = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    a.layer
+        .cmp(&b.layer)
+        .then_with(|| match (a.opacity, b.opacity) {
+            (Opacity::Opaque, Opacity::Opaque) => depth(b.depth, a.depth),
+            (Opacity::Transparent, Opacity::Transparent) => depth(a.depth, b.depth),
+            (Opacity::Opaque, Opacity::Transparent) => Ordering::Less,
+            (Opacity::Transparent, Opacity::Opaque) => Ordering::Greater,
+        })
+}
+
+fn compare_back_to_front(a: &Entry, b: &Entry) -> Ordering {
+    a.layer
+        .cmp(&b.layer)
+        .then_with(|| a.depth.partial_cmp(&b.depth).unwrap_or(Ordering::Equal))
+}
+
+/// Lets a drawable be submitted to a `RenderQueue` every frame without being cloned.
+impl<D: Drawable> Drawable for Rc<RefCell<D>> {
+    fn draw(&mut self) {
+        self.borrow_mut().draw();
+    }
+
+    fn get_size(&mut self) -> Vec2<f32> {
+        self.borrow_mut().get_size()
+    }
+
+    fn set_size(&mut self, new_size: Vec2<f32>) {
+        self.borrow_mut().set_size(new_size);
+    }
+
+    fn get_scale(&mut self) -> Vec2<f32> {
+        self.borrow_mut().get_scale()
+    }
+
+    fn set_scale(&mut self, new_scale: Vec2<f32>) {
+        self.borrow_mut().set_scale(new_scale);
+    }
+
+    fn get_pos(&mut self) -> Vec3<f32> {
+        self.borrow_mut().get_pos()
+    }
+
+    fn set_pos(&mut self, new_position: Vec3<f32>) {
+        self.borrow_mut().set_pos(new_position);
+    }
+
+    fn get_rot(&mut self) -> f32 {
+        self.borrow_mut().get_rot()
+    }
+
+    fn set_rot(&mut self, new_rotation: f32) {
+        self.borrow_mut().set_rot(new_rotation);
+    }
+
+    fn get_color(&mut self) -> Color {
+        self.borrow_mut().get_color()
+    }
+
+    fn set_color(&mut self, new_color: Color) {
+        self.borrow_mut().set_color(new_color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use Opacity::{Opaque, Transparent};
+
+    /// Drawable writing its name to a shared log when drawn.
+    struct Marker {
+        name: char,
+        position: Vec3<f32>,
+        log: Rc<RefCell<String>>,
+    }
+
+    impl Drawable for Marker {
+        fn draw(&mut self) {
+            self.log.borrow_mut().push(self.name);
+        }
+
+        fn get_size(&mut self) -> Vec2<f32> {
+            Vec2::new(1.0, 1.0)
+        }
+
+        fn set_size(&mut self, _new_size: Vec2<f32>) {}
+
+        fn get_scale(&mut self) -> Vec2<f32> {
+            Vec2::new(1.0, 1.0)
+        }
+
+        fn set_scale(&mut self, _new_scale: Vec2<f32>) {}
+
+        fn get_pos(&mut self) -> Vec3<f32> {
+            self.position
+        }
+
+        fn set_pos(&mut self, new_position: Vec3<f32>) {
+            self.position = new_position;
+        }
+
+        fn get_rot(&mut self) -> f32 {
+            0.0
+        }
+
+        fn set_rot(&mut self, _new_rotation: f32) {}
+
+        fn get_color(&mut self) -> Color {
+            Color::new(255, 255, 255, 255)
+        }
+
+        fn set_color(&mut self, _new_color: Color) {}
+    }
+
+    /// Returns a queue of `(name, layer, opacity, depth)` drawables, and the log they are drawn to.
+    fn queue(drawables: &[(char, i32, Opacity, f32)]) -> (RenderQueue, Rc<RefCell<String>>) {
+        let log = Rc::new(RefCell::new(String::new()));
+        let mut queue = RenderQueue::new();
+        for &(name, layer, opacity, depth) in drawables {
+            let marker = Marker {
+                name,
+                position: Vec3::new(0.0, 0.0, depth),
+                log: log.clone(),
+            };
+            queue.submit(marker, layer, opacity);
+        }
+        (queue, log)
+    }
+
+    /// Returns the names of the drawables in the order `compare` sorts them in.
+    fn sorted(drawables: &[(char, i32, Opacity, f32)]) -> String {
+        let (mut queue, log) = queue(drawables);
+        queue.entries.sort_by(compare);
+        for entry in &mut queue.entries {
+            entry.drawable.draw();
+        }
+        let order = log.borrow().clone();
+        order
+    }
+
+    #[test]
+    fn layers_first() {
+        assert_eq!(
+            sorted(&[
+                ('a', 2, Opaque, 10.0),
+                ('b', -1, Transparent, -10.0),
+                ('c', 0, Opaque, -10.0),
+                ('d', 2, Transparent, 0.0),
+            ]),
+            "bcad"
+        );
+    }
+
+    #[test]
+    fn opaque_before_transparent() {
+        assert_eq!(
+            sorted(&[
+                ('a', 0, Transparent, 10.0),
+                ('b', 0, Opaque, -10.0),
+                ('c', 0, Transparent, -10.0),
+                ('d', 0, Opaque, 10.0),
+            ]),
+            "dbca"
+        );
+    }
+
+    #[test]
+    fn opaque_near_to_far_transparent_far_to_near() {
+        assert_eq!(
+            sorted(&[
+                ('a', 0, Opaque, 1.0),
+                ('b', 0, Opaque, 3.0),
+                ('c', 0, Opaque, 2.0),
+                ('d', 0, Transparent, 1.0),
+                ('e', 0, Transparent, 3.0),
+                ('f', 0, Transparent, 2.0),
+            ]),
+            "bcadfe"
+        );
+    }
+
+    #[test]
+    fn equal_keys_keep_submission_order() {
+        assert_eq!(
+            sorted(&[
+                ('a', 0, Transparent, 1.0),
+                ('b', 0, Opaque, 1.0),
+                ('c', 0, Transparent, 1.0),
+                ('d', 0, Opaque, 1.0),
+                ('e', 0, Transparent, 1.0),
+            ]),
+            "bdace"
+        );
+    }
+
+    #[test]
+    fn back_to_front_without_depth_buffer() {
+        let (mut queue, log) = queue(&[
+            ('a', 1, Opaque, 0.0),
+            ('b', 0, Opaque, 2.0),
+            ('c', 0, Transparent, 1.0),
+            ('d', 0, Opaque, 1.0),
+            ('e', 0, Transparent, -1.0),
+        ]);
+        queue.set_depth_buffer(false);
+        queue.flush();
+        // Opacity doesn't matter, and equal depths keep their submission order.
+        assert_eq!(*log.borrow(), "ecdba");
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+extern crate alloc;
+use alloc::{format, string::String, vec::Vec};
+
+use psp::sys::{sceRtcGetCurrentClockLocalTime, ScePspDateTime};
+
+use crate::core::{
+    input::{Buttons, InputManager},
+    io::{File, IoError},
+};
+use crate::graphics::{canvas::Canvas, image::ImageFormat};
+
+/// Saves a screenshot to the memory stick whenever its button chord is pressed, so testers can attach what
+/// they saw to their bug reports. Files are named after the local time, such as
+/// `ms0:/PICTURE/20240131_184502_0.png`.
+pub struct ScreenshotTrigger {
+    directory: String,
+    format: ImageFormat,
+    chord: Vec<Buttons>,
+    count: u32,
+}
+
+impl ScreenshotTrigger {
+    /// Returns a trigger saving to `directory`, created if missing, when `L`, `R` and `Start` are pressed
+    /// together.
+    pub fn new(directory: &str, format: ImageFormat) -> Self {
+        Self {
+            directory: String::from(directory.trim_end_matches('/')),
+            format,
+            chord: alloc::vec![Buttons::LTrigger, Buttons::RTrigger, Buttons::Start],
+            count: 0,
+        }
+    }
+
+    pub fn get_format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: ImageFormat) {
+        self.format = format;
+    }
+
+    /// Sets the buttons that take a screenshot when held together, none disabling the trigger.
+    pub fn set_chord(&mut self, buttons: &[Buttons]) {
+        self.chord = buttons.to_vec();
+    }
+
+    /// Saves the frame on screen if the button chord was just pressed, returning the path it was written to.
+    /// Must be called every frame, after `InputManager::update`.
+    pub fn update(
+        &mut self,
+        canvas: &Canvas,
+        input: &mut InputManager,
+    ) -> Option<Result<String, IoError>> {
+        if self.chord.is_empty() {
+            return None;
+        }
+        let held = self
+            .chord
+            .iter()
+            .all(|button| input.is_key_down(button.clone()));
+        let pressed = self
+            .chord
+            .iter()
+            .any(|button| input.is_key_down_changed(button.clone()));
+        if !(held && pressed) {
+            return None;
+        }
+
+        Some(self.capture(canvas))
+    }
+
+    /// Saves the frame on screen right away, returning the path it was written to.
+    pub fn capture(&mut self, canvas: &Canvas) -> Result<String, IoError> {
+        // Fails once the directory exists, which is fine.
+        let _ = File::create_dir(&self.directory);
+
+        let mut time = ScePspDateTime {
+            year: 0,
+            month: 0,
+            day: 0,
+            hour: 0,
+            minutes: 0,
+            seconds: 0,
+            microseconds: 0,
+        };
+        unsafe { sceRtcGetCurrentClockLocalTime(&mut time) };
+
+        // The counter keeps the names of screenshots taken within the same second apart.
+        let path = format!(
+            "{}/{:04}{:02}{:02}_{:02}{:02}{:02}_{}.{}",
+            self.directory,
+            time.year,
+            time.month,
+            time.day,
+            time.hour,
+            time.minutes,
+            time.seconds,
+            self.count,
+            self.format.extension()
+        );
+        self.count += 1;
+
+        canvas.save_screenshot(&path, self.format)?;
+        Ok(path)
+    }
+}
@@ -1,18 +1,92 @@
+extern crate alloc;
+use alloc::rc::Rc;
+
 use psp::{
     sys::{
-        sceGuEnable, sceGuTexFilter, sceGuTexFunc, sceGuTexImage, sceGuTexMode, sceGuTexOffset,
-        sceGuTexScale, sceGuTexWrap, sceGumDrawArray, sceGumLoadIdentity, sceGumMatrixMode,
-        sceGumPopMatrix, sceGumPushMatrix, sceGumRotateZ, sceGumScale, sceGumTranslate,
-        GuPrimitive, GuState, GuTexWrapMode, MatrixMode, MipmapLevel, ScePspFVector3,
-        TextureColorComponent, TextureEffect, TextureFilter, TexturePixelFormat, VertexType,
+        sceGuEnable, sceGuTexFilter, sceGuTexFunc, sceGuTexOffset, sceGuTexScale, sceGuTexWrap,
+        sceGumDrawArray, sceGumLoadIdentity, sceGumMatrixMode, sceGumPopMatrix, sceGumPushMatrix,
+        sceGumRotateZ, sceGumScale, sceGumTranslate, GuPrimitive, GuState, GuTexWrapMode,
+        MatrixMode, ScePspFVector3, TextureColorComponent, TextureEffect, TextureFilter,
+        VertexType,
     },
     Align16,
 };
 
 use crate::core::{Vec2, Vec3};
-use crate::graphics::{colors::Color, Drawable, Vertex, PI};
+use crate::graphics::{
+    animation::{AnimationEvent, Animator},
+    atlas::Atlas,
+    batch::{self, BatchState},
+    colors::{Color, Gradient},
+    texture::{Region, Texture, TextureError},
+    utils::gradient_colors,
+    Drawable, Vertex, PI,
+};
 
-pub struct Sprite<const N: usize> {
+/// How texels are picked when a texture is drawn bigger or smaller than it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Takes the closest texel, keeping pixel art sharp.
+    Nearest,
+    /// Blends the four closest texels, smoothing scaled and rotated images.
+    Linear,
+}
+
+/// What is drawn past the edge of a texture, such as when scrolling it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    /// The texture is tiled. Tiles are as big as the texture padded to a power of two.
+    Repeat,
+    /// The texels on the edge are stretched.
+    Clamp,
+}
+
+/// How the texture is combined with the sprite's color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFunction {
+    /// Multiplies the texture by the color, tinting it.
+    Modulate,
+    /// Draws the texture as it is, ignoring the color.
+    Replace,
+    /// Blends the color under the texture according to the texture's alpha.
+    Decal,
+    /// Adds the color to the texture, brightening it.
+    Add,
+}
+
+impl Filter {
+    pub(crate) fn as_psp(&self) -> TextureFilter {
+        match self {
+            Filter::Nearest => TextureFilter::Nearest,
+            Filter::Linear => TextureFilter::Linear,
+        }
+    }
+}
+
+impl Wrap {
+    pub(crate) fn as_psp(&self) -> GuTexWrapMode {
+        match self {
+            Wrap::Repeat => GuTexWrapMode::Repeat,
+            Wrap::Clamp => GuTexWrapMode::Clamp,
+        }
+    }
+}
+
+impl TextureFunction {
+    pub(crate) fn as_effect(&self) -> TextureEffect {
+        match self {
+            TextureFunction::Modulate => TextureEffect::Modulate,
+            TextureFunction::Replace => TextureEffect::Replace,
+            TextureFunction::Decal => TextureEffect::Decal,
+            TextureFunction::Add => TextureEffect::Add,
+        }
+    }
+}
+
+/// A textured rectangle. Its texture is shared, so cloning a sprite or creating many sprites from the
+/// same `Rc<Texture>` does not copy any pixels.
+#[derive(Clone)]
+pub struct Sprite {
     vertices: Align16<[Vertex; 4]>,
     indices: Align16<[u16; 6]>,
     position: Vec3<f32>,
@@ -20,73 +94,219 @@ pub struct Sprite<const N: usize> {
     size: Vec2<f32>,
     scale: Vec2<f32>,
 
-    texture: Align16<[u8; N]>,
+    texture: Rc<Texture>,
+    source: Region,
     color: Color,
-    texture_size: f32,
+    colors: [Color; 4],
+
+    flip: (bool, bool),
+    filter: Filter,
+    wrap: (Wrap, Wrap),
+    function: TextureFunction,
+    scroll: Vec2<f32>,
 }
 
-impl<const N: usize> Sprite<N> {
+impl Sprite {
     pub fn new(
         position: Vec3<f32>,
         rotation: f32,
         size: Vec2<f32>,
-        texture: Align16<[u8; N]>,
+        texture: Rc<Texture>,
         color: Color,
     ) -> Self {
-        let texture_size = psp::math::sqrtf(texture.0.len() as f32 / 4.0);
-
         Self {
-            vertices: Self::generate_vertices(size, color),
+            vertices: Self::generate_vertices(size, [color; 4], texture.uv(texture.region())),
             indices: Align16([0, 1, 2, 2, 1, 3]),
             position,
             rotation,
             size,
             scale: Vec2::new(1.0, 1.0),
-            texture_size,
+            source: texture.region(),
             texture,
             color,
+            colors: [color; 4],
+            flip: (false, false),
+            filter: Filter::Nearest,
+            wrap: (Wrap::Repeat, Wrap::Repeat),
+            function: TextureFunction::Modulate,
+            scroll: Vec2::new(0.0, 0.0),
+        }
+    }
+
+    /// Returns the texture drawn by this sprite.
+    pub fn get_texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    /// Changes the texture drawn by this sprite, drawing the whole of it.
+    pub fn set_texture(&mut self, texture: Rc<Texture>) {
+        self.source = texture.region();
+        self.texture = texture;
+        self.update_vertices();
+    }
+
+    /// Returns the region of the texture drawn by this sprite.
+    pub fn get_source(&self) -> Region {
+        self.source
+    }
+
+    /// Draws only `source` of the texture, such as a frame of a sprite sheet, stretched to the sprite's size.
+    /// The source is left as it was if `source` doesn't lie within the texture.
+    pub fn set_source(&mut self, source: Region) -> Result<(), TextureError> {
+        if !self.texture.contains(source) {
+            return Err(TextureError::OutOfBounds);
+        }
+        self.source = source;
+        self.update_vertices();
+        Ok(())
+    }
+
+    /// Returns whether the sprite is mirrored horizontally and vertically.
+    pub fn get_flip(&self) -> (bool, bool) {
+        self.flip
+    }
+
+    /// Mirrors the sprite horizontally and/or vertically, such as to make a character face the other way.
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        self.flip = (horizontal, vertical);
+        self.update_vertices();
+    }
+
+    pub fn get_filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Sets how the texture is sampled when drawn scaled or rotated.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Returns the wrap mode of the horizontal and vertical axes.
+    pub fn get_wrap(&self) -> (Wrap, Wrap) {
+        self.wrap
+    }
+
+    /// Sets what is drawn past the edges of the texture, for each axis.
+    pub fn set_wrap(&mut self, horizontal: Wrap, vertical: Wrap) {
+        self.wrap = (horizontal, vertical);
+    }
+
+    pub fn get_texture_function(&self) -> TextureFunction {
+        self.function
+    }
+
+    /// Sets how the texture is combined with the sprite's color.
+    pub fn set_texture_function(&mut self, function: TextureFunction) {
+        self.function = function;
+    }
+
+    /// Returns how far the texture is scrolled, in texels.
+    pub fn get_scroll(&self) -> Vec2<f32> {
+        self.scroll
+    }
+
+    /// Scrolls the texture within the sprite by `scroll` texels, such as for a moving background. Pair it
+    /// with `Wrap::Repeat` and a texture whose size is a power of two to tile seamlessly.
+    pub fn set_scroll(&mut self, scroll: Vec2<f32>) {
+        self.scroll = scroll;
+    }
+
+    /// Sets the tint of each corner, in the order top-left, top-right, bottom-left and bottom-right.
+    pub fn set_vertex_colors(&mut self, colors: [Color; 4]) {
+        self.colors = colors;
+        self.update_vertices();
+    }
+
+    /// Tints the sprite with a gradient, evaluated at each corner.
+    pub fn set_gradient(&mut self, gradient: Gradient) {
+        self.set_vertex_colors(gradient_colors(
+            [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(self.size.x, 0.0),
+                Vec2::new(0.0, self.size.y),
+                Vec2::new(self.size.x, self.size.y),
+            ],
+            &gradient,
+        ));
+    }
+
+    fn update_vertices(&mut self) {
+        let mut uv = self.texture.uv(self.source);
+        if self.flip.0 {
+            uv.swap(0, 2);
         }
+        if self.flip.1 {
+            uv.swap(1, 3);
+        }
+        self.vertices = Self::generate_vertices(self.size, self.colors, uv);
+    }
+
+    /// Returns the sprite's state and its corners as `Drawable::draw` would place them, for `SpriteBatch`.
+    /// The scroll is added to the UVs, as the quads of a batch share their texture offset.
+    pub(crate) fn batch_quad(&self) -> (BatchState, [Vertex; 4]) {
+        let scroll = Vec2::new(
+            self.scroll.x / self.texture.stride() as f32,
+            self.scroll.y / self.texture.height().next_power_of_two() as f32,
+        );
+        let corners = self.vertices.0.map(|corner| Vertex {
+            u: corner.u + scroll.x,
+            v: corner.v + scroll.y,
+            ..corner
+        });
+        (
+            BatchState {
+                texture: Some(self.texture.clone()),
+                filter: self.filter,
+                wrap: self.wrap,
+                function: self.function,
+            },
+            batch::transform(corners, self.position, self.rotation, self.scale),
+        )
     }
 
-    pub(crate) fn generate_vertices(size: Vec2<f32>, color: Color) -> Align16<[Vertex; 4]> {
+    pub(crate) fn generate_vertices(
+        size: Vec2<f32>,
+        colors: [Color; 4],
+        uv: [f32; 4],
+    ) -> Align16<[Vertex; 4]> {
         Align16([
             Vertex {
-                u: 0.0,
-                v: 0.0,
-                color: color.as_abgr(),
+                u: uv[0],
+                v: uv[1],
+                color: colors[0].as_abgr(),
                 x: 0.0,
                 y: 0.0,
-                z: -1.0,
+                z: 0.0,
             },
             Vertex {
-                u: size.x,
-                v: 0.0,
-                color: color.as_abgr(),
+                u: uv[2],
+                v: uv[1],
+                color: colors[1].as_abgr(),
                 x: size.x,
                 y: 0.0,
-                z: -1.0,
+                z: 0.0,
             },
             Vertex {
-                u: 0.0,
-                v: size.y,
-                color: color.as_abgr(),
+                u: uv[0],
+                v: uv[3],
+                color: colors[2].as_abgr(),
                 x: 0.0,
                 y: size.y,
-                z: -1.0,
+                z: 0.0,
             },
             Vertex {
-                u: size.x,
-                v: size.y,
-                color: color.as_abgr(),
+                u: uv[2],
+                v: uv[3],
+                color: colors[3].as_abgr(),
                 x: size.x,
                 y: size.y,
-                z: -1.0,
+                z: 0.0,
             },
         ])
     }
 }
 
-impl<const N: usize> Drawable for Sprite<N> {
+impl Drawable for Sprite {
     fn draw(&mut self) {
         unsafe {
             sceGuEnable(GuState::Texture2D);
@@ -108,19 +328,16 @@ impl<const N: usize> Drawable for Sprite<N> {
                 z: 1.0,
             });
 
-            sceGuTexMode(TexturePixelFormat::Psm8888, 0, 0, 0);
-            sceGuTexImage(
-                MipmapLevel::None,
-                self.texture_size as i32,
-                self.texture_size as i32,
-                self.texture_size as i32,
-                &self.texture as *const Align16<_> as *const _,
+            self.texture.bind();
+            sceGuTexFunc(self.function.as_effect(), TextureColorComponent::Rgba);
+            sceGuTexFilter(self.filter.as_psp(), self.filter.as_psp());
+            sceGuTexScale(1.0, 1.0);
+            // UVs span the texture padded to a power of two, so texels are converted with its size.
+            sceGuTexOffset(
+                self.scroll.x / self.texture.stride() as f32,
+                self.scroll.y / self.texture.height().next_power_of_two() as f32,
             );
-            sceGuTexFunc(TextureEffect::Modulate, TextureColorComponent::Rgba);
-            sceGuTexFilter(TextureFilter::Nearest, TextureFilter::Nearest);
-            sceGuTexScale(1.0 / self.size.x, 1.0 / self.size.y);
-            sceGuTexOffset(0.0, 0.0);
-            sceGuTexWrap(GuTexWrapMode::Repeat, GuTexWrapMode::Repeat);
+            sceGuTexWrap(self.wrap.0.as_psp(), self.wrap.1.as_psp());
 
             sceGumDrawArray(
                 GuPrimitive::Triangles,
@@ -144,9 +361,7 @@ impl<const N: usize> Drawable for Sprite<N> {
 
     fn set_size(&mut self, new_size: Vec2<f32>) {
         self.size = new_size;
-        let vertices = Self::generate_vertices(self.size, self.color);
-
-        self.vertices = vertices;
+        self.update_vertices();
     }
 
     fn get_scale(&mut self) -> Vec2<f32> {
@@ -172,6 +387,169 @@ impl<const N: usize> Drawable for Sprite<N> {
     fn set_rot(&mut self, new_rotation: f32) {
         self.rotation = new_rotation * (PI / 180.0);
     }
+
+    fn get_color(&mut self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, new_color: Color) {
+        self.color = new_color;
+        self.set_vertex_colors([new_color; 4]);
+    }
 }
 
-fn swizzle() {}
+/// A sprite showing the frames of an `Atlas` in turn, as played by its `Animator`. It starts with the
+/// clips read from the atlas' data file, such as Aseprite tags.
+#[derive(Clone)]
+pub struct AnimatedSprite {
+    sprite: Sprite,
+    atlas: Rc<Atlas>,
+    animator: Animator,
+    frame: Option<usize>,
+}
+
+impl AnimatedSprite {
+    /// Returns an animated sprite, sized after the first frame of `atlas`.
+    pub fn new(atlas: Rc<Atlas>, position: Vec3<f32>, color: Color) -> Self {
+        let region = atlas
+            .frame(0)
+            .map_or(atlas.texture().region(), |frame| frame.region);
+        let mut sprite = Sprite::new(
+            position,
+            0.0,
+            Vec2::new(region.width as f32, region.height as f32),
+            atlas.texture().clone(),
+            color,
+        );
+        // Frames were checked against the texture when the atlas was loaded.
+        let _ = sprite.set_source(region);
+
+        let mut animator = Animator::new();
+        for clip in atlas.clips() {
+            animator.add_clip(clip.clone());
+        }
+
+        Self {
+            sprite,
+            atlas,
+            animator,
+            frame: None,
+        }
+    }
+
+    /// Returns the animator, to add clips or change the speed.
+    pub fn animator(&mut self) -> &mut Animator {
+        &mut self.animator
+    }
+
+    /// Returns the sprite drawn, to change its source size or tint.
+    pub fn sprite(&mut self) -> &mut Sprite {
+        &mut self.sprite
+    }
+
+    /// Plays the clip called `name`, see `Animator::play`.
+    pub fn play(&mut self, name: &str) -> bool {
+        let found = self.animator.play(name);
+        self.update_frame();
+        found
+    }
+
+    /// Advances the animation by `delta` seconds.
+    pub fn update(&mut self, delta: f32) {
+        self.animator.update(delta);
+        self.update_frame();
+    }
+
+    /// Returns the oldest animation event not yet polled.
+    pub fn poll_event(&mut self) -> Option<AnimationEvent> {
+        self.animator.poll_event()
+    }
+
+    fn update_frame(&mut self) {
+        let frame = self.animator.current_frame();
+        if frame != self.frame {
+            self.frame = frame;
+            if let Some(frame) = frame.and_then(|frame| self.atlas.frame(frame)) {
+                let _ = self.sprite.set_source(frame.region);
+            }
+        }
+    }
+}
+
+impl Drawable for AnimatedSprite {
+    fn draw(&mut self) {
+        self.sprite.draw();
+    }
+
+    fn get_size(&mut self) -> Vec2<f32> {
+        self.sprite.get_size()
+    }
+
+    fn set_size(&mut self, new_size: Vec2<f32>) {
+        self.sprite.set_size(new_size);
+    }
+
+    fn get_scale(&mut self) -> Vec2<f32> {
+        self.sprite.get_scale()
+    }
+
+    fn set_scale(&mut self, new_scale: Vec2<f32>) {
+        self.sprite.set_scale(new_scale);
+    }
+
+    fn get_pos(&mut self) -> Vec3<f32> {
+        self.sprite.get_pos()
+    }
+
+    fn set_pos(&mut self, new_position: Vec3<f32>) {
+        self.sprite.set_pos(new_position);
+    }
+
+    fn get_rot(&mut self) -> f32 {
+        self.sprite.get_rot()
+    }
+
+    fn set_rot(&mut self, new_rotation: f32) {
+        self.sprite.set_rot(new_rotation);
+    }
+
+    fn get_color(&mut self) -> Color {
+        self.sprite.get_color()
+    }
+
+    fn set_color(&mut self, new_color: Color) {
+        self.sprite.set_color(new_color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::texture::PixelFormat;
+
+    #[test]
+    fn gradient_follows_the_corners() {
+        let texture = Rc::new(Texture::new(32, 16, PixelFormat::Psm8888).unwrap());
+        let mut sprite = Sprite::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            0.0,
+            Vec2::new(32.0, 16.0),
+            texture,
+            Color::new(255, 255, 255, 255),
+        );
+        let gradient = Gradient::radial(
+            Vec2::new(0.0, 1.0),
+            1.0,
+            Color::new(255, 0, 0, 255),
+            Color::new(0, 0, 255, 0),
+        );
+        sprite.set_gradient(gradient);
+
+        // Each vertex gets the color of the gradient where it is drawn.
+        for vertex in sprite.vertices.0 {
+            let point = Vec2::new(vertex.x / 32.0, vertex.y / 16.0);
+            assert_eq!(vertex.color, gradient.color_at(point).as_abgr());
+        }
+        assert_eq!(sprite.vertices.0[2].color, 0xff00_00ff);
+    }
+}
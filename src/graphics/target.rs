@@ -0,0 +1,94 @@
+extern crate alloc;
+use alloc::rc::Rc;
+use core::ffi::c_void;
+
+use psp::sys::DisplayPixelFormat;
+
+use crate::graphics::{
+    texture::{PixelFormat, Texture, MAX_TEXTURE_SIZE},
+    vram::{self, VramBlock, VramError},
+};
+
+/// Errors that may happen when creating a `RenderTarget`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderTargetError {
+    /// Width or height is zero or larger than the `512` pixels the PSP can sample.
+    InvalidSize,
+    /// The GE can't draw in `Clut` formats.
+    UnsupportedFormat(PixelFormat),
+    /// There isn't enough VRAM left for the target.
+    Vram(VramError),
+}
+
+/// An offscreen image the GE can draw into, bound with `Canvas::set_target`, then drawn by sprites through
+/// its `texture`. Useful for minimaps, split-screen, post-processing or UI that rarely changes.
+///
+/// Its pixels and its depth buffer live in VRAM, which is never given back, so targets are meant to be
+/// created once and reused.
+pub struct RenderTarget {
+    texture: Rc<Texture>,
+    pixels: VramBlock,
+    depth: VramBlock,
+    format: DisplayPixelFormat,
+    width: u32,
+    height: u32,
+    stride: u32,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Result<Self, RenderTargetError> {
+        if width == 0 || height == 0 || width > MAX_TEXTURE_SIZE || height > MAX_TEXTURE_SIZE {
+            return Err(RenderTargetError::InvalidSize);
+        }
+        let display_format = format
+            .as_display()
+            .ok_or(RenderTargetError::UnsupportedFormat(format))?;
+
+        // Framebuffers are at least 64 pixels wide, and textures are sampled up to a power-of-two height.
+        let stride = width.next_power_of_two().max(64);
+        let rows = height.next_power_of_two();
+        let pixels =
+            vram::alloc(format.bytes_for(stride * rows) as u32).map_err(RenderTargetError::Vram)?;
+        let depth = vram::alloc(stride * height * 2).map_err(RenderTargetError::Vram)?;
+
+        Ok(Self {
+            texture: Rc::new(Texture::from_vram(pixels, width, height, stride, format)),
+            pixels,
+            depth,
+            format: display_format,
+            width,
+            height,
+            stride,
+        })
+    }
+
+    /// Returns the texture holding what was drawn into the target, to be drawn by sprites.
+    pub fn texture(&self) -> Rc<Texture> {
+        self.texture.clone()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub(crate) fn format(&self) -> DisplayPixelFormat {
+        self.format
+    }
+
+    pub(crate) fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Returns the address of the pixels relative to the start of VRAM, as the GE expects it.
+    pub(crate) fn pixels(&self) -> *mut c_void {
+        self.pixels.from_zero as *mut c_void
+    }
+
+    pub(crate) fn depth(&self) -> *mut c_void {
+        self.depth.from_zero as *mut c_void
+    }
+}
@@ -0,0 +1,232 @@
+//! Glyph metrics of bitmap fonts, read from AngelCode BMFont `.fnt` files or laid out as a fixed grid.
+//! Nothing here touches the hardware, so it runs the same on the PSP and on the host.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+/// Errors that may happen when reading a font description.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontError {
+    /// The line (counting from `1`) isn't a valid `tag key=value ...` line.
+    Syntax(usize),
+    /// A field the font needs is missing.
+    MissingField(&'static str),
+    /// The file is a BMFont variant that isn't supported, such as the binary or XML formats.
+    Unsupported(&'static str),
+}
+
+/// Where a glyph is in the font's texture and how it is placed, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Glyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Distance from the pen position to the glyph's top-left corner.
+    pub x_offset: f32,
+    pub y_offset: f32,
+    /// How far the pen moves after the glyph.
+    pub x_advance: f32,
+    /// Index of the texture the glyph is in.
+    pub page: usize,
+}
+
+/// The glyphs of a font and how they are spaced.
+#[derive(Clone, Debug, Default)]
+pub struct FontData {
+    /// Distance between two lines, in pixels.
+    pub line_height: f32,
+    /// Distance from the top of a line to the baseline, in pixels.
+    pub base: f32,
+    /// File names of the textures the glyphs are in, as written in the font description.
+    pub pages: Vec<String>,
+    glyphs: BTreeMap<char, Glyph>,
+    kerning: BTreeMap<(char, char), f32>,
+}
+
+impl FontData {
+    pub fn new(line_height: f32, base: f32) -> Self {
+        Self {
+            line_height,
+            base,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_glyph(&mut self, character: char, glyph: Glyph) {
+        self.glyphs.insert(character, glyph);
+    }
+
+    /// Adds `amount` pixels between `first` and `second` when they follow each other.
+    pub fn add_kerning(&mut self, first: char, second: char, amount: f32) {
+        self.kerning.insert((first, second), amount);
+    }
+
+    pub fn remove_glyph(&mut self, character: char) {
+        self.glyphs.remove(&character);
+    }
+
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+
+    /// Returns the extra space between `first` and `second`, usually negative.
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+
+    /// Reads an AngelCode BMFont description in the text format.
+    pub fn from_bmfont(text: &str) -> Result<Self, FontError> {
+        if text.starts_with("BMF") {
+            return Err(FontError::Unsupported("binary BMFont"));
+        }
+        if text.trim_start().starts_with('<') {
+            return Err(FontError::Unsupported("XML BMFont"));
+        }
+
+        let mut font = FontData::default();
+        let mut has_common = false;
+        for (number, line) in text.lines().enumerate() {
+            let mut tokens = Tokens::new(line);
+            let tag = match tokens.next_tag() {
+                Some(tag) => tag,
+                None => continue,
+            };
+            let mut fields = BTreeMap::new();
+            while let Some(field) = tokens.next_field() {
+                let (key, value) = field.ok_or(FontError::Syntax(number + 1))?;
+                fields.insert(key, value);
+            }
+            let int = |key: &'static str| -> Result<i32, FontError> {
+                fields
+                    .get(key)
+                    .ok_or(FontError::MissingField(key))?
+                    .parse()
+                    .map_err(|_| FontError::Syntax(number + 1))
+            };
+
+            match tag {
+                "common" => {
+                    font.line_height = int("lineHeight")? as f32;
+                    font.base = int("base")? as f32;
+                    has_common = true;
+                }
+                "page" => {
+                    let id = int("id")? as usize;
+                    let file = fields.get("file").ok_or(FontError::MissingField("file"))?;
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = String::from(*file);
+                }
+                "char" => {
+                    let character = match char::from_u32(int("id")? as u32) {
+                        Some(character) => character,
+                        // BMFont writes `id=-1` for the glyph shown in place of missing characters.
+                        None => continue,
+                    };
+                    font.add_glyph(
+                        character,
+                        Glyph {
+                            x: int("x")? as u32,
+                            y: int("y")? as u32,
+                            width: int("width")? as u32,
+                            height: int("height")? as u32,
+                            x_offset: int("xoffset")? as f32,
+                            y_offset: int("yoffset")? as f32,
+                            x_advance: int("xadvance")? as f32,
+                            page: int("page").unwrap_or(0) as usize,
+                        },
+                    );
+                }
+                "kerning" => {
+                    if let (Some(first), Some(second)) = (
+                        char::from_u32(int("first")? as u32),
+                        char::from_u32(int("second")? as u32),
+                    ) {
+                        font.add_kerning(first, second, int("amount")? as f32);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !has_common {
+            return Err(FontError::MissingField("common"));
+        }
+        Ok(font)
+    }
+
+    /// Describes a font whose glyphs are cells of `cell_width` by `cell_height` pixels, `columns` per row,
+    /// holding `count` consecutive characters starting from `first`.
+    pub fn grid(first: char, count: u32, columns: u32, cell_width: u32, cell_height: u32) -> Self {
+        let mut font = FontData::new(cell_height as f32, cell_height as f32);
+        for index in 0..count {
+            if let Some(character) = char::from_u32(first as u32 + index) {
+                font.add_glyph(
+                    character,
+                    Glyph {
+                        x: (index % columns.max(1)) * cell_width,
+                        y: (index / columns.max(1)) * cell_height,
+                        width: cell_width,
+                        height: cell_height,
+                        x_offset: 0.0,
+                        y_offset: 0.0,
+                        x_advance: cell_width as f32,
+                        page: 0,
+                    },
+                );
+            }
+        }
+        font
+    }
+}
+
+/// Splits a BMFont line into its tag and `key=value` fields, values being optionally quoted.
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { rest: line }
+    }
+
+    fn next_tag(&mut self) -> Option<&'a str> {
+        self.rest = self.rest.trim_start();
+        let end = self.rest.find(' ').unwrap_or(self.rest.len());
+        let tag = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        if tag.is_empty() {
+            None
+        } else {
+            Some(tag)
+        }
+    }
+
+    /// Returns the next field, `Some(None)` if it is malformed.
+    fn next_field(&mut self) -> Option<Option<(&'a str, &'a str)>> {
+        self.rest = self.rest.trim_start();
+        if self.rest.is_empty() {
+            return None;
+        }
+        let equals = match self.rest.find('=') {
+            Some(equals) => equals,
+            None => return Some(None),
+        };
+        let key = &self.rest[..equals];
+        let value_start = &self.rest[equals + 1..];
+
+        let (value, rest) = if let Some(quoted) = value_start.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => return Some(None),
+            }
+        } else {
+            let end = value_start.find(' ').unwrap_or(value_start.len());
+            (&value_start[..end], &value_start[end..])
+        };
+        self.rest = rest;
+        Some(Some((key, value)))
+    }
+}
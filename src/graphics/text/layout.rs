@@ -0,0 +1,331 @@
+//! Placement of the glyphs of a text: kerning, line wrapping and alignment. Nothing here touches the
+//! hardware, so it runs the same on the PSP and on the host.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::font::{FontData, Glyph};
+
+/// Character drawn in place of those missing from the font, if the font has it.
+const REPLACEMENT: char = '?';
+
+/// How the lines of a text are aligned with each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// How a text is laid out, all distances being in the font's pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LayoutOptions {
+    pub align: Align,
+    /// Width after which lines are wrapped, between words when possible.
+    pub max_width: Option<f32>,
+    /// Extra space between lines.
+    pub line_spacing: f32,
+    /// Extra space between characters.
+    pub letter_spacing: f32,
+}
+
+/// A glyph placed at `x`, `y`, the position of its top-left corner from the top-left corner of the text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub character: char,
+    pub glyph: Glyph,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// The glyphs of a laid out text and the size of the box containing them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    /// Glyphs to draw, leaving out those without pixels such as spaces.
+    pub glyphs: Vec<PlacedGlyph>,
+    pub width: f32,
+    pub height: f32,
+    pub lines: usize,
+}
+
+/// Lays out `text`, breaking lines on `\n` and, with `LayoutOptions::max_width`, where they get too long.
+pub fn layout(font: &FontData, text: &str, options: &LayoutOptions) -> Layout {
+    let lines = break_lines(font, text, options);
+
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|line| line_width(font, line, options))
+        .collect();
+    let width = options
+        .max_width
+        .unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+    let line_advance = font.line_height + options.line_spacing;
+
+    let mut glyphs = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut pen = match options.align {
+            Align::Left => 0.0,
+            Align::Center => (width - widths[index]) / 2.0,
+            Align::Right => width - widths[index],
+        };
+        let top = index as f32 * line_advance;
+
+        let mut previous = None;
+        for &character in line {
+            let (character, glyph) = match lookup(font, character) {
+                Some(found) => found,
+                None => continue,
+            };
+            if let Some(previous) = previous {
+                pen += font.kerning(previous, character);
+            }
+            if glyph.width > 0 && glyph.height > 0 {
+                glyphs.push(PlacedGlyph {
+                    character,
+                    glyph: *glyph,
+                    x: pen + glyph.x_offset,
+                    y: top + glyph.y_offset,
+                });
+            }
+            pen += glyph.x_advance + options.letter_spacing;
+            previous = Some(character);
+        }
+    }
+
+    Layout {
+        glyphs,
+        width,
+        height: if lines.is_empty() {
+            0.0
+        } else {
+            lines.len() as f32 * line_advance - options.line_spacing
+        },
+        lines: lines.len(),
+    }
+}
+
+/// Returns the width and height of `text` once laid out.
+pub fn measure(font: &FontData, text: &str, options: &LayoutOptions) -> (f32, f32) {
+    let layout = layout(font, text, options);
+    (layout.width, layout.height)
+}
+
+/// Returns the glyph for `character`, or the replacement one if the font doesn't have it.
+fn lookup(font: &FontData, character: char) -> Option<(char, &Glyph)> {
+    font.glyph(character)
+        .map(|glyph| (character, glyph))
+        .or_else(|| font.glyph(REPLACEMENT).map(|glyph| (REPLACEMENT, glyph)))
+}
+
+/// Returns the pen distance covered by `line`, without the letter spacing after its last character.
+fn line_width(font: &FontData, line: &[char], options: &LayoutOptions) -> f32 {
+    let mut width: f32 = 0.0;
+    let mut previous = None;
+    for &character in line {
+        let (character, glyph) = match lookup(font, character) {
+            Some(found) => found,
+            None => continue,
+        };
+        if let Some(previous) = previous {
+            width += font.kerning(previous, character) + options.letter_spacing;
+        }
+        width += glyph.x_advance;
+        previous = Some(character);
+    }
+    width
+}
+
+/// Splits `text` into lines of characters, wrapping words that don't fit within `max_width`. Spaces where a
+/// line is wrapped are dropped, and words longer than a whole line are split between characters.
+fn break_lines(font: &FontData, text: &str, options: &LayoutOptions) -> Vec<Vec<char>> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let paragraph: Vec<char> = paragraph.chars().filter(|&c| c != '\r').collect();
+        let max_width = match options.max_width {
+            Some(max_width) => max_width,
+            None => {
+                lines.push(paragraph);
+                continue;
+            }
+        };
+
+        let mut line: Vec<char> = Vec::new();
+        let mut position = 0;
+        while position < paragraph.len() {
+            // The next word, along with the spaces in front of it.
+            let mut end = position;
+            while end < paragraph.len() && paragraph[end] == ' ' {
+                end += 1;
+            }
+            while end < paragraph.len() && paragraph[end] != ' ' {
+                end += 1;
+            }
+            let word = &paragraph[position..end];
+            position = end;
+
+            let mut candidate = line.clone();
+            candidate.extend_from_slice(word);
+            if line_width(font, &candidate, options) <= max_width {
+                line = candidate;
+                continue;
+            }
+
+            // Starts a new line with the word, dropping its leading spaces.
+            let word: Vec<char> = word.iter().copied().skip_while(|&c| c == ' ').collect();
+            if !line.is_empty() {
+                lines.push(core::mem::take(&mut line));
+            }
+            for character in word {
+                line.push(character);
+                if line.len() > 1 && line_width(font, &line, options) > max_width {
+                    line.pop();
+                    lines.push(core::mem::replace(&mut line, alloc::vec![character]));
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    /// Returns a font whose lowercase letters, `A`, `V` and `?` are 8 pixels apart, with 4 pixels wide spaces.
+    fn font() -> FontData {
+        let mut font = FontData::new(12.0, 10.0);
+        for character in ('a'..='z').chain(['A', 'V', '?']) {
+            font.add_glyph(
+                character,
+                Glyph {
+                    width: 6,
+                    height: 10,
+                    x_offset: 1.0,
+                    x_advance: 8.0,
+                    ..Default::default()
+                },
+            );
+        }
+        font.add_glyph(
+            ' ',
+            Glyph {
+                x_advance: 4.0,
+                ..Default::default()
+            },
+        );
+        font.add_kerning('A', 'V', -2.0);
+        font
+    }
+
+    fn lines(text: &str, options: &LayoutOptions) -> Vec<String> {
+        break_lines(&font(), text, options)
+            .into_iter()
+            .map(|line| line.into_iter().collect())
+            .collect()
+    }
+
+    fn wrapped(max_width: f32) -> LayoutOptions {
+        LayoutOptions {
+            max_width: Some(max_width),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn kerning() {
+        let layout = layout(&font(), "AVa", &LayoutOptions::default());
+        let x: Vec<f32> = layout.glyphs.iter().map(|glyph| glyph.x).collect();
+        assert_eq!(x, [1.0, 7.0, 15.0]);
+        assert_eq!(layout.width, 22.0);
+    }
+
+    #[test]
+    fn spaces_and_letter_spacing() {
+        let options = LayoutOptions {
+            letter_spacing: 1.0,
+            line_spacing: 3.0,
+            ..Default::default()
+        };
+        let layout = layout(&font(), "a b\nc", &options);
+        // Spaces have no pixels, so they aren't placed but still move the pen.
+        let x: Vec<f32> = layout.glyphs.iter().map(|glyph| glyph.x).collect();
+        assert_eq!(x, [1.0, 15.0, 1.0]);
+        assert_eq!(layout.glyphs[2].y, 15.0);
+        assert_eq!(layout.width, 22.0);
+        assert_eq!(layout.height, 27.0);
+        assert_eq!(layout.lines, 2);
+    }
+
+    #[test]
+    fn wraps_at_max_width() {
+        assert_eq!(lines("ab ab ab", &wrapped(40.0)), ["ab ab", "ab"]);
+        assert_eq!(lines("abc abc abc", &wrapped(40.0)), ["abc", "abc", "abc"]);
+        // Lines exactly as wide as `max_width` fit.
+        assert_eq!(lines("abc ab", &wrapped(44.0)), ["abc ab"]);
+
+        let layout = layout(&font(), "abc abc abc", &wrapped(40.0));
+        assert_eq!(layout.lines, 3);
+        assert_eq!(layout.width, 40.0);
+        assert_eq!(layout.height, 36.0);
+    }
+
+    #[test]
+    fn splits_long_words() {
+        assert_eq!(lines("abcdefg", &wrapped(20.0)), ["ab", "cd", "ef", "g"]);
+        assert_eq!(lines("a bcdefg", &wrapped(20.0)), ["a", "bc", "de", "fg"]);
+        // A line holds at least one character, however narrow.
+        assert_eq!(lines("abc", &wrapped(1.0)), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn alignment() {
+        let placed = |align| {
+            let options = LayoutOptions {
+                align,
+                ..Default::default()
+            };
+            let layout = layout(&font(), "ab\nabcd", &options);
+            assert_eq!(layout.width, 32.0);
+            (layout.glyphs[0].x, layout.glyphs[2].x)
+        };
+        assert_eq!(placed(Align::Left), (1.0, 1.0));
+        assert_eq!(placed(Align::Center), (9.0, 1.0));
+        assert_eq!(placed(Align::Right), (17.0, 1.0));
+
+        let options = LayoutOptions {
+            align: Align::Right,
+            max_width: Some(40.0),
+            ..Default::default()
+        };
+        let layout = layout(&font(), "ab", &options);
+        assert_eq!(layout.glyphs[0].x, 25.0);
+    }
+
+    #[test]
+    fn line_breaks() {
+        let options = LayoutOptions::default();
+        assert_eq!(lines("ab\r\ncd", &options), ["ab", "cd"]);
+        assert_eq!(lines("ab\n\ncd\n", &options), ["ab", "", "cd", ""]);
+        assert_eq!(lines("ab\r\ncd", &wrapped(100.0)), ["ab", "cd"]);
+        assert_eq!(layout(&font(), "", &options).height, 12.0);
+    }
+
+    #[test]
+    fn missing_glyphs() {
+        let mut font = font();
+        let text = layout(&font, "a!b", &LayoutOptions::default());
+        let characters: String = text.glyphs.iter().map(|glyph| glyph.character).collect();
+        assert_eq!(characters, "a?b");
+        assert_eq!(text.width, 24.0);
+
+        // Without a replacement glyph, missing characters are left out.
+        font.remove_glyph('?');
+        let text = layout(&font, "a!b", &LayoutOptions::default());
+        let characters: String = text.glyphs.iter().map(|glyph| glyph.character).collect();
+        assert_eq!(characters, "ab");
+        assert_eq!(text.width, 16.0);
+    }
+}
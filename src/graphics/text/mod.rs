@@ -0,0 +1,466 @@
+extern crate alloc;
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::{Ref, RefCell},
+    mem::size_of,
+    ptr,
+};
+
+use psp::sys::{
+    sceGuEnable, sceGuGetMemory, sceGuTexFilter, sceGuTexFunc, sceGuTexOffset, sceGuTexScale,
+    sceGuTexWrap, sceGumDrawArray, sceGumLoadIdentity, sceGumMatrixMode, sceGumPopMatrix,
+    sceGumPushMatrix, sceGumRotateZ, sceGumScale, sceGumTranslate, GuPrimitive, GuState,
+    GuTexWrapMode, MatrixMode, ScePspFVector3, TextureColorComponent, TextureEffect, VertexType,
+};
+
+use crate::core::{
+    io::{File, IoError},
+    Vec2, Vec3,
+};
+use crate::graphics::{
+    canvas,
+    colors::Color,
+    sprite::Filter,
+    texture::{Dither, PixelFormat, Region, Texture, TextureError},
+    Drawable, Vertex, PI,
+};
+
+/// The `font` module reads the glyph metrics of BMFont and grid fonts, without depending on the PSP.
+pub mod font;
+pub use font::{FontData, FontError, Glyph};
+/// The `layout` module places the glyphs of a text, without depending on the PSP.
+pub mod layout;
+pub use layout::{Align, Layout, LayoutOptions};
+/// The `truetype` module renders the glyphs of TrueType/OpenType fonts into a texture as they are needed.
+#[cfg(feature = "ttf")]
+mod truetype;
+#[cfg(feature = "ttf")]
+pub use truetype::TrueTypeError;
+
+/// Errors that may happen when loading a `Font`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontLoadError {
+    /// The font description is malformed.
+    Font(FontError),
+    /// Fewer textures were given than the font has pages.
+    MissingPage(usize),
+    /// A page's texture couldn't be loaded.
+    Texture(TextureError),
+    /// The font file couldn't be read.
+    Io(IoError),
+    /// The TrueType/OpenType font couldn't be loaded.
+    #[cfg(feature = "ttf")]
+    TrueType(TrueTypeError),
+}
+
+/// A font: glyph metrics and the textures (pages) holding the glyphs. Bitmap fonts have all their glyphs
+/// drawn beforehand, while TrueType/OpenType fonts render them into a cache texture as texts need them.
+pub struct Font {
+    data: RefCell<FontData>,
+    pages: Vec<Rc<Texture>>,
+    #[cfg(feature = "ttf")]
+    cache: Option<RefCell<truetype::GlyphCache>>,
+}
+
+impl Font {
+    /// Returns a font from its metrics and pages.
+    pub fn new(data: FontData, pages: Vec<Rc<Texture>>) -> Result<Self, FontLoadError> {
+        if pages.len() < data.pages.len().max(1) {
+            return Err(FontLoadError::MissingPage(pages.len()));
+        }
+        Ok(Self {
+            data: RefCell::new(data),
+            pages,
+            #[cfg(feature = "ttf")]
+            cache: None,
+        })
+    }
+
+    /// Returns a font from an AngelCode BMFont description in the text format and its page textures, in
+    /// the order of their ids.
+    pub fn from_bmfont(description: &str, pages: Vec<Rc<Texture>>) -> Result<Self, FontLoadError> {
+        let data = FontData::from_bmfont(description).map_err(FontLoadError::Font)?;
+        Self::new(data, pages)
+    }
+
+    /// Returns a font loading a BMFont description and its pages from the memory stick, the pages being
+    /// looked up next to the description and converted to `format`.
+    pub fn load_bmfont(path: &str, format: PixelFormat) -> Result<Self, FontLoadError> {
+        let description = File::read_to_end(path).map_err(FontLoadError::Io)?;
+        let description = String::from_utf8_lossy(&description);
+        let data = FontData::from_bmfont(&description).map_err(FontLoadError::Font)?;
+
+        let directory = path.rfind('/').map_or("", |end| &path[..end + 1]);
+        let mut pages = Vec::with_capacity(data.pages.len());
+        for page in &data.pages {
+            let texture = Texture::load(
+                &alloc::format!("{}{}", directory, page),
+                format,
+                Dither::None,
+            )
+            .map_err(FontLoadError::Texture)?;
+            pages.push(Rc::new(texture));
+        }
+        Self::new(data, pages)
+    }
+
+    /// Returns a font whose glyphs are cells of `cell_width` by `cell_height` pixels filling `texture`
+    /// from left to right and top to bottom, starting from the character `first`.
+    pub fn from_grid(texture: Rc<Texture>, first: char, cell_width: u32, cell_height: u32) -> Self {
+        let columns = texture.width() / cell_width.max(1);
+        let rows = texture.height() / cell_height.max(1);
+        Self {
+            data: RefCell::new(FontData::grid(
+                first,
+                columns * rows,
+                columns,
+                cell_width,
+                cell_height,
+            )),
+            pages: alloc::vec![texture],
+            #[cfg(feature = "ttf")]
+            cache: None,
+        }
+    }
+
+    /// Returns a font rendering the glyphs of a TrueType/OpenType font `px` pixels high as they are
+    /// needed, into a `cache_size` by `cache_size` texture. Once it is full, the glyphs used least recently
+    /// make room for new ones, so it should be big enough for the glyphs shown at once.
+    #[cfg(feature = "ttf")]
+    pub fn truetype(data: &[u8], px: f32, cache_size: u32) -> Result<Self, FontLoadError> {
+        let (cache, data) =
+            truetype::GlyphCache::new(data, px, cache_size).map_err(FontLoadError::TrueType)?;
+        Ok(Self {
+            data: RefCell::new(data),
+            pages: Vec::new(),
+            cache: Some(RefCell::new(cache)),
+        })
+    }
+
+    /// Returns a font loading a TrueType/OpenType file from the memory stick, see `Font::truetype`.
+    #[cfg(feature = "ttf")]
+    pub fn load_truetype(path: &str, px: f32, cache_size: u32) -> Result<Self, FontLoadError> {
+        let data = File::read_to_end(path).map_err(FontLoadError::Io)?;
+        Self::truetype(&data, px, cache_size)
+    }
+
+    /// Returns the glyph metrics. Those of TrueType/OpenType fonts only hold the glyphs rendered so far.
+    pub fn data(&self) -> Ref<'_, FontData> {
+        self.data.borrow()
+    }
+
+    /// Returns the textures holding the glyphs of a bitmap font.
+    pub fn pages(&self) -> &[Rc<Texture>] {
+        &self.pages
+    }
+
+    /// Returns the width and height `text` takes once laid out, in the font's pixels.
+    pub fn measure(&self, text: &str, options: &LayoutOptions) -> Vec2<f32> {
+        self.prepare(text);
+        let (width, height) = layout::measure(&self.data.borrow(), text, options);
+        Vec2::new(width, height)
+    }
+
+    /// Makes sure the glyphs of `text` are available, returning a number that changes whenever glyphs laid
+    /// out before may have moved.
+    pub(crate) fn prepare(&self, text: &str) -> u32 {
+        #[cfg(feature = "ttf")]
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.borrow_mut();
+            cache.prepare(text, &mut self.data.borrow_mut(), canvas::frame());
+            return cache.generation();
+        }
+        let _ = text;
+        0
+    }
+
+    pub(crate) fn page_count(&self) -> usize {
+        #[cfg(feature = "ttf")]
+        if self.cache.is_some() {
+            return 1;
+        }
+        self.pages.len()
+    }
+
+    /// Returns the UV coordinates of `region` of a page, see `Texture::uv`.
+    pub(crate) fn page_uv(&self, page: usize, region: Region) -> Option<[f32; 4]> {
+        #[cfg(feature = "ttf")]
+        if let Some(cache) = &self.cache {
+            return Some(cache.borrow().texture().uv(region));
+        }
+        self.pages.get(page).map(|texture| texture.uv(region))
+    }
+
+    /// Sets a page as the texture used by the next draw calls.
+    pub(crate) fn bind_page(&self, page: usize) {
+        #[cfg(feature = "ttf")]
+        if let Some(cache) = &self.cache {
+            cache.borrow_mut().bind();
+            return;
+        }
+        if let Some(texture) = self.pages.get(page) {
+            texture.bind();
+        }
+    }
+}
+
+/// A text drawn with a bitmap `Font`, its position being the top-left corner of the text.
+#[derive(Clone)]
+pub struct Text {
+    font: Rc<Font>,
+    text: String,
+    options: LayoutOptions,
+    filter: Filter,
+
+    position: Vec3<f32>,
+    rotation: f32,
+    scale: Vec2<f32>,
+    color: Color,
+
+    layout: Layout,
+    /// Vertices of the glyphs in each page, six per glyph.
+    vertices: Vec<Vec<Vertex>>,
+    generation: u32,
+    dirty: bool,
+}
+
+impl Text {
+    pub fn new(font: Rc<Font>, text: &str, position: Vec3<f32>, color: Color) -> Self {
+        Self {
+            font,
+            text: String::from(text),
+            options: LayoutOptions::default(),
+            filter: Filter::Nearest,
+            position,
+            rotation: 0.0,
+            scale: Vec2::new(1.0, 1.0),
+            color,
+            layout: Layout::default(),
+            vertices: Vec::new(),
+            generation: 0,
+            dirty: true,
+        }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text.clear();
+            self.text.push_str(text);
+            self.dirty = true;
+        }
+    }
+
+    pub fn get_font(&self) -> &Rc<Font> {
+        &self.font
+    }
+
+    pub fn set_font(&mut self, font: Rc<Font>) {
+        self.font = font;
+        self.dirty = true;
+    }
+
+    pub fn get_align(&self) -> Align {
+        self.options.align
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.options.align = align;
+        self.dirty = true;
+    }
+
+    /// Returns the width after which lines are wrapped, in screen pixels.
+    pub fn get_max_width(&self) -> Option<f32> {
+        self.options
+            .max_width
+            .map(|max_width| max_width * self.scale.x)
+    }
+
+    /// Wraps lines longer than `max_width` screen pixels, between words when possible.
+    pub fn set_max_width(&mut self, max_width: Option<f32>) {
+        self.options.max_width = max_width.map(|max_width| max_width / self.scale.x);
+        self.dirty = true;
+    }
+
+    /// Sets the extra space between lines and between characters, in the font's pixels.
+    pub fn set_spacing(&mut self, line_spacing: f32, letter_spacing: f32) {
+        self.options.line_spacing = line_spacing;
+        self.options.letter_spacing = letter_spacing;
+        self.dirty = true;
+    }
+
+    /// Sets how glyphs are sampled, `Filter::Linear` smoothing text drawn scaled.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Returns the laid out glyphs.
+    pub fn layout(&mut self) -> &Layout {
+        self.update_layout();
+        &self.layout
+    }
+
+    /// Returns the width and height of the text on screen, scale included.
+    pub fn measure(&mut self) -> Vec2<f32> {
+        self.update_layout();
+        Vec2::new(
+            self.layout.width * self.scale.x,
+            self.layout.height * self.scale.y,
+        )
+    }
+
+    fn update_layout(&mut self) {
+        let generation = self.font.prepare(&self.text);
+        if !self.dirty && generation == self.generation {
+            return;
+        }
+        self.dirty = false;
+        self.generation = generation;
+        self.layout = layout::layout(&self.font.data(), &self.text, &self.options);
+
+        let color = self.color.as_abgr();
+        self.vertices = alloc::vec![Vec::new(); self.font.page_count()];
+        for placed in &self.layout.glyphs {
+            let glyph = &placed.glyph;
+            let uv = match self.font.page_uv(
+                glyph.page,
+                Region::new(glyph.x, glyph.y, glyph.width, glyph.height),
+            ) {
+                Some(uv) => uv,
+                None => continue,
+            };
+            let (left, top) = (placed.x, placed.y);
+            let (right, bottom) = (left + glyph.width as f32, top + glyph.height as f32);
+            let corner = |u: f32, v: f32, x: f32, y: f32| Vertex {
+                u,
+                v,
+                color,
+                x,
+                y,
+                z: 0.0,
+            };
+
+            let top_left = corner(uv[0], uv[1], left, top);
+            let top_right = corner(uv[2], uv[1], right, top);
+            let bottom_left = corner(uv[0], uv[3], left, bottom);
+            let bottom_right = corner(uv[2], uv[3], right, bottom);
+            self.vertices[glyph.page].extend_from_slice(&[
+                top_left,
+                top_right,
+                bottom_left,
+                bottom_left,
+                top_right,
+                bottom_right,
+            ]);
+        }
+    }
+}
+
+impl Drawable for Text {
+    fn draw(&mut self) {
+        self.update_layout();
+
+        unsafe {
+            sceGuEnable(GuState::Texture2D);
+            sceGumMatrixMode(MatrixMode::Model);
+
+            sceGumPushMatrix();
+
+            sceGumLoadIdentity();
+
+            sceGumTranslate(&ScePspFVector3 {
+                x: self.position.x,
+                y: self.position.y,
+                z: self.position.z,
+            });
+            sceGumRotateZ(self.rotation);
+            sceGumScale(&ScePspFVector3 {
+                x: self.scale.x,
+                y: self.scale.y,
+                z: 1.0,
+            });
+
+            for (page, vertices) in self.vertices.iter().enumerate() {
+                if vertices.is_empty() {
+                    continue;
+                }
+
+                self.font.bind_page(page);
+                sceGuTexFunc(TextureEffect::Modulate, TextureColorComponent::Rgba);
+                sceGuTexFilter(self.filter.as_psp(), self.filter.as_psp());
+                sceGuTexScale(1.0, 1.0);
+                sceGuTexOffset(0.0, 0.0);
+                sceGuTexWrap(GuTexWrapMode::Clamp, GuTexWrapMode::Clamp);
+
+                // The vertices are copied into the display list, as the GE reads them after `draw` returns.
+                let memory =
+                    sceGuGetMemory((vertices.len() * size_of::<Vertex>()) as i32) as *mut Vertex;
+                ptr::copy_nonoverlapping(vertices.as_ptr(), memory, vertices.len());
+
+                sceGumDrawArray(
+                    GuPrimitive::Triangles,
+                    VertexType::TEXTURE_32BITF
+                        | VertexType::COLOR_8888
+                        | VertexType::VERTEX_32BITF
+                        | VertexType::TRANSFORM_3D,
+                    vertices.len() as i32,
+                    ptr::null(),
+                    memory as *const _,
+                );
+            }
+
+            sceGumPopMatrix();
+        }
+    }
+
+    /// Returns the size of the text on screen, see `Text::measure`.
+    fn get_size(&mut self) -> Vec2<f32> {
+        self.measure()
+    }
+
+    /// Wraps lines at `new_size.x` screen pixels, the height following from the number of lines.
+    fn set_size(&mut self, new_size: Vec2<f32>) {
+        self.set_max_width(Some(new_size.x));
+    }
+
+    fn get_scale(&mut self) -> Vec2<f32> {
+        self.scale
+    }
+
+    fn set_scale(&mut self, new_scale: Vec2<f32>) {
+        // Keeps the wrapping width the same on screen.
+        let max_width = self.get_max_width();
+        self.scale = new_scale;
+        if max_width.is_some() {
+            self.set_max_width(max_width);
+        }
+    }
+
+    fn get_pos(&mut self) -> Vec3<f32> {
+        self.position
+    }
+
+    fn set_pos(&mut self, new_position: Vec3<f32>) {
+        self.position = new_position;
+    }
+
+    fn get_rot(&mut self) -> f32 {
+        self.rotation * (180.0 / PI)
+    }
+
+    fn set_rot(&mut self, new_rotation: f32) {
+        self.rotation = new_rotation * (PI / 180.0);
+    }
+
+    fn get_color(&mut self) -> Color {
+        self.color
+    }
+
+    fn set_color(&mut self, new_color: Color) {
+        self.color = new_color;
+        self.dirty = true;
+    }
+}
@@ -0,0 +1,309 @@
+extern crate alloc;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use fontdue::{Font as Outlines, FontSettings};
+use psp::sys::sceGuTexFlush;
+
+use super::font::{FontData, Glyph};
+use crate::graphics::texture::{PixelFormat, Texture, TextureError};
+
+/// Errors that may happen when creating a glyph cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrueTypeError {
+    /// The font file couldn't be parsed.
+    Font(&'static str),
+    /// The cache texture couldn't be created.
+    Texture(TextureError),
+}
+
+struct Slot {
+    character: Option<char>,
+    last_used: u32,
+}
+
+/// Glyphs of a TrueType/OpenType font rendered on demand into a `Clut8` texture, split in square cells.
+/// Coverage values are stored as they are, indexing a palette of white with increasing alpha, and the least
+/// recently used glyphs make room for new ones once every cell is taken. Glyphs used in the frame being
+/// drawn are never evicted, as the GE only reads their pixels once the frame ends.
+pub(crate) struct GlyphCache {
+    outlines: Outlines,
+    px: f32,
+    base: f32,
+    texture: Texture,
+    cell: u32,
+    columns: u32,
+    slots: Vec<Slot>,
+    cached: BTreeMap<char, usize>,
+    kerned: BTreeSet<(char, char)>,
+    /// Frame the glyphs are being prepared for.
+    clock: u32,
+    generation: u32,
+    stale: bool,
+}
+
+impl GlyphCache {
+    /// Returns a cache rendering glyphs `px` pixels high into a `size` by `size` texture, along with the
+    /// font's metrics, which start without any glyph.
+    pub(crate) fn new(data: &[u8], px: f32, size: u32) -> Result<(Self, FontData), TrueTypeError> {
+        let outlines = Outlines::from_bytes(
+            data,
+            FontSettings {
+                scale: px,
+                ..FontSettings::default()
+            },
+        )
+        .map_err(TrueTypeError::Font)?;
+        let (ascent, line_height) = outlines
+            .horizontal_line_metrics(px)
+            .map_or((px, px), |metrics| (metrics.ascent, metrics.new_line_size));
+        let base = ceil(ascent);
+
+        let mut texture =
+            Texture::new(size, size, PixelFormat::Clut8).map_err(TrueTypeError::Texture)?;
+        let palette: Vec<[u8; 4]> = (0..=255).map(|alpha| [255, 255, 255, alpha]).collect();
+        texture
+            .set_palette(&palette)
+            .map_err(TrueTypeError::Texture)?;
+
+        // Cells are as tall as a line, plus a pixel so neighbours never bleed into each other.
+        let cell = (ceil(line_height.max(px)) as u32 + 1).min(size);
+        let columns = size / cell;
+        let slots = (0..columns * (size / cell))
+            .map(|_| Slot {
+                character: None,
+                last_used: 0,
+            })
+            .collect();
+
+        Ok((
+            Self {
+                outlines,
+                px,
+                base,
+                texture,
+                cell,
+                columns,
+                slots,
+                cached: BTreeMap::new(),
+                kerned: BTreeSet::new(),
+                clock: 0,
+                generation: 0,
+                stale: false,
+            },
+            FontData::new(ceil(line_height), base),
+        ))
+    }
+
+    /// Renders the glyphs of `text` that aren't cached yet, adding them and the kerning between them to
+    /// `data`, `frame` being the number of the frame the text is drawn in. Glyphs never evict those already
+    /// used in the same frame: those that don't fit are left out.
+    pub(crate) fn prepare(&mut self, text: &str, data: &mut FontData, frame: u32) {
+        self.clock = frame;
+        let mut rendered = false;
+        let mut previous = None;
+        for character in text.chars() {
+            if character == '\n' || character == '\r' {
+                previous = None;
+                continue;
+            }
+            rendered |= self.cache(character, data);
+
+            if let Some(previous) = previous {
+                if self.kerned.insert((previous, character)) {
+                    if let Some(amount) =
+                        self.outlines.horizontal_kern(previous, character, self.px)
+                    {
+                        data.add_kerning(previous, character, amount);
+                    }
+                }
+            }
+            previous = Some(character);
+        }
+
+        if rendered {
+            self.stale = true;
+        }
+    }
+
+    /// Changes each time glyphs are evicted, so texts know to lay themselves out again.
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub(crate) fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Sets the cache texture as the one used by the next draw calls.
+    pub(crate) fn bind(&mut self) {
+        if self.stale {
+            self.texture.flush();
+            // The GE may still hold the old texels in its texture cache.
+            unsafe { sceGuTexFlush() };
+            self.stale = false;
+        }
+        self.texture.bind();
+    }
+
+    /// Makes sure `character` is in `data`, returning whether its pixels had to be rendered.
+    fn cache(&mut self, character: char, data: &mut FontData) -> bool {
+        if let Some(&slot) = self.cached.get(&character) {
+            self.slots[slot].last_used = self.clock;
+            return false;
+        }
+        // Glyphs without pixels, such as spaces, never take a cell.
+        if data.glyph(character).is_some() {
+            return false;
+        }
+        let index = self.outlines.lookup_glyph_index(character);
+        if index == 0 {
+            return false;
+        }
+
+        let metrics = self.outlines.metrics_indexed(index, self.px);
+        let mut glyph = Glyph {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            x_offset: metrics.xmin as f32,
+            y_offset: self.base - (metrics.ymin + metrics.height as i32) as f32,
+            x_advance: metrics.advance_width,
+            page: 0,
+        };
+        if metrics.width == 0 || metrics.height == 0 {
+            data.add_glyph(character, glyph);
+            return false;
+        }
+
+        let slot = match self.free_slot() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        if let Some(evicted) = self.slots[slot].character.take() {
+            self.cached.remove(&evicted);
+            data.remove_glyph(evicted);
+            self.generation = self.generation.wrapping_add(1);
+        }
+        self.slots[slot] = Slot {
+            character: Some(character),
+            last_used: self.clock,
+        };
+        self.cached.insert(character, slot);
+
+        // Glyphs bigger than a cell, which fonts rarely have, are cropped.
+        let (_, coverage) = self.outlines.rasterize_indexed(index, self.px);
+        glyph.x = (slot as u32 % self.columns) * self.cell;
+        glyph.y = (slot as u32 / self.columns) * self.cell;
+        glyph.width = (metrics.width as u32).min(self.cell - 1);
+        glyph.height = (metrics.height as u32).min(self.cell - 1);
+
+        let stride = self.texture.stride() as usize;
+        let cell = self.cell as usize;
+        if let Some(pixels) = self.texture.pixels_mut() {
+            for row in 0..cell - 1 {
+                let start = (glyph.y as usize + row) * stride + glyph.x as usize;
+                let target = &mut pixels[start..start + cell - 1];
+                target.fill(0);
+                if row < glyph.height as usize {
+                    let source = &coverage[row * metrics.width..][..glyph.width as usize];
+                    target[..source.len()].copy_from_slice(source);
+                }
+            }
+        }
+        data.add_glyph(character, glyph);
+        true
+    }
+
+    /// Returns an empty cell, or the least recently used one not used in the current frame.
+    fn free_slot(&self) -> Option<usize> {
+        if let Some(free) = self.slots.iter().position(|slot| slot.character.is_none()) {
+            return Some(free);
+        }
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.last_used != self.clock)
+            .max_by_key(|(_, slot)| self.clock.wrapping_sub(slot.last_used))
+            .map(|(index, _)| index)
+    }
+}
+
+fn ceil(value: f32) -> f32 {
+    let truncated = value as i32 as f32;
+    if truncated < value {
+        truncated + 1.0
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a cache with room for 4 glyphs of a font whose letters `A` to `H` are squares.
+    fn cache() -> (GlyphCache, FontData) {
+        let (cache, data) =
+            GlyphCache::new(include_bytes!("fixtures/squares.ttf"), 14.0, 32).unwrap();
+        assert_eq!(cache.slots.len(), 4);
+        (cache, data)
+    }
+
+    fn cached(data: &FontData, characters: &str) -> bool {
+        characters
+            .chars()
+            .all(|character| data.glyph(character).is_some())
+    }
+
+    #[test]
+    fn glyphs_of_a_text_dont_evict_each_other() {
+        let (mut cache, mut data) = cache();
+        cache.prepare("ABCDE", &mut data, 0);
+        assert!(cached(&data, "ABCD"));
+        assert!(data.glyph('E').is_none());
+        assert_eq!(cache.generation(), 0);
+
+        // Spaces don't take a cell.
+        cache.prepare("A B", &mut data, 0);
+        assert!(data.glyph(' ').is_some());
+    }
+
+    #[test]
+    fn texts_of_a_frame_dont_evict_each_other() {
+        let (mut cache, mut data) = cache();
+        cache.prepare("ABC", &mut data, 1);
+        cache.prepare("DEF", &mut data, 1);
+        // The first text's glyphs are already in the frame's display list.
+        assert!(cached(&data, "ABCD"));
+        assert!(data.glyph('E').is_none() && data.glyph('F').is_none());
+        assert_eq!(cache.generation(), 0);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let (mut cache, mut data) = cache();
+        cache.prepare("ABCD", &mut data, 1);
+        let c = *data.glyph('C').unwrap();
+        cache.prepare("AB", &mut data, 2);
+        cache.prepare("E", &mut data, 3);
+        cache.prepare("F", &mut data, 3);
+
+        assert!(cached(&data, "ABEF"));
+        assert!(data.glyph('C').is_none() && data.glyph('D').is_none());
+        assert_ne!(cache.generation(), 0);
+
+        // The new glyphs took the evicted cells.
+        let cells: Vec<(u32, u32)> = "EF"
+            .chars()
+            .map(|character| {
+                let glyph = data.glyph(character).unwrap();
+                (glyph.x, glyph.y)
+            })
+            .collect();
+        assert!(cells.contains(&(c.x, c.y)));
+    }
+}
@@ -0,0 +1,162 @@
+//! Texture blobs: pixels already converted, padded and optionally swizzled on the host (see the
+//! `spspf-convert` tool), behind a 16-byte header describing them so they can be validated when loaded.
+//!
+//! Layout, all numbers little endian:
+//!
+//! | Offset | Size | Content                                               |
+//! |--------|------|-------------------------------------------------------|
+//! | 0      | 4    | Magic, `SPTX`                                         |
+//! | 4      | 1    | Version, `1`                                          |
+//! | 5      | 1    | `PixelFormat`, numbered as the PSP does (`0` to `5`)  |
+//! | 6      | 1    | Flags, bit 0 set if swizzled                          |
+//! | 7      | 1    | Reserved, `0`                                         |
+//! | 8      | 2    | Width, in pixels                                      |
+//! | 10     | 2    | Height, in pixels                                     |
+//! | 12     | 2    | Palette colors, `0` for formats without one           |
+//! | 14     | 2    | Reserved, `0`                                         |
+//! | 16     |      | Palette, RGBA8                                        |
+//! |        |      | Pixels, `width` and `height` padded to a power of two |
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use super::convert::PixelFormat;
+
+pub const MAGIC: [u8; 4] = *b"SPTX";
+pub const VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = 16;
+
+const FLAG_SWIZZLED: u8 = 1;
+
+/// Errors that may happen when reading a blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobError {
+    /// The data doesn't start with `SPTX`.
+    InvalidMagic,
+    /// The blob was made by a newer version of the tool.
+    UnsupportedVersion(u8),
+    /// The format number isn't one of `PixelFormat`.
+    UnknownFormat(u8),
+    /// The data is smaller than the header says.
+    Truncated,
+    /// Width or height is zero, or the palette doesn't fit the format.
+    Invalid,
+}
+
+/// Description of the pixels in a blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlobHeader {
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub swizzled: bool,
+    pub palette_colors: u32,
+}
+
+impl BlobHeader {
+    /// Distance between the start of two rows, in pixels.
+    pub fn stride(&self) -> u32 {
+        self.width.next_power_of_two()
+    }
+
+    /// Rows stored, the height padded to a power of two.
+    pub fn rows(&self) -> u32 {
+        self.height.next_power_of_two()
+    }
+
+    /// Bytes taken by the pixels.
+    pub fn pixels_size(&self) -> usize {
+        self.format.bytes_for(self.stride() * self.rows())
+    }
+
+    /// Returns the header as stored at the start of a blob.
+    pub fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = VERSION;
+        bytes[5] = format_id(self.format);
+        bytes[6] = if self.swizzled { FLAG_SWIZZLED } else { 0 };
+        bytes[8..10].copy_from_slice(&(self.width as u16).to_le_bytes());
+        bytes[10..12].copy_from_slice(&(self.height as u16).to_le_bytes());
+        bytes[12..14].copy_from_slice(&(self.palette_colors as u16).to_le_bytes());
+        bytes
+    }
+}
+
+/// A blob split into its parts.
+pub struct Blob<'a> {
+    pub header: BlobHeader,
+    pub palette: &'a [u8],
+    pub pixels: &'a [u8],
+}
+
+/// Reads a blob, checking its header against the data that follows.
+pub fn parse(data: &[u8]) -> Result<Blob<'_>, BlobError> {
+    let header = data.get(..HEADER_SIZE).ok_or(BlobError::Truncated)?;
+    if header[0..4] != MAGIC {
+        return Err(BlobError::InvalidMagic);
+    }
+    if header[4] > VERSION {
+        return Err(BlobError::UnsupportedVersion(header[4]));
+    }
+
+    let header = BlobHeader {
+        format: format_from_id(header[5]).ok_or(BlobError::UnknownFormat(header[5]))?,
+        width: u16::from_le_bytes([header[8], header[9]]) as u32,
+        height: u16::from_le_bytes([header[10], header[11]]) as u32,
+        swizzled: header[6] & FLAG_SWIZZLED != 0,
+        palette_colors: u16::from_le_bytes([header[12], header[13]]) as u32,
+    };
+    if header.width == 0
+        || header.height == 0
+        || header.palette_colors as usize > header.format.palette_size()
+    {
+        return Err(BlobError::Invalid);
+    }
+
+    let palette_end = HEADER_SIZE + header.palette_colors as usize * 4;
+    let pixels_end = palette_end + header.pixels_size();
+    if data.len() < pixels_end {
+        return Err(BlobError::Truncated);
+    }
+
+    Ok(Blob {
+        header,
+        palette: &data[HEADER_SIZE..palette_end],
+        pixels: &data[palette_end..pixels_end],
+    })
+}
+
+/// Builds a blob from its header, RGBA8 palette and padded pixels.
+pub fn build(header: &BlobHeader, palette: &[[u8; 4]], pixels: &[u8]) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(HEADER_SIZE + palette.len() * 4 + pixels.len());
+    blob.extend_from_slice(&header.to_bytes());
+    for color in palette {
+        blob.extend_from_slice(color);
+    }
+    blob.extend_from_slice(pixels);
+    blob
+}
+
+fn format_id(format: PixelFormat) -> u8 {
+    match format {
+        PixelFormat::Psm5650 => 0,
+        PixelFormat::Psm5551 => 1,
+        PixelFormat::Psm4444 => 2,
+        PixelFormat::Psm8888 => 3,
+        PixelFormat::Clut4 => 4,
+        PixelFormat::Clut8 => 5,
+    }
+}
+
+fn format_from_id(id: u8) -> Option<PixelFormat> {
+    Some(match id {
+        0 => PixelFormat::Psm5650,
+        1 => PixelFormat::Psm5551,
+        2 => PixelFormat::Psm4444,
+        3 => PixelFormat::Psm8888,
+        4 => PixelFormat::Clut4,
+        5 => PixelFormat::Clut8,
+        _ => return None,
+    })
+}
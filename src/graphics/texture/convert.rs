@@ -0,0 +1,413 @@
+//! Conversion of RGBA8 images into the pixel formats the PSP can sample. Nothing here touches the hardware,
+//! so it runs the same on the PSP and on the host.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// Pixel formats a `Texture` can be stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 16-bit RGB, 5 bits for red and blue and 6 for green, no alpha.
+    Psm5650,
+    /// 16-bit RGBA, 5 bits per color and 1 bit of alpha.
+    Psm5551,
+    /// 16-bit RGBA, 4 bits per channel.
+    Psm4444,
+    /// 32-bit RGBA, 8 bits per channel.
+    Psm8888,
+    /// 4-bit indices into a palette of up to 16 colors, two pixels per byte (first one on the low nibble).
+    Clut4,
+    /// 8-bit indices into a palette of up to 256 colors.
+    Clut8,
+}
+
+impl PixelFormat {
+    /// Returns how many bits a single pixel takes in this format.
+    pub fn bits_per_pixel(&self) -> u32 {
+        match self {
+            PixelFormat::Psm5650 | PixelFormat::Psm5551 | PixelFormat::Psm4444 => 16,
+            PixelFormat::Psm8888 => 32,
+            PixelFormat::Clut4 => 4,
+            PixelFormat::Clut8 => 8,
+        }
+    }
+
+    /// Returns how many bytes `pixels` pixels take in this format.
+    pub fn bytes_for(&self, pixels: u32) -> usize {
+        (pixels as usize * self.bits_per_pixel() as usize).div_ceil(8)
+    }
+
+    /// Returns how many colors the palette of this format holds, or `0` if it has no palette.
+    pub fn palette_size(&self) -> usize {
+        match self {
+            PixelFormat::Clut4 => 16,
+            PixelFormat::Clut8 => 256,
+            _ => 0,
+        }
+    }
+}
+
+/// How the loss of precision is spread when converting to a format with fewer bits per channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// Each pixel is rounded down to the nearest representable color, which may show banding.
+    None,
+    /// A 4x4 Bayer matrix is added before rounding, trading banding for a regular pattern.
+    Ordered,
+}
+
+/// Result of a conversion: the pixels, rows tightly packed (padded only to a full byte), and the palette in
+/// RGBA8 for the `Clut` formats.
+pub struct Converted {
+    pub pixels: Vec<u8>,
+    pub palette: Vec<[u8; 4]>,
+}
+
+const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Converts tightly packed RGBA8 pixels to `format`.
+pub fn convert(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    dither: Dither,
+) -> Converted {
+    match format {
+        PixelFormat::Psm8888 => Converted {
+            pixels: rgba[..(width * height * 4) as usize].to_vec(),
+            palette: Vec::new(),
+        },
+        PixelFormat::Psm5650 | PixelFormat::Psm5551 | PixelFormat::Psm4444 => Converted {
+            pixels: to_16bit(rgba, width, height, format, dither),
+            palette: Vec::new(),
+        },
+        PixelFormat::Clut4 | PixelFormat::Clut8 => {
+            let palette = quantize(rgba, width, height, format.palette_size());
+            Converted {
+                pixels: to_indexed(rgba, width, height, format, &palette, dither),
+                palette,
+            }
+        }
+    }
+}
+
+/// Packs one RGBA8 color into a 16-bit format, as laid out in memory by the PSP (little endian).
+pub fn pack_16bit(color: [u8; 4], format: PixelFormat) -> u16 {
+    let [r, g, b, a] = color.map(|channel| channel as u16);
+    match format {
+        PixelFormat::Psm5650 => (r >> 3) | (g >> 2) << 5 | (b >> 3) << 11,
+        PixelFormat::Psm5551 => (r >> 3) | (g >> 3) << 5 | (b >> 3) << 10 | (a >> 7) << 15,
+        PixelFormat::Psm4444 => (r >> 4) | (g >> 4) << 4 | (b >> 4) << 8 | (a >> 4) << 12,
+        _ => panic!("{:?} is not a 16-bit format", format),
+    }
+}
+
+/// Unpacks one color from a 16-bit format back to RGBA8, replicating the high bits into the low ones.
+pub fn unpack_16bit(pixel: u16, format: PixelFormat) -> [u8; 4] {
+    let expand = |value: u16, bits: u32| {
+        let value = value & ((1 << bits) - 1);
+        ((value << (8 - bits)) | (value >> (2 * bits).saturating_sub(8))) as u8
+    };
+    match format {
+        PixelFormat::Psm5650 => [
+            expand(pixel, 5),
+            expand(pixel >> 5, 6),
+            expand(pixel >> 11, 5),
+            255,
+        ],
+        PixelFormat::Psm5551 => [
+            expand(pixel, 5),
+            expand(pixel >> 5, 5),
+            expand(pixel >> 10, 5),
+            if pixel >> 15 != 0 { 255 } else { 0 },
+        ],
+        PixelFormat::Psm4444 => [
+            expand(pixel, 4),
+            expand(pixel >> 4, 4),
+            expand(pixel >> 8, 4),
+            expand(pixel >> 12, 4),
+        ],
+        _ => panic!("{:?} is not a 16-bit format", format),
+    }
+}
+
+fn channel_bits(format: PixelFormat) -> [u32; 4] {
+    match format {
+        PixelFormat::Psm5650 => [5, 6, 5, 8],
+        PixelFormat::Psm5551 => [5, 5, 5, 1],
+        PixelFormat::Psm4444 => [4, 4, 4, 4],
+        _ => [8, 8, 8, 8],
+    }
+}
+
+/// Returns the Bayer threshold for `(x, y)` scaled to the rounding step of a channel with `bits` bits, from
+/// `0` up to (not including) that step.
+fn bayer_offset(bits: u32, x: u32, y: u32) -> i32 {
+    if bits >= 8 {
+        return 0;
+    }
+    (BAYER[(y & 3) as usize][(x & 3) as usize] << (8 - bits)) / 16
+}
+
+fn to_16bit(rgba: &[u8], width: u32, height: u32, format: PixelFormat, dither: Dither) -> Vec<u8> {
+    let bits = channel_bits(format);
+    let mut pixels = Vec::with_capacity((width * height * 2) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let id = ((y * width + x) * 4) as usize;
+            let mut color = [rgba[id], rgba[id + 1], rgba[id + 2], rgba[id + 3]];
+            if dither == Dither::Ordered {
+                for (channel, bits) in color.iter_mut().zip(bits) {
+                    *channel = (*channel as i32 + bayer_offset(bits, x, y)).min(255) as u8;
+                }
+            }
+            pixels.extend_from_slice(&pack_16bit(color, format).to_le_bytes());
+        }
+    }
+    pixels
+}
+
+fn to_indexed(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    palette: &[[u8; 4]],
+    dither: Dither,
+) -> Vec<u8> {
+    // The fewer colors, the further apart they are and the stronger the dithering needs to be. Colors are
+    // matched to the nearest entry rather than rounded down, so the threshold is centered around zero.
+    let bits = if format == PixelFormat::Clut4 { 3 } else { 5 };
+    let half_step = 1 << (7 - bits);
+    let row = format.bytes_for(width);
+    let mut pixels = alloc::vec![0; row * height as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let id = ((y * width + x) * 4) as usize;
+            let mut color = [rgba[id], rgba[id + 1], rgba[id + 2], rgba[id + 3]];
+            if dither == Dither::Ordered {
+                let offset = bayer_offset(bits, x, y) - half_step;
+                for channel in color.iter_mut().take(3) {
+                    *channel = (*channel as i32 + offset).clamp(0, 255) as u8;
+                }
+            }
+            let index = nearest(palette, color);
+
+            let offset = y as usize * row;
+            if format == PixelFormat::Clut4 {
+                pixels[offset + x as usize / 2] |= index << ((x & 1) * 4);
+            } else {
+                pixels[offset + x as usize] = index;
+            }
+        }
+    }
+    pixels
+}
+
+/// Returns the index of the palette color closest to `color`.
+pub fn nearest(palette: &[[u8; 4]], color: [u8; 4]) -> u8 {
+    let mut best = 0;
+    let mut best_distance = u32::MAX;
+    for (id, entry) in palette.iter().enumerate() {
+        let distance: u32 = (0..4)
+            .map(|channel| {
+                let delta = entry[channel] as i32 - color[channel] as i32;
+                (delta * delta) as u32
+            })
+            .sum();
+        if distance < best_distance {
+            best = id;
+            best_distance = distance;
+        }
+    }
+    best as u8
+}
+
+/// Reduces the colors of an RGBA8 image to at most `max_colors` using median cut, weighting each color by
+/// how many pixels use it. Images that already fit keep their exact colors.
+pub fn quantize(rgba: &[u8], width: u32, height: u32, max_colors: usize) -> Vec<[u8; 4]> {
+    let mut colors: Vec<u32> = rgba[..(width * height * 4) as usize]
+        .chunks_exact(4)
+        .map(|pixel| u32::from_be_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+        .collect();
+    colors.sort_unstable();
+
+    // Unique colors along with how many pixels use them.
+    let mut unique: Vec<([u8; 4], u32)> = Vec::new();
+    for color in colors {
+        match unique.last_mut() {
+            Some((last, count)) if u32::from_be_bytes(*last) == color => *count += 1,
+            _ => unique.push((color.to_be_bytes(), 1)),
+        }
+    }
+
+    if unique.len() <= max_colors {
+        return unique.into_iter().map(|(color, _)| color).collect();
+    }
+
+    let mut boxes: Vec<(usize, usize)> = alloc::vec![(0, unique.len())];
+    while boxes.len() < max_colors {
+        // Splits the box spanning the widest channel range.
+        let mut widest = None;
+        for (id, &(start, end)) in boxes.iter().enumerate() {
+            if end - start < 2 {
+                continue;
+            }
+            let (channel, range) = widest_channel(&unique[start..end]);
+            if widest.is_none_or(|(_, _, best)| range > best) {
+                widest = Some((id, channel, range));
+            }
+        }
+        let (id, channel) = match widest {
+            Some((id, channel, _)) => (id, channel),
+            None => break,
+        };
+
+        let (start, end) = boxes[id];
+        let slice = &mut unique[start..end];
+        slice.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total: u32 = slice.iter().map(|(_, count)| count).sum();
+        let mut accumulated = 0;
+        let mut split = 1;
+        for (offset, (_, count)) in slice.iter().enumerate() {
+            accumulated += count;
+            if accumulated * 2 >= total {
+                split = (offset + 1).clamp(1, slice.len() - 1);
+                break;
+            }
+        }
+
+        boxes[id] = (start, start + split);
+        boxes.push((start + split, end));
+    }
+
+    boxes
+        .into_iter()
+        .map(|(start, end)| {
+            let mut sum = [0u64; 4];
+            let mut total = 0u64;
+            for (color, count) in &unique[start..end] {
+                for channel in 0..4 {
+                    sum[channel] += color[channel] as u64 * *count as u64;
+                }
+                total += *count as u64;
+            }
+            sum.map(|channel| ((channel + total / 2) / total) as u8)
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+    let mut min = [u8::MAX; 4];
+    let mut max = [u8::MIN; 4];
+    for (color, _) in colors {
+        for channel in 0..4 {
+            min[channel] = min[channel].min(color[channel]);
+            max[channel] = max[channel].max(color[channel]);
+        }
+    }
+    (0..4)
+        .map(|channel| (channel, max[channel] - min[channel]))
+        .max_by_key(|&(_, range)| range)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn image(colors: &[[u8; 4]]) -> Vec<u8> {
+        colors.iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn packs_channel_extremes() {
+        assert_eq!(pack_16bit(RED, PixelFormat::Psm5650), 0x001F);
+        assert_eq!(pack_16bit(GREEN, PixelFormat::Psm5650), 0x07E0);
+        assert_eq!(pack_16bit(BLUE, PixelFormat::Psm5650), 0xF800);
+        assert_eq!(pack_16bit([0, 0, 0, 255], PixelFormat::Psm5551), 0x8000);
+        assert_eq!(pack_16bit(BLUE, PixelFormat::Psm5551), 0xFC00);
+        assert_eq!(pack_16bit([0, 0, 0, 255], PixelFormat::Psm4444), 0xF000);
+        assert_eq!(pack_16bit(GREEN, PixelFormat::Psm4444), 0xF0F0);
+    }
+
+    #[test]
+    fn round_trips_channel_extremes() {
+        let opaque = [[0, 0, 0, 255], [255, 255, 255, 255], RED, GREEN, BLUE];
+        for format in [
+            PixelFormat::Psm5650,
+            PixelFormat::Psm5551,
+            PixelFormat::Psm4444,
+        ] {
+            for color in opaque {
+                let pixel = pack_16bit(color, format);
+                assert_eq!(unpack_16bit(pixel, format), color, "{:?}", format);
+            }
+        }
+        for format in [PixelFormat::Psm5551, PixelFormat::Psm4444] {
+            for color in [[0, 0, 0, 0], [255, 255, 255, 0], [0, 255, 0, 0]] {
+                let pixel = pack_16bit(color, format);
+                assert_eq!(unpack_16bit(pixel, format), color, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn indexed_pixels() {
+        // Few enough colors to be kept as they are, sorted: blue, green, red.
+        let rgba = image(&[RED, GREEN, BLUE, BLUE, BLUE, RED]);
+
+        let clut8 = convert(&rgba, 3, 2, PixelFormat::Clut8, Dither::None);
+        assert_eq!(clut8.palette, [BLUE, GREEN, RED]);
+        assert_eq!(clut8.pixels, [2, 1, 0, 0, 0, 2]);
+
+        // The first pixel of each byte is on the low nibble, and rows are padded to a full byte.
+        let clut4 = convert(&rgba, 3, 2, PixelFormat::Clut4, Dither::None);
+        assert_eq!(clut4.palette, [BLUE, GREEN, RED]);
+        assert_eq!(clut4.pixels, [0x12, 0x00, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn quantize_keeps_exact_colors() {
+        let rgba = image(&[RED, GREEN, RED, BLUE, GREEN, RED]);
+        assert_eq!(quantize(&rgba, 6, 1, 16), [BLUE, GREEN, RED]);
+    }
+
+    #[test]
+    fn quantize_caps_colors() {
+        let colors: Vec<[u8; 4]> = (0..64)
+            .map(|i| [i * 4, 255 - i * 4, (i % 8) * 32, 255])
+            .collect();
+        let rgba = image(&colors);
+        for max_colors in [16, 256] {
+            let palette = quantize(&rgba, 8, 8, max_colors);
+            assert_eq!(palette.len(), max_colors.min(64));
+        }
+        assert_eq!(quantize(&rgba, 8, 8, 2).len(), 2);
+    }
+
+    #[test]
+    fn dithers_only_when_enabled() {
+        // A gray halfway between two steps of every channel.
+        let between = image(&[[100, 98, 100, 255]; 16]);
+        let flat = convert(&between, 4, 4, PixelFormat::Psm5650, Dither::None);
+        assert!(flat
+            .pixels
+            .chunks_exact(2)
+            .all(|pixel| pixel == &flat.pixels[..2]));
+        let dithered = convert(&between, 4, 4, PixelFormat::Psm5650, Dither::Ordered);
+        assert_ne!(dithered.pixels, flat.pixels);
+
+        // Colors that are already representable stay as they are.
+        let exact = image(&[[96, 96, 96, 255]; 16]);
+        assert_eq!(
+            convert(&exact, 4, 4, PixelFormat::Psm5650, Dither::Ordered).pixels,
+            convert(&exact, 4, 4, PixelFormat::Psm5650, Dither::None).pixels
+        );
+    }
+}
@@ -0,0 +1,517 @@
+extern crate alloc;
+use alloc::vec::Vec;
+use core::{ffi::c_void, slice};
+
+use psp::{
+    sys::{
+        sceGuClutLoad, sceGuClutMode, sceGuTexImage, sceGuTexMode, sceKernelDcacheWritebackRange,
+        ClutPixelFormat, DisplayPixelFormat, MipmapLevel, TexturePixelFormat,
+    },
+    Align16,
+};
+
+use crate::core::io::{File, IoError};
+use crate::graphics::{
+    image::{self, Image, ImageError},
+    vram::{self, VramBlock, VramError},
+};
+
+/// The `blob` module reads and writes the texture blobs made by the `spspf-convert` tool.
+pub mod blob;
+pub use blob::BlobError;
+/// The `convert` module turns RGBA8 images into any `PixelFormat`, without depending on the PSP.
+pub mod convert;
+pub use convert::{Dither, PixelFormat};
+/// The `swizzle` module reorders pixels into the block layout the GE samples fastest.
+pub mod swizzle;
+
+impl PixelFormat {
+    pub(crate) fn as_psm(&self) -> TexturePixelFormat {
+        match self {
+            PixelFormat::Psm5650 => TexturePixelFormat::Psm5650,
+            PixelFormat::Psm5551 => TexturePixelFormat::Psm5551,
+            PixelFormat::Psm4444 => TexturePixelFormat::Psm4444,
+            PixelFormat::Psm8888 => TexturePixelFormat::Psm8888,
+            PixelFormat::Clut4 => TexturePixelFormat::PsmT4,
+            PixelFormat::Clut8 => TexturePixelFormat::PsmT8,
+        }
+    }
+
+    /// Returns the matching framebuffer format, `None` for the `Clut` formats the GE can't draw to.
+    pub(crate) fn as_display(&self) -> Option<DisplayPixelFormat> {
+        match self {
+            PixelFormat::Psm5650 => Some(DisplayPixelFormat::Psm5650),
+            PixelFormat::Psm5551 => Some(DisplayPixelFormat::Psm5551),
+            PixelFormat::Psm4444 => Some(DisplayPixelFormat::Psm4444),
+            PixelFormat::Psm8888 => Some(DisplayPixelFormat::Psm8888),
+            PixelFormat::Clut4 | PixelFormat::Clut8 => None,
+        }
+    }
+}
+
+/// Errors that may happen when creating a `Texture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureError {
+    /// Width or height is zero or larger than the `512` pixels the PSP can sample.
+    InvalidSize,
+    /// The provided pixel data is smaller than the texture needs.
+    NotEnoughData,
+    /// The provided pixel data does not start on a 16-byte boundary.
+    Misaligned,
+    /// The palette has more colors than the texture's format can index, or the format has no palette.
+    InvalidPalette,
+    /// Rows aren't a multiple of 16 bytes or the (power-of-two) height isn't a multiple of 8, so the
+    /// texture can't be swizzled.
+    NotSwizzlable,
+    /// The texture was created with `from_static`, so its pixels can't be changed.
+    ReadOnly,
+    /// The region doesn't lie within the texture.
+    OutOfBounds,
+    /// The texture couldn't be placed in VRAM.
+    Vram(VramError),
+    /// The image couldn't be decoded.
+    Image(ImageError),
+    /// The image file couldn't be read.
+    Io(IoError),
+    /// The texture blob is malformed.
+    Blob(BlobError),
+}
+
+/// Largest width and height the PSP can sample from.
+pub const MAX_TEXTURE_SIZE: u32 = 512;
+
+/// A rectangle of a texture, in pixels from its top-left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Heap buffer whose start is aligned to 16 bytes, as required by the GE for textures.
+pub(crate) struct AlignedBuffer {
+    blocks: Vec<Align16<[u8; 16]>>,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            blocks: alloc::vec![Align16([0; 16]); len.div_ceil(16)],
+            len,
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.len) }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.blocks.as_mut_ptr() as *mut u8, self.len) }
+    }
+}
+
+enum Pixels {
+    Static(&'static [u8]),
+    Owned(AlignedBuffer),
+    Vram(VramBlock, usize),
+}
+
+/// An image that can be sampled by the PSP. Rows are `stride` pixels apart, `stride` being the width padded
+/// to a power of two, and a single texture may be shared by many sprites through an `Rc<Texture>`.
+///
+/// For sprite-heavy scenes, `swizzle` the texture and `move_to_vram` it: both make sampling faster.
+pub struct Texture {
+    pixels: Pixels,
+    palette: Option<AlignedBuffer>,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: PixelFormat,
+    swizzled: bool,
+}
+
+impl Texture {
+    /// Returns a blank (fully transparent) texture.
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Result<Self, TextureError> {
+        check_size(width, height)?;
+        let stride = width.next_power_of_two();
+
+        Ok(Self {
+            pixels: Pixels::Owned(AlignedBuffer::new(
+                format.bytes_for(stride * height.next_power_of_two()),
+            )),
+            palette: None,
+            width,
+            height,
+            stride,
+            format,
+            swizzled: false,
+        })
+    }
+
+    /// Returns a texture copying tightly packed `pixels` (rows `width` pixels long) into padded storage.
+    pub fn from_pixels(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Result<Self, TextureError> {
+        let mut texture = Self::new(width, height, format)?;
+        let row = format.bytes_for(width);
+        if pixels.len() < row * height as usize {
+            return Err(TextureError::NotEnoughData);
+        }
+
+        let stride = format.bytes_for(texture.stride);
+        if let Pixels::Owned(buffer) = &mut texture.pixels {
+            for (src, dst) in pixels
+                .chunks_exact(row)
+                .zip(buffer.as_mut_slice().chunks_exact_mut(stride))
+            {
+                dst[..row].copy_from_slice(src);
+            }
+        }
+        texture.flush();
+
+        Ok(texture)
+    }
+
+    /// Returns a texture converting tightly packed RGBA8 `pixels` to `format`, building its palette for the
+    /// `Clut` formats.
+    pub fn from_rgba(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        dither: Dither,
+    ) -> Result<Self, TextureError> {
+        if pixels.len() < (width * height * 4) as usize {
+            return Err(TextureError::NotEnoughData);
+        }
+        let converted = convert::convert(pixels, width, height, format, dither);
+
+        let mut texture = Self::from_pixels(&converted.pixels, width, height, format)?;
+        if format.palette_size() > 0 {
+            texture.set_palette(&converted.palette)?;
+        }
+        Ok(texture)
+    }
+
+    /// Returns a texture from a decoded image, converted to `format`.
+    pub fn from_image(
+        image: &Image,
+        format: PixelFormat,
+        dither: Dither,
+    ) -> Result<Self, TextureError> {
+        Self::from_rgba(&image.pixels, image.width, image.height, format, dither)
+    }
+
+    /// Returns a texture decoding a PNG, BMP or TGA file held in memory, such as with `include_bytes!`.
+    pub fn decode(data: &[u8], format: PixelFormat, dither: Dither) -> Result<Self, TextureError> {
+        let image = image::decode(data).map_err(TextureError::Image)?;
+        Self::from_image(&image, format, dither)
+    }
+
+    /// Returns a texture decoding a PNG, BMP or TGA file from the memory stick.
+    pub fn load(path: &str, format: PixelFormat, dither: Dither) -> Result<Self, TextureError> {
+        let data = File::read_to_end(path).map_err(TextureError::Io)?;
+        Self::decode(&data, format, dither)
+    }
+
+    /// Returns a texture sampling straight from the pixels of a blob made by `spspf-convert`, such as an
+    /// `Align16` static filled with `include_bytes!`. Only the palette, if any, is copied.
+    pub fn from_blob(data: &'static [u8]) -> Result<Self, TextureError> {
+        let blob = blob::parse(data).map_err(TextureError::Blob)?;
+        let header = blob.header;
+        check_size(header.width, header.height)?;
+        if !(blob.pixels.as_ptr() as usize).is_multiple_of(16) {
+            return Err(TextureError::Misaligned);
+        }
+
+        let mut texture = Self {
+            pixels: Pixels::Static(blob.pixels),
+            palette: None,
+            width: header.width,
+            height: header.height,
+            stride: header.stride(),
+            format: header.format,
+            swizzled: header.swizzled,
+        };
+        texture.set_blob_palette(blob.palette)?;
+        Ok(texture)
+    }
+
+    /// Returns a texture reading a blob made by `spspf-convert` from the memory stick.
+    pub fn load_blob(path: &str) -> Result<Self, TextureError> {
+        let data = File::read_to_end(path).map_err(TextureError::Io)?;
+        let blob = blob::parse(&data).map_err(TextureError::Blob)?;
+        let header = blob.header;
+        check_size(header.width, header.height)?;
+
+        let mut pixels = AlignedBuffer::new(blob.pixels.len());
+        pixels.as_mut_slice().copy_from_slice(blob.pixels);
+        let mut texture = Self {
+            pixels: Pixels::Owned(pixels),
+            palette: None,
+            width: header.width,
+            height: header.height,
+            stride: header.stride(),
+            format: header.format,
+            swizzled: header.swizzled,
+        };
+        texture.set_blob_palette(blob.palette)?;
+        texture.flush();
+        Ok(texture)
+    }
+
+    fn set_blob_palette(&mut self, palette: &[u8]) -> Result<(), TextureError> {
+        if palette.is_empty() {
+            return Ok(());
+        }
+        let colors: Vec<[u8; 4]> = palette
+            .chunks_exact(4)
+            .map(|color| [color[0], color[1], color[2], color[3]])
+            .collect();
+        self.set_palette(&colors)
+    }
+
+    /// Returns a texture sampling straight from `pixels` without copying them, such as an `Align16` static
+    /// filled with `include_bytes!`. Rows must already be padded to `width.next_power_of_two()` pixels.
+    pub fn from_static(
+        pixels: &'static [u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Result<Self, TextureError> {
+        check_size(width, height)?;
+        let stride = width.next_power_of_two();
+        if pixels.len() < format.bytes_for(stride * height) {
+            return Err(TextureError::NotEnoughData);
+        }
+        if !(pixels.as_ptr() as usize).is_multiple_of(16) {
+            return Err(TextureError::Misaligned);
+        }
+
+        Ok(Self {
+            pixels: Pixels::Static(pixels),
+            palette: None,
+            width,
+            height,
+            stride,
+            format,
+            swizzled: false,
+        })
+    }
+
+    /// Width of the image, in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image, in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Distance between the start of two rows, in pixels.
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Returns the region covering the whole image.
+    pub fn region(&self) -> Region {
+        Region::new(0, 0, self.width, self.height)
+    }
+
+    /// Returns whether `region` lies within the image.
+    pub fn contains(&self, region: Region) -> bool {
+        region.x as u64 + region.width as u64 <= self.width as u64
+            && region.y as u64 + region.height as u64 <= self.height as u64
+    }
+
+    /// Format the pixels are stored in.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Returns the raw pixel data, including the row padding.
+    pub fn pixels(&self) -> &[u8] {
+        match &self.pixels {
+            Pixels::Static(pixels) => pixels,
+            Pixels::Owned(buffer) => buffer.as_slice(),
+            Pixels::Vram(block, len) => unsafe { slice::from_raw_parts(block.direct, *len) },
+        }
+    }
+
+    /// Returns the raw pixel data for editing, or `None` for textures created with `from_static`. Call
+    /// `Texture::flush` once done so the GE doesn't sample stale pixels.
+    pub fn pixels_mut(&mut self) -> Option<&mut [u8]> {
+        match &mut self.pixels {
+            Pixels::Static(_) => None,
+            Pixels::Owned(buffer) => Some(buffer.as_mut_slice()),
+            Pixels::Vram(block, len) => {
+                Some(unsafe { slice::from_raw_parts_mut(block.direct, *len) })
+            }
+        }
+    }
+
+    /// Reorders the pixels into the swizzled layout, which the GE samples faster.
+    pub fn swizzle(&mut self) -> Result<(), TextureError> {
+        if self.swizzled {
+            return Ok(());
+        }
+        let row_bytes = self.format.bytes_for(self.stride);
+        let rows = self.height.next_power_of_two() as usize;
+        if !swizzle::can_swizzle(row_bytes, rows) {
+            return Err(TextureError::NotSwizzlable);
+        }
+
+        let pixels = self.pixels_mut().ok_or(TextureError::ReadOnly)?;
+        let swizzled = swizzle::swizzle(pixels, row_bytes, rows);
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
+        self.swizzled = true;
+        self.flush();
+        Ok(())
+    }
+
+    /// Tells whether the pixels are already swizzled, such as a `from_static` blob swizzled beforehand.
+    pub fn set_swizzled(&mut self, swizzled: bool) {
+        self.swizzled = swizzled;
+    }
+
+    /// Returns whether the pixels are in the swizzled layout.
+    pub fn is_swizzled(&self) -> bool {
+        self.swizzled
+    }
+
+    /// Copies the pixels into VRAM, where the GE samples them faster than from main RAM. The VRAM is never
+    /// given back, so this is meant for textures that live as long as the game.
+    pub fn move_to_vram(&mut self) -> Result<(), TextureError> {
+        if self.in_vram() {
+            return Ok(());
+        }
+        let len = self.pixels().len();
+        let block = vram::alloc(len as u32).map_err(TextureError::Vram)?;
+
+        unsafe { slice::from_raw_parts_mut(block.direct, len) }.copy_from_slice(self.pixels());
+        self.pixels = Pixels::Vram(block, len);
+        self.flush();
+        Ok(())
+    }
+
+    /// Returns a texture sampling pixels the GE draws into, `stride` pixels per row, for `RenderTarget`.
+    pub(crate) fn from_vram(
+        block: VramBlock,
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: PixelFormat,
+    ) -> Self {
+        Self {
+            pixels: Pixels::Vram(block, block.len as usize),
+            palette: None,
+            width,
+            height,
+            stride,
+            format,
+            swizzled: false,
+        }
+    }
+
+    /// Returns whether the pixels are stored in VRAM.
+    pub fn in_vram(&self) -> bool {
+        matches!(self.pixels, Pixels::Vram(..))
+    }
+
+    /// Writes the pixels and palette still in the CPU's data cache back to memory, where the GE reads them.
+    pub fn flush(&self) {
+        unsafe {
+            sceKernelDcacheWritebackRange(
+                self.pixels().as_ptr() as *const c_void,
+                self.pixels().len() as u32,
+            );
+            if let Some(palette) = &self.palette {
+                sceKernelDcacheWritebackRange(
+                    palette.as_slice().as_ptr() as *const c_void,
+                    palette.as_slice().len() as u32,
+                );
+            }
+        }
+    }
+
+    /// Sets the RGBA8 colors the pixels of a `Clut` texture index into.
+    pub fn set_palette(&mut self, colors: &[[u8; 4]]) -> Result<(), TextureError> {
+        let size = self.format.palette_size();
+        if colors.len() > size {
+            return Err(TextureError::InvalidPalette);
+        }
+
+        // Always holds the whole palette, as it is uploaded in blocks of 8 colors.
+        let mut palette = AlignedBuffer::new(size * 4);
+        for (entry, color) in palette.as_mut_slice().chunks_exact_mut(4).zip(colors) {
+            entry.copy_from_slice(color);
+        }
+        self.palette = Some(palette);
+        self.flush();
+        Ok(())
+    }
+
+    /// Returns the RGBA8 palette of a `Clut` texture, if it was set.
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.palette.as_ref().map(|palette| palette.as_slice())
+    }
+
+    /// Returns the UV coordinates of the top-left and bottom-right corners of `region`, since the PSP samples
+    /// the whole power-of-two sized texture from `0.0` to `1.0`.
+    pub(crate) fn uv(&self, region: Region) -> [f32; 4] {
+        let rows = self.height.next_power_of_two() as f32;
+        [
+            region.x as f32 / self.stride as f32,
+            region.y as f32 / rows,
+            (region.x + region.width) as f32 / self.stride as f32,
+            (region.y + region.height) as f32 / rows,
+        ]
+    }
+
+    /// Sets this texture as the one used by the next draw calls.
+    pub(crate) fn bind(&self) {
+        unsafe {
+            if let Some(palette) = &self.palette {
+                sceGuClutMode(ClutPixelFormat::Psm8888, 0, 0xff, 0);
+                sceGuClutLoad(
+                    (palette.as_slice().len() / 32) as i32,
+                    palette.as_slice().as_ptr() as *const c_void,
+                );
+            }
+            sceGuTexMode(self.format.as_psm(), 0, 0, self.swizzled as i32);
+            sceGuTexImage(
+                MipmapLevel::None,
+                self.stride as i32,
+                self.height.next_power_of_two() as i32,
+                self.stride as i32,
+                self.pixels().as_ptr() as *const c_void,
+            );
+        }
+    }
+}
+
+fn check_size(width: u32, height: u32) -> Result<(), TextureError> {
+    if width == 0 || height == 0 || width > MAX_TEXTURE_SIZE || height > MAX_TEXTURE_SIZE {
+        Err(TextureError::InvalidSize)
+    } else {
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::graphics::Vertex;
+use crate::core::Vec2;
+use crate::graphics::{Color, Gradient, Vertex};
 
 pub(crate) fn sort_vertices<const N: usize>(vertices: [Vertex; N], clockwise: bool) -> [Vertex; N] {
     let mut vertices_sorted = vertices;
@@ -12,3 +13,95 @@ pub(crate) fn sort_vertices<const N: usize>(vertices: [Vertex; N], clockwise: bo
 
     vertices_sorted
 }
+
+/// Returns the gradient's color at each point, relative to the bounds of all points.
+pub(crate) fn gradient_colors<const N: usize>(
+    points: [Vec2<f32>; N],
+    gradient: &Gradient,
+) -> [Color; N] {
+    let mut min = Vec2::new(f32::MAX, f32::MAX);
+    let mut max = Vec2::new(f32::MIN, f32::MIN);
+    for point in points.iter() {
+        min.x = min.x.min(point.x);
+        min.y = min.y.min(point.y);
+        max.x = max.x.max(point.x);
+        max.y = max.y.max(point.y);
+    }
+
+    let size = Vec2::new(max.x - min.x, max.y - min.y);
+    points.map(|point| {
+        gradient.color_at(Vec2::new(
+            if size.x > 0.0 {
+                (point.x - min.x) / size.x
+            } else {
+                0.0
+            },
+            if size.y > 0.0 {
+                (point.y - min.y) / size.y
+            } else {
+                0.0
+            },
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(level: u8) -> Color {
+        Color::new(level, level, level, 255)
+    }
+
+    fn horizontal() -> Gradient {
+        Gradient::linear(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), gray(0), gray(255))
+    }
+
+    #[test]
+    fn relative_to_the_bounds() {
+        // Points anywhere, the leftmost one being the start of the gradient and the rightmost one its end.
+        let colors = gradient_colors(
+            [
+                Vec2::new(-10.0, 5.0),
+                Vec2::new(30.0, 5.0),
+                Vec2::new(10.0, -20.0),
+                Vec2::new(0.0, 40.0),
+            ],
+            &horizontal(),
+        );
+        assert_eq!(colors, [gray(0), gray(255), gray(128), gray(64)]);
+    }
+
+    #[test]
+    fn flat_bounds() {
+        // Without width, every point is on the left edge.
+        let colors = gradient_colors([Vec2::new(3.0, 0.0), Vec2::new(3.0, 10.0)], &horizontal());
+        assert_eq!(colors, [gray(0), gray(0)]);
+
+        let vertical =
+            Gradient::linear(Vec2::new(0.0, 0.0), Vec2::new(0.0, 1.0), gray(0), gray(255));
+        let colors = gradient_colors([Vec2::new(0.0, 3.0), Vec2::new(10.0, 3.0)], &vertical);
+        assert_eq!(colors, [gray(0), gray(0)]);
+    }
+
+    #[test]
+    fn corners_in_sprite_order() {
+        // Top-left, top-right, bottom-left and bottom-right, as `Sprite::set_vertex_colors` takes them.
+        let corners = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(32.0, 0.0),
+            Vec2::new(0.0, 16.0),
+            Vec2::new(32.0, 16.0),
+        ];
+        assert_eq!(
+            gradient_colors(corners, &horizontal()),
+            [gray(0), gray(255), gray(0), gray(255)]
+        );
+        let diagonal =
+            Gradient::linear(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), gray(0), gray(255));
+        assert_eq!(
+            gradient_colors(corners, &diagonal),
+            [gray(0), gray(128), gray(128), gray(255)]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use core::ptr::addr_of_mut;
+
+use psp::vram_alloc::{get_vram_allocator, SimpleVramAllocator};
+
+/// Errors that may happen when allocating VRAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VramError {
+    /// The allocator was taken with `psp::vram_alloc::get_vram_allocator` outside of SPSPF.
+    Unavailable,
+    /// There isn't enough free VRAM left for the allocation.
+    OutOfMemory,
+}
+
+/// A block of VRAM. It is never freed, as the allocator only hands out memory sequentially.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct VramBlock {
+    /// Address relative to the start of VRAM, as expected by the framebuffer functions.
+    pub(crate) from_zero: *mut u8,
+    /// Address the CPU and the texture functions can use directly.
+    pub(crate) direct: *mut u8,
+    pub(crate) len: u32,
+}
+
+// `get_vram_allocator` can only be called once, so the allocator is kept here and shared by everything in
+// SPSPF that needs VRAM.
+static mut ALLOCATOR: Option<SimpleVramAllocator> = None;
+static mut USED: u32 = 0;
+
+/// Allocates `size` bytes of VRAM, keeping the next allocation aligned to 16 bytes.
+pub(crate) fn alloc(size: u32) -> Result<VramBlock, VramError> {
+    let size = (size + 15) & !15;
+
+    unsafe {
+        let allocator = &mut *addr_of_mut!(ALLOCATOR);
+        if allocator.is_none() {
+            *allocator = Some(get_vram_allocator().map_err(|_| VramError::Unavailable)?);
+        }
+        let allocator = allocator.as_ref().unwrap();
+
+        if USED + size > allocator.total_mem() {
+            return Err(VramError::OutOfMemory);
+        }
+        USED += size;
+
+        let chunk = allocator.alloc(size);
+        Ok(VramBlock {
+            from_zero: chunk.as_mut_ptr_from_zero(),
+            direct: chunk.as_mut_ptr_direct_to_vram(),
+            len: size,
+        })
+    }
+}
@@ -10,9 +10,10 @@
 //! making sure to comply with the License of this project and its references.
 
 #![no_std]
-#![no_main]
-#![feature(bigint_helper_methods)]
+#![cfg_attr(not(test), no_main)]
 
+pub mod app;
 pub mod core;
 #[cfg(feature = "graphics")]
 pub mod graphics;
+pub mod system;
@@ -0,0 +1,132 @@
+use core::{
+    ffi::c_void,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use psp::sys::{
+    sceKernelCreateCallback, sceKernelCreateThread, sceKernelExitGame,
+    sceKernelRegisterExitCallback, sceKernelSleepThreadCB, sceKernelStartThread,
+    scePowerRegisterCallback, ThreadAttributes,
+};
+
+/// Something the system did, as polled with `poll`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemEvent {
+    /// The user chose to quit from the HOME menu. Only sent once the game handles exits, see
+    /// `set_handle_exit`.
+    Exit,
+    /// The PSP is about to go to sleep.
+    Suspend,
+    /// The PSP woke up.
+    Resume,
+    /// The battery just ran low.
+    BatteryLow,
+}
+
+impl SystemEvent {
+    /// Every event, in the order `poll` returns them when several are waiting.
+    const ALL: [SystemEvent; 4] = [
+        SystemEvent::Exit,
+        SystemEvent::Suspend,
+        SystemEvent::Resume,
+        SystemEvent::BatteryLow,
+    ];
+
+    fn bit(&self) -> u32 {
+        1 << *self as u32
+    }
+}
+
+/// Power callback flags, as given by `scePowerRegisterCallback`.
+const POWER_SUSPENDING: i32 = 0x0001_0000;
+const POWER_RESUME_COMPLETE: i32 = 0x0004_0000;
+const POWER_BATTERY_LOW: i32 = 0x0000_0100;
+
+// Set by the callback thread, read by the main loop.
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+static HANDLE_EXIT: AtomicBool = AtomicBool::new(false);
+static BATTERY_LOW: AtomicBool = AtomicBool::new(false);
+/// One bit for each `SystemEvent` waiting to be polled.
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+/// Starts the thread the callbacks run on, once. Called by `Canvas::new`, so games drawing with SPSPF
+/// don't need to.
+pub fn register() {
+    if REGISTERED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    unsafe {
+        let id = sceKernelCreateThread(
+            b"spspf_callbacks\0".as_ptr(),
+            callback_thread,
+            0x11,
+            0x1000,
+            ThreadAttributes::empty(),
+            ptr::null_mut(),
+        );
+        sceKernelStartThread(id, 0, ptr::null_mut());
+    }
+}
+
+/// Sets whether the game handles quitting from the HOME menu itself. By default it doesn't, and the game
+/// quits right away. When it does, `SystemEvent::Exit` is sent instead, giving the game a chance to save
+/// before it calls `Canvas::terminate`, which it must do soon after.
+pub fn set_handle_exit(handle_exit: bool) {
+    HANDLE_EXIT.store(handle_exit, Ordering::SeqCst);
+}
+
+/// Returns the next event waiting, if any. Must be called regularly, such as once per frame, for the events
+/// to be handled in time.
+pub fn poll() -> Option<SystemEvent> {
+    let pending = PENDING.load(Ordering::SeqCst);
+    let event = SystemEvent::ALL
+        .into_iter()
+        .find(|event| pending & event.bit() != 0)?;
+    PENDING.fetch_and(!event.bit(), Ordering::SeqCst);
+    Some(event)
+}
+
+/// Returns whether the battery is low, as of the last power callback.
+pub fn is_battery_low() -> bool {
+    BATTERY_LOW.load(Ordering::SeqCst)
+}
+
+fn send(event: SystemEvent) {
+    PENDING.fetch_or(event.bit(), Ordering::SeqCst);
+}
+
+unsafe extern "C" fn exit_callback(_arg1: i32, _arg2: i32, _arg: *mut c_void) -> i32 {
+    if HANDLE_EXIT.load(Ordering::SeqCst) {
+        send(SystemEvent::Exit);
+    } else {
+        sceKernelExitGame();
+    }
+    0
+}
+
+unsafe extern "C" fn power_callback(_count: i32, info: i32, _arg: *mut c_void) -> i32 {
+    if info & POWER_SUSPENDING != 0 {
+        send(SystemEvent::Suspend);
+    }
+    if info & POWER_RESUME_COMPLETE != 0 {
+        send(SystemEvent::Resume);
+    }
+    // The flag is set in every callback while the battery is low, but only sent once it becomes low.
+    let low = info & POWER_BATTERY_LOW != 0;
+    if low && !BATTERY_LOW.swap(low, Ordering::SeqCst) {
+        send(SystemEvent::BatteryLow);
+    }
+    BATTERY_LOW.store(low, Ordering::SeqCst);
+    0
+}
+
+/// Callbacks only run on a thread sleeping with `sceKernelSleepThreadCB`, so they get a thread of their own.
+unsafe extern "C" fn callback_thread(_args: usize, _argp: *mut c_void) -> i32 {
+    let id = sceKernelCreateCallback(b"spspf_exit\0".as_ptr(), exit_callback, ptr::null_mut());
+    sceKernelRegisterExitCallback(id);
+    let id = sceKernelCreateCallback(b"spspf_power\0".as_ptr(), power_callback, ptr::null_mut());
+    scePowerRegisterCallback(-1, id);
+    sceKernelSleepThreadCB();
+    0
+}
@@ -0,0 +1,312 @@
+extern crate alloc;
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::mem;
+
+use psp::sys::{
+    sceUtilityMsgDialogGetStatus, sceUtilityMsgDialogInitStart, sceUtilityMsgDialogShutdownStart,
+    sceUtilityMsgDialogUpdate, sceUtilityOskGetStatus, sceUtilityOskInitStart,
+    sceUtilityOskShutdownStart, sceUtilityOskUpdate, SceUtilityOskData, SceUtilityOskInputLanguage,
+    SceUtilityOskInputType, SceUtilityOskParams, SceUtilityOskResult, SceUtilityOskState,
+    UtilityMsgDialogMode, UtilityMsgDialogOption, UtilityMsgDialogParams, UtilityMsgDialogPressed,
+};
+
+use crate::graphics::canvas::Canvas;
+use crate::system::utility;
+
+/// Longest text the keyboard accepts by default, in characters.
+pub const DEFAULT_MAX_LENGTH: usize = 32;
+
+/// Result of a dialog the user backed out of.
+const RESULT_CANCELLED: i32 = 1;
+
+/// Errors that may happen when showing a dialog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DialogError {
+    /// The user backed out of the dialog.
+    Cancelled,
+    /// Error code returned by the utility, such as when another dialog is already open.
+    Utility(i32),
+}
+
+/// Button the user closed a `MessageDialog` with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    /// The message was acknowledged, for dialogs without `Yes` and `No`.
+    Ok,
+    Yes,
+    No,
+}
+
+/// The system's on-screen keyboard, to enter a name or any other text.
+///
+/// It doesn't block the game: once opened, `Keyboard::update` is called in every frame, between
+/// `Canvas::start_frame` and `Canvas::end_frame`, until it returns the text entered. The keyboard is drawn
+/// over whatever the game drew in the frame.
+pub struct Keyboard {
+    description: String,
+    text: String,
+    max_length: usize,
+    lines: u32,
+    session: Option<Box<KeyboardSession>>,
+}
+
+/// What the utility reads and writes while the keyboard is open, which must not move until it is closed.
+struct KeyboardSession {
+    params: SceUtilityOskParams,
+    data: SceUtilityOskData,
+    description: Vec<u16>,
+    text: Vec<u16>,
+    output: Vec<u16>,
+}
+
+impl Keyboard {
+    /// Returns a keyboard titled `description`, starting from `text`.
+    pub fn new(description: &str, text: &str) -> Self {
+        Self {
+            description: String::from(description),
+            text: String::from(text),
+            max_length: DEFAULT_MAX_LENGTH,
+            lines: 1,
+            session: None,
+        }
+    }
+
+    /// Sets the longest text the user may enter, in characters.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.max(1);
+        self
+    }
+
+    /// Sets the number of lines the text may take.
+    pub fn with_lines(mut self, lines: u32) -> Self {
+        self.lines = lines.max(1);
+        self
+    }
+
+    /// Returns whether the keyboard is open.
+    pub fn is_open(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Opens the keyboard. Only one utility dialog may be open at a time.
+    pub fn open(&mut self) -> Result<(), DialogError> {
+        if self.session.is_some() {
+            return Ok(());
+        }
+
+        let mut session = Box::new(KeyboardSession {
+            // Every field is either a number, a pointer or an enum with a variant for `0`.
+            params: unsafe { mem::zeroed() },
+            data: unsafe { mem::zeroed() },
+            description: utf16(&self.description),
+            text: utf16(&self.text),
+            output: alloc::vec![0; self.max_length + 1],
+        });
+
+        session.data.language = SceUtilityOskInputLanguage::Default;
+        session.data.inputtype = SceUtilityOskInputType::All;
+        session.data.lines = self.lines as i32;
+        session.data.unk_24 = 1;
+        session.data.desc = session.description.as_mut_ptr();
+        session.data.intext = session.text.as_mut_ptr();
+        session.data.outtext = session.output.as_mut_ptr();
+        session.data.outtextlength = session.output.len() as i32;
+        session.data.outtextlimit = self.max_length as i32;
+        session.data.result = SceUtilityOskResult::Unchanged;
+
+        session.params.base = utility::dialog_common::<SceUtilityOskParams>();
+        session.params.datacount = 1;
+        session.params.data = &mut session.data;
+        session.params.state = SceUtilityOskState::None;
+
+        let result = unsafe { sceUtilityOskInitStart(&mut session.params) };
+        if result < 0 {
+            return Err(DialogError::Utility(result));
+        }
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Updates the keyboard for the current frame, returning the text entered once it is closed. Returns
+    /// `None` while it is open, or if it wasn't opened.
+    pub fn update(&mut self, canvas: &mut Canvas) -> Option<Result<String, DialogError>> {
+        self.session.as_ref()?;
+        let status = unsafe { sceUtilityOskGetStatus() };
+        if !utility::step(canvas, status, update_keyboard, shutdown_keyboard) {
+            return None;
+        }
+
+        let session = self.session.take()?;
+        let result = session.params.base.result;
+        Some(if result < 0 {
+            Err(DialogError::Utility(result))
+        } else if result == RESULT_CANCELLED
+            || session.data.result == SceUtilityOskResult::Cancelled
+        {
+            Err(DialogError::Cancelled)
+        } else {
+            let len = session
+                .output
+                .iter()
+                .position(|&unit| unit == 0)
+                .unwrap_or(session.output.len());
+            let text: String = char::decode_utf16(session.output[..len].iter().copied())
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect();
+            // Starts from the text entered when opened again.
+            self.text = text.clone();
+            Ok(text)
+        })
+    }
+}
+
+impl Drop for Keyboard {
+    /// The utility may still write to an open keyboard's buffers, so they are leaked rather than freed.
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            unsafe { sceUtilityOskShutdownStart() };
+            Box::leak(session);
+        }
+    }
+}
+
+fn update_keyboard() {
+    unsafe { sceUtilityOskUpdate(1) };
+}
+
+fn shutdown_keyboard() {
+    unsafe { sceUtilityOskShutdownStart() };
+}
+
+/// Returns `text` as NUL-terminated UTF-16, as the keyboard takes it.
+fn utf16(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// A system message box, showing text or the system's own message for an error code, optionally asking the
+/// user `Yes` or `No`.
+///
+/// Like `Keyboard`, it doesn't block the game: once opened, `MessageDialog::update` is called in every frame,
+/// between `Canvas::start_frame` and `Canvas::end_frame`, until it returns the button pressed.
+pub struct MessageDialog {
+    mode: UtilityMsgDialogMode,
+    message: String,
+    error: u32,
+    options: UtilityMsgDialogOption,
+    params: Option<Box<UtilityMsgDialogParams>>,
+}
+
+impl MessageDialog {
+    /// Returns a dialog showing `message`, closed with `Answer::Ok`.
+    pub fn new(message: &str) -> Self {
+        Self {
+            mode: UtilityMsgDialogMode::Text,
+            message: String::from(message),
+            error: 0,
+            options: UtilityMsgDialogOption::TEXT,
+            params: None,
+        }
+    }
+
+    /// Returns a dialog asking `question`, answered with `Answer::Yes` or `Answer::No`. Backing out of it
+    /// gives `DialogError::Cancelled`.
+    pub fn confirm(question: &str) -> Self {
+        let mut dialog = Self::new(question);
+        dialog.options |= UtilityMsgDialogOption::YES_NO_BUTTONS;
+        dialog
+    }
+
+    /// Returns a dialog showing the system's message for the error `code`, such as one returned in
+    /// `DialogError::Utility`.
+    pub fn error(code: u32) -> Self {
+        Self {
+            mode: UtilityMsgDialogMode::Error,
+            message: String::new(),
+            error: code,
+            options: UtilityMsgDialogOption::ERROR,
+            params: None,
+        }
+    }
+
+    /// Selects `No` when the dialog opens, for questions whose `Yes` can't be undone.
+    pub fn with_default_no(mut self) -> Self {
+        self.options |= UtilityMsgDialogOption::DEFAULT_NO;
+        self
+    }
+
+    /// Returns whether the dialog is open.
+    pub fn is_open(&self) -> bool {
+        self.params.is_some()
+    }
+
+    /// Opens the dialog. Only one utility dialog may be open at a time.
+    pub fn open(&mut self) -> Result<(), DialogError> {
+        if self.params.is_some() {
+            return Ok(());
+        }
+
+        let mut params = Box::new(UtilityMsgDialogParams {
+            base: utility::dialog_common::<UtilityMsgDialogParams>(),
+            unknown: 0,
+            mode: self.mode,
+            error_value: self.error,
+            message: [0; 512],
+            options: self.options,
+            button_pressed: UtilityMsgDialogPressed::Unknown1,
+        });
+        utility::copy_str(&mut params.message, &self.message);
+
+        let result = unsafe { sceUtilityMsgDialogInitStart(&mut *params) };
+        if result < 0 {
+            return Err(DialogError::Utility(result));
+        }
+        self.params = Some(params);
+        Ok(())
+    }
+
+    /// Updates the dialog for the current frame, returning the button pressed once it is closed. Returns
+    /// `None` while it is open, or if it wasn't opened.
+    pub fn update(&mut self, canvas: &mut Canvas) -> Option<Result<Answer, DialogError>> {
+        self.params.as_ref()?;
+        let status = unsafe { sceUtilityMsgDialogGetStatus() };
+        if !utility::step(canvas, status, update_message, shutdown_message) {
+            return None;
+        }
+
+        let params = self.params.take()?;
+        let result = params.base.result;
+        if result < 0 {
+            return Some(Err(DialogError::Utility(result)));
+        }
+        Some(
+            match (
+                params.button_pressed,
+                self.options
+                    .contains(UtilityMsgDialogOption::YES_NO_BUTTONS),
+            ) {
+                (UtilityMsgDialogPressed::Yes, true) => Ok(Answer::Yes),
+                (UtilityMsgDialogPressed::No, true) => Ok(Answer::No),
+                (_, true) => Err(DialogError::Cancelled),
+                (_, false) => Ok(Answer::Ok),
+            },
+        )
+    }
+}
+
+impl Drop for MessageDialog {
+    /// The utility may still write to an open dialog's parameters, so they are leaked rather than freed.
+    fn drop(&mut self) {
+        if let Some(params) = self.params.take() {
+            unsafe { sceUtilityMsgDialogShutdownStart() };
+            Box::leak(params);
+        }
+    }
+}
+
+fn update_message() {
+    unsafe { sceUtilityMsgDialogUpdate(1) };
+}
+
+fn shutdown_message() {
+    unsafe { sceUtilityMsgDialogShutdownStart() };
+}
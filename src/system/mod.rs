@@ -0,0 +1,24 @@
+//! This is the `system` module, which lets games react to what the PSP's system does around them, such as
+//! quitting from the HOME menu or going to sleep, and use its saves and dialogs.
+
+/// The `callbacks` module registers the exit and power callbacks, delivering them as events to poll.
+pub mod callbacks;
+pub use callbacks::SystemEvent;
+#[cfg(feature = "graphics")]
+/// The `dialog` module shows the system's on-screen keyboard and message boxes over the game.
+pub mod dialog;
+#[cfg(feature = "graphics")]
+pub use dialog::{Answer, DialogError, Keyboard, MessageDialog};
+/// The `save` module serializes games and stores them, in the XMB or in plain files.
+pub mod save;
+pub use save::{
+    ByteReader, ByteWriter, FileBackend, FileSystem, PspFiles, SaveBackend, SaveData, SaveError,
+    SaveInfo,
+};
+#[cfg(feature = "graphics")]
+/// The `savedata` module stores saves with the PSP's savedata utility.
+pub mod savedata;
+#[cfg(feature = "graphics")]
+pub use savedata::SavedataBackend;
+#[cfg(feature = "graphics")]
+mod utility;
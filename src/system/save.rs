@@ -0,0 +1,613 @@
+//! Saving and loading games. Saves are serialized to bytes with `SaveData`, then stored by a `SaveBackend`:
+//! `SavedataBackend` shows them in the XMB through the PSP's savedata utility, while `FileBackend` keeps
+//! them in plain files, through any `FileSystem`. Only `PspFiles` and `SavedataBackend` touch the hardware,
+//! so save logic can be tested on the host with a `FileBackend` over the host's files.
+
+extern crate alloc;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::core::io::{File, IoError};
+
+/// Errors that may happen when saving or loading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveError {
+    /// There is no save in the slot.
+    NotFound,
+    /// The save is damaged, or isn't the data expected.
+    Corrupt,
+    /// The user backed out of the dialog.
+    Cancelled,
+    /// The save is larger than the backend allows.
+    TooLarge,
+    /// Error code returned by the PSP's IO functions.
+    Io(IoError),
+    /// Error code returned by the savedata utility.
+    Utility(i32),
+}
+
+/// What the XMB shows about a save.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SaveInfo<'a> {
+    /// Name of the game, such as `SPSPF Demo`.
+    pub title: &'a str,
+    /// Name of the save, such as `Chapter 2`.
+    pub save_title: &'a str,
+    /// Description of the save, such as the play time.
+    pub detail: &'a str,
+    /// `144`x`80` PNG icon of the save.
+    pub icon0: Option<&'a [u8]>,
+    /// `480`x`272` PNG background shown while the save is selected.
+    pub pic1: Option<&'a [u8]>,
+}
+
+/// A value that can be saved, such as the progress of the player.
+pub trait SaveData: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    fn from_bytes(data: &[u8]) -> Result<Self, SaveError>;
+}
+
+impl SaveData for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, SaveError> {
+        Ok(data.to_vec())
+    }
+}
+
+/// Writes values as little-endian bytes, to implement `SaveData::to_bytes`.
+#[derive(Clone, Debug, Default)]
+pub struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes `bytes` after their length.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads values written by a `ByteWriter`, in the same order, to implement `SaveData::from_bytes`. Reading
+/// past the end gives `SaveError::Corrupt`.
+#[derive(Clone, Debug)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Returns whether every byte was read.
+    pub fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_raw(N)?);
+        Ok(array)
+    }
+
+    fn read_raw(&mut self, len: usize) -> Result<&'a [u8], SaveError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(SaveError::Corrupt)?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveError::Corrupt),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, SaveError> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, SaveError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], SaveError> {
+        let len = self.read_u32()? as usize;
+        self.read_raw(len)
+    }
+
+    pub fn read_str(&mut self) -> Result<&'a str, SaveError> {
+        core::str::from_utf8(self.read_bytes()?).map_err(|_| SaveError::Corrupt)
+    }
+}
+
+/// Where saves are stored, each one in a named slot, such as `SLOT0`.
+pub trait SaveBackend {
+    /// Stores `data` in `slot`, replacing what it held.
+    fn save(&mut self, slot: &str, info: &SaveInfo, data: &[u8]) -> Result<(), SaveError>;
+
+    fn load(&mut self, slot: &str) -> Result<Vec<u8>, SaveError>;
+
+    fn delete(&mut self, slot: &str) -> Result<(), SaveError>;
+
+    /// Lets the user choose which of `slots` to save to, returning the chosen one.
+    fn save_to_list(
+        &mut self,
+        slots: &[&str],
+        info: &SaveInfo,
+        data: &[u8],
+    ) -> Result<String, SaveError>;
+
+    /// Lets the user choose which of `slots` to load, returning the chosen one and its data.
+    fn load_from_list(&mut self, slots: &[&str]) -> Result<(String, Vec<u8>), SaveError>;
+
+    /// Lets the user choose which of `slots` to delete, returning the chosen one.
+    fn delete_from_list(&mut self, slots: &[&str]) -> Result<String, SaveError>;
+
+    /// Serializes `value` and stores it in `slot`.
+    fn save_data<T: SaveData>(
+        &mut self,
+        slot: &str,
+        info: &SaveInfo,
+        value: &T,
+    ) -> Result<(), SaveError>
+    where
+        Self: Sized,
+    {
+        self.save(slot, info, &value.to_bytes())
+    }
+
+    /// Loads the value stored in `slot`.
+    fn load_data<T: SaveData>(&mut self, slot: &str) -> Result<T, SaveError>
+    where
+        Self: Sized,
+    {
+        T::from_bytes(&self.load(slot)?)
+    }
+}
+
+/// Files a `FileBackend` keeps saves in.
+pub trait FileSystem {
+    /// Reads a whole file, `SaveError::NotFound` if it doesn't exist.
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, SaveError>;
+
+    /// Writes a file, creating its directory if needed.
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<(), SaveError>;
+
+    /// Deletes a file, `SaveError::NotFound` if it doesn't exist.
+    fn remove(&mut self, path: &str) -> Result<(), SaveError>;
+}
+
+/// Error code of the PSP's IO functions for a missing file.
+const IO_NOT_FOUND: i32 = 0x8001_0002_u32 as i32;
+
+fn io_error(error: IoError) -> SaveError {
+    match error.0 {
+        IO_NOT_FOUND => SaveError::NotFound,
+        _ => SaveError::Io(error),
+    }
+}
+
+/// The memory stick, through the PSP's IO functions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PspFiles;
+
+impl FileSystem for PspFiles {
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, SaveError> {
+        File::read_to_end(path).map_err(io_error)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<(), SaveError> {
+        if let Some((directory, _)) = path.rsplit_once('/') {
+            // Fails once the directory exists, which is fine.
+            let _ = File::create_dir(directory);
+        }
+        File::write(path, data).map_err(io_error)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), SaveError> {
+        File::remove(path).map_err(io_error)
+    }
+}
+
+/// Identifies the files written by `FileBackend`.
+const MAGIC: &[u8; 4] = b"SPSV";
+const VERSION: u8 = 1;
+
+/// Keeps each save in a plain file of `directory`, named after its slot, along with its title and detail.
+/// Without dialogs, the list variants pick a slot themselves: the first holding a save to load or delete, and
+/// the first free one to save, or else the first one.
+pub struct FileBackend<F: FileSystem> {
+    files: F,
+    directory: String,
+}
+
+impl<F: FileSystem> FileBackend<F> {
+    /// Returns a backend keeping saves in `directory`, such as `ms0:/PSP/SAVEDATA/SPSPF`.
+    pub fn new(files: F, directory: &str) -> Self {
+        Self {
+            files,
+            directory: String::from(directory.trim_end_matches('/')),
+        }
+    }
+
+    /// Returns the title, save title and detail stored along with the save in `slot`.
+    pub fn info(&mut self, slot: &str) -> Result<(String, String, String), SaveError> {
+        let file = self.files.read(&self.path(slot))?;
+        let mut reader = Self::header(&file)?;
+        Ok((
+            reader.read_str()?.to_string(),
+            reader.read_str()?.to_string(),
+            reader.read_str()?.to_string(),
+        ))
+    }
+
+    fn path(&self, slot: &str) -> String {
+        format!("{}/{}.sav", self.directory, slot)
+    }
+
+    /// Checks the header of a save file, returning a reader past it.
+    fn header(file: &[u8]) -> Result<ByteReader<'_>, SaveError> {
+        let mut reader = ByteReader::new(file);
+        let magic: [u8; 4] = reader.take()?;
+        if &magic != MAGIC || reader.read_u8()? != VERSION {
+            return Err(SaveError::Corrupt);
+        }
+        Ok(reader)
+    }
+
+    fn first_saved(&mut self, slots: &[&str]) -> Result<String, SaveError> {
+        slots
+            .iter()
+            .find(|slot| self.files.read(&self.path(slot)).is_ok())
+            .map(|slot| slot.to_string())
+            .ok_or(SaveError::NotFound)
+    }
+}
+
+impl<F: FileSystem> SaveBackend for FileBackend<F> {
+    fn save(&mut self, slot: &str, info: &SaveInfo, data: &[u8]) -> Result<(), SaveError> {
+        let mut writer = ByteWriter::new();
+        MAGIC.iter().for_each(|&byte| writer.write_u8(byte));
+        writer.write_u8(VERSION);
+        writer.write_str(info.title);
+        writer.write_str(info.save_title);
+        writer.write_str(info.detail);
+        writer.write_bytes(data);
+        self.files.write(&self.path(slot), &writer.into_bytes())
+    }
+
+    fn load(&mut self, slot: &str) -> Result<Vec<u8>, SaveError> {
+        let file = self.files.read(&self.path(slot))?;
+        let mut reader = Self::header(&file)?;
+        for _ in 0..3 {
+            reader.read_str()?;
+        }
+        let data = reader.read_bytes()?;
+        if !reader.is_empty() {
+            return Err(SaveError::Corrupt);
+        }
+        Ok(data.to_vec())
+    }
+
+    fn delete(&mut self, slot: &str) -> Result<(), SaveError> {
+        self.files.remove(&self.path(slot))
+    }
+
+    fn save_to_list(
+        &mut self,
+        slots: &[&str],
+        info: &SaveInfo,
+        data: &[u8],
+    ) -> Result<String, SaveError> {
+        let slot = slots
+            .iter()
+            .find(|slot| self.files.read(&self.path(slot)).is_err())
+            .or(slots.first())
+            .ok_or(SaveError::NotFound)?
+            .to_string();
+        self.save(&slot, info, data)?;
+        Ok(slot)
+    }
+
+    fn load_from_list(&mut self, slots: &[&str]) -> Result<(String, Vec<u8>), SaveError> {
+        let slot = self.first_saved(slots)?;
+        let data = self.load(&slot)?;
+        Ok((slot, data))
+    }
+
+    fn delete_from_list(&mut self, slots: &[&str]) -> Result<String, SaveError> {
+        let slot = self.first_saved(slots)?;
+        self.delete(&slot)?;
+        Ok(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    /// Files kept in memory, standing in for the memory stick.
+    #[derive(Default)]
+    struct MemoryFiles {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemoryFiles {
+        fn read(&mut self, path: &str) -> Result<Vec<u8>, SaveError> {
+            self.files.get(path).cloned().ok_or(SaveError::NotFound)
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<(), SaveError> {
+            self.files.insert(String::from(path), data.to_vec());
+            Ok(())
+        }
+
+        fn remove(&mut self, path: &str) -> Result<(), SaveError> {
+            self.files
+                .remove(path)
+                .map(|_| ())
+                .ok_or(SaveError::NotFound)
+        }
+    }
+
+    const PATH: &str = "saves/SLOT0.sav";
+
+    const INFO: SaveInfo = SaveInfo {
+        title: "Demo",
+        save_title: "Chapter 2",
+        detail: "1:23",
+        icon0: None,
+        pic1: None,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Progress {
+        level: u16,
+        score: u64,
+        health: f32,
+        name: String,
+        hard: bool,
+    }
+
+    impl SaveData for Progress {
+        fn to_bytes(&self) -> Vec<u8> {
+            let mut writer = ByteWriter::new();
+            writer.write_u16(self.level);
+            writer.write_u64(self.score);
+            writer.write_f32(self.health);
+            writer.write_str(&self.name);
+            writer.write_bool(self.hard);
+            writer.into_bytes()
+        }
+
+        fn from_bytes(data: &[u8]) -> Result<Self, SaveError> {
+            let mut reader = ByteReader::new(data);
+            let progress = Self {
+                level: reader.read_u16()?,
+                score: reader.read_u64()?,
+                health: reader.read_f32()?,
+                name: reader.read_str()?.to_string(),
+                hard: reader.read_bool()?,
+            };
+            if !reader.is_empty() {
+                return Err(SaveError::Corrupt);
+            }
+            Ok(progress)
+        }
+    }
+
+    fn backend() -> FileBackend<MemoryFiles> {
+        FileBackend::new(MemoryFiles::default(), "saves/")
+    }
+
+    /// Returns a backend holding a save of `data` in `SLOT0`, and the file it was written to.
+    fn saved(data: &[u8]) -> (FileBackend<MemoryFiles>, Vec<u8>) {
+        let mut backend = backend();
+        backend.save("SLOT0", &INFO, data).unwrap();
+        let file = backend.files.files[PATH].clone();
+        (backend, file)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut backend = backend();
+        let progress = Progress {
+            level: 7,
+            score: 1 << 40,
+            health: 0.75,
+            name: String::from("Ness"),
+            hard: true,
+        };
+        backend.save_data("SLOT0", &INFO, &progress).unwrap();
+        assert_eq!(backend.load_data::<Progress>("SLOT0").unwrap(), progress);
+        assert_eq!(
+            backend.info("SLOT0").unwrap(),
+            (
+                String::from("Demo"),
+                String::from("Chapter 2"),
+                String::from("1:23")
+            )
+        );
+
+        // Saving again replaces the save.
+        backend.save("SLOT0", &INFO, &[]).unwrap();
+        assert_eq!(backend.load("SLOT0").unwrap(), []);
+        assert_eq!(
+            backend.load_data::<Progress>("SLOT0").unwrap_err(),
+            SaveError::Corrupt
+        );
+    }
+
+    #[test]
+    fn missing_saves() {
+        let mut backend = backend();
+        assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::NotFound);
+        assert_eq!(backend.delete("SLOT0").unwrap_err(), SaveError::NotFound);
+
+        backend.save("SLOT0", &INFO, &[1, 2, 3]).unwrap();
+        backend.delete("SLOT0").unwrap();
+        assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::NotFound);
+    }
+
+    #[test]
+    fn bad_magic() {
+        let (mut backend, mut file) = saved(&[1, 2, 3]);
+        file[..4].copy_from_slice(b"SPSX");
+        backend.files.files.insert(String::from(PATH), file);
+        assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::Corrupt);
+        assert_eq!(backend.info("SLOT0").unwrap_err(), SaveError::Corrupt);
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let (mut backend, mut file) = saved(&[1, 2, 3]);
+        file[4] = VERSION + 1;
+        backend.files.files.insert(String::from(PATH), file);
+        assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::Corrupt);
+    }
+
+    #[test]
+    fn truncated_and_trailing_bytes() {
+        let (mut backend, file) = saved(&[1, 2, 3]);
+        for len in 0..file.len() {
+            backend
+                .files
+                .files
+                .insert(String::from(PATH), file[..len].to_vec());
+            assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::Corrupt);
+        }
+
+        let mut longer = file.clone();
+        longer.push(0);
+        backend.files.files.insert(String::from(PATH), longer);
+        assert_eq!(backend.load("SLOT0").unwrap_err(), SaveError::Corrupt);
+    }
+
+    #[test]
+    fn list_variants() {
+        let mut backend = backend();
+        let slots = ["SLOT0", "SLOT1", "SLOT2"];
+        assert_eq!(
+            backend.load_from_list(&slots).unwrap_err(),
+            SaveError::NotFound
+        );
+
+        assert_eq!(backend.save_to_list(&slots, &INFO, &[1]).unwrap(), "SLOT0");
+        assert_eq!(backend.save_to_list(&slots, &INFO, &[2]).unwrap(), "SLOT1");
+        assert_eq!(
+            backend.load_from_list(&slots).unwrap(),
+            (String::from("SLOT0"), alloc::vec![1])
+        );
+        assert_eq!(backend.delete_from_list(&slots).unwrap(), "SLOT0");
+        assert_eq!(
+            backend.load_from_list(&slots).unwrap(),
+            (String::from("SLOT1"), alloc::vec![2])
+        );
+
+        // With every slot taken, the first one is overwritten.
+        backend.save_to_list(&slots, &INFO, &[3]).unwrap();
+        backend.save_to_list(&slots, &INFO, &[4]).unwrap();
+        assert_eq!(backend.save_to_list(&slots, &INFO, &[5]).unwrap(), "SLOT0");
+        assert_eq!(backend.load("SLOT0").unwrap(), [5]);
+    }
+
+    #[test]
+    fn reader_errors() {
+        let mut reader = ByteReader::new(&[2, 0xff, 0xff]);
+        assert_eq!(reader.read_bool().unwrap_err(), SaveError::Corrupt);
+        assert_eq!(reader.read_u32().unwrap_err(), SaveError::Corrupt);
+        assert_eq!(reader.read_u16().unwrap(), 0xffff);
+        assert!(reader.is_empty());
+
+        // Lengths past the end, and text that isn't UTF-8.
+        let mut writer = ByteWriter::new();
+        writer.write_u32(u32::MAX);
+        assert_eq!(
+            ByteReader::new(&writer.into_bytes())
+                .read_bytes()
+                .unwrap_err(),
+            SaveError::Corrupt
+        );
+        let mut writer = ByteWriter::new();
+        writer.write_bytes(&[0xc3, 0x28]);
+        assert_eq!(
+            ByteReader::new(&writer.into_bytes())
+                .read_str()
+                .unwrap_err(),
+            SaveError::Corrupt
+        );
+    }
+}
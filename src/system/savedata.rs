@@ -0,0 +1,226 @@
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+use core::{ffi::c_void, mem, ptr};
+
+use psp::sys::{
+    sceUtilitySavedataGetStatus, sceUtilitySavedataInitStart, sceUtilitySavedataShutdownStart,
+    sceUtilitySavedataUpdate, SceUtilitySavedataParam, UtilitySavedataFileData,
+    UtilitySavedataFocus, UtilitySavedataMode,
+};
+
+use crate::graphics::{canvas::Canvas, colors::Color};
+use crate::system::{
+    save::{SaveBackend, SaveError, SaveInfo},
+    utility,
+};
+
+/// Size of the buffer saves are loaded into by default, in bytes.
+pub const DEFAULT_MAX_SIZE: usize = 0x10000;
+
+/// Result of a dialog the user backed out of.
+const RESULT_CANCELLED: i32 = 1;
+/// Results of the savedata utility that `SaveError` names.
+const ERROR_DATA_BROKEN: i32 = 0x8011_0306_u32 as i32;
+const ERROR_NO_DATA: i32 = 0x8011_0307_u32 as i32;
+
+/// Stores saves with the PSP's savedata utility, showing them in the XMB with their icon and title. The
+/// utility draws its dialogs over the screen, so each call runs frames on `canvas`, clearing it to the
+/// background color, until the utility is done. Saves of a game are kept in `ms0:/PSP/SAVEDATA`, in a
+/// directory named after the game code followed by the slot.
+pub struct SavedataBackend<'a> {
+    canvas: &'a mut Canvas,
+    game_name: String,
+    file_name: String,
+    max_size: usize,
+    background: Color,
+}
+
+impl<'a> SavedataBackend<'a> {
+    /// Returns a backend for the game with the code `game_name`, such as `ULUS99999`, whose saves are
+    /// written to `DATA.BIN`.
+    pub fn new(canvas: &'a mut Canvas, game_name: &str) -> Self {
+        Self {
+            canvas,
+            game_name: String::from(game_name),
+            file_name: String::from("DATA.BIN"),
+            max_size: DEFAULT_MAX_SIZE,
+            background: Color::new(0, 0, 0, 255),
+        }
+    }
+
+    /// Sets the name of the file saves are written to, up to `12` characters.
+    pub fn set_file_name(&mut self, file_name: &str) {
+        self.file_name = String::from(file_name);
+    }
+
+    /// Sets the size of the largest save that can be loaded, in bytes.
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+
+    /// Sets the color the screen is cleared to behind the dialogs.
+    pub fn set_background(&mut self, color: Color) {
+        self.background = color;
+    }
+
+    fn params(&self, mode: UtilitySavedataMode, slot: &str) -> SceUtilitySavedataParam {
+        // Every field is either a number, a pointer or an enum with a variant for `0`.
+        let mut params: SceUtilitySavedataParam = unsafe { mem::zeroed() };
+        params.base = utility::dialog_common::<SceUtilitySavedataParam>();
+        params.mode = mode;
+        params.overwrite = 1;
+        params.focus = UtilitySavedataFocus::Latest;
+        utility::copy_str(&mut params.game_name, &self.game_name);
+        utility::copy_str(&mut params.save_name, slot);
+        utility::copy_str(&mut params.file_name, &self.file_name);
+        params
+    }
+
+    /// Runs the utility with `params` until it is done, returning its result.
+    fn run(&mut self, params: &mut SceUtilitySavedataParam) -> Result<(), SaveError> {
+        unsafe {
+            let result = sceUtilitySavedataInitStart(params);
+            if result < 0 {
+                return Err(SaveError::Utility(result));
+            }
+
+            loop {
+                self.canvas.start_frame();
+                self.canvas.clear(self.background);
+                let status = sceUtilitySavedataGetStatus();
+                let done = utility::step(self.canvas, status, update, shutdown);
+                self.canvas.end_frame();
+                if done {
+                    break;
+                }
+            }
+        }
+
+        match params.base.result {
+            0 => Ok(()),
+            RESULT_CANCELLED => Err(SaveError::Cancelled),
+            ERROR_NO_DATA => Err(SaveError::NotFound),
+            ERROR_DATA_BROKEN => Err(SaveError::Corrupt),
+            result => Err(SaveError::Utility(result)),
+        }
+    }
+
+    fn save_with(
+        &mut self,
+        mut params: SceUtilitySavedataParam,
+        info: &SaveInfo,
+        data: &[u8],
+    ) -> Result<String, SaveError> {
+        utility::copy_str(&mut params.sfo_param.title, info.title);
+        utility::copy_str(&mut params.sfo_param.savedata_title, info.save_title);
+        utility::copy_str(&mut params.sfo_param.detail, info.detail);
+        params.icon0_file_data = file_data(info.icon0);
+        params.pic1_file_data = file_data(info.pic1);
+
+        // The utility only reads the data, but takes it mutable.
+        let mut data = data.to_vec();
+        params.data_buf = data.as_mut_ptr() as *mut c_void;
+        params.data_buf_size = data.len();
+        params.data_size = data.len();
+
+        self.run(&mut params)?;
+        Ok(String::from(utility::read_str(&params.save_name)))
+    }
+
+    fn load_with(
+        &mut self,
+        mut params: SceUtilitySavedataParam,
+    ) -> Result<(String, Vec<u8>), SaveError> {
+        let mut data = alloc::vec![0; self.max_size];
+        params.data_buf = data.as_mut_ptr() as *mut c_void;
+        params.data_buf_size = data.len();
+
+        self.run(&mut params)?;
+        if params.data_size > data.len() {
+            return Err(SaveError::TooLarge);
+        }
+        data.truncate(params.data_size);
+        Ok((String::from(utility::read_str(&params.save_name)), data))
+    }
+}
+
+fn update() {
+    unsafe { sceUtilitySavedataUpdate(1) };
+}
+
+fn shutdown() {
+    unsafe { sceUtilitySavedataShutdownStart() };
+}
+
+/// Returns the NUL-terminated names of `slots`, followed by an empty one ending the list.
+fn name_list(slots: &[&str]) -> Vec<[u8; 20]> {
+    let mut names = alloc::vec![[0; 20]; slots.len() + 1];
+    for (name, slot) in names.iter_mut().zip(slots) {
+        utility::copy_str(name, slot);
+    }
+    names
+}
+
+fn file_data(file: Option<&[u8]>) -> UtilitySavedataFileData {
+    match file {
+        Some(file) => UtilitySavedataFileData {
+            buf: file.as_ptr() as *mut c_void,
+            buf_size: file.len(),
+            size: file.len(),
+            unknown: 0,
+        },
+        None => UtilitySavedataFileData {
+            buf: ptr::null_mut(),
+            buf_size: 0,
+            size: 0,
+            unknown: 0,
+        },
+    }
+}
+
+impl<'a> SaveBackend for SavedataBackend<'a> {
+    /// Saves without asking, showing only that the game is saving.
+    fn save(&mut self, slot: &str, info: &SaveInfo, data: &[u8]) -> Result<(), SaveError> {
+        let params = self.params(UtilitySavedataMode::AutoSave, slot);
+        self.save_with(params, info, data).map(|_| ())
+    }
+
+    /// Loads without asking.
+    fn load(&mut self, slot: &str) -> Result<Vec<u8>, SaveError> {
+        let params = self.params(UtilitySavedataMode::AutoLoad, slot);
+        self.load_with(params).map(|(_, data)| data)
+    }
+
+    /// Asks the user to confirm before deleting.
+    fn delete(&mut self, slot: &str) -> Result<(), SaveError> {
+        let mut params = self.params(UtilitySavedataMode::Delete, slot);
+        self.run(&mut params)
+    }
+
+    fn save_to_list(
+        &mut self,
+        slots: &[&str],
+        info: &SaveInfo,
+        data: &[u8],
+    ) -> Result<String, SaveError> {
+        let mut names = name_list(slots);
+        let mut params = self.params(UtilitySavedataMode::ListSave, "");
+        params.save_name_list = names.as_mut_ptr();
+        self.save_with(params, info, data)
+    }
+
+    fn load_from_list(&mut self, slots: &[&str]) -> Result<(String, Vec<u8>), SaveError> {
+        let mut names = name_list(slots);
+        let mut params = self.params(UtilitySavedataMode::ListLoad, "");
+        params.save_name_list = names.as_mut_ptr();
+        self.load_with(params)
+    }
+
+    fn delete_from_list(&mut self, slots: &[&str]) -> Result<String, SaveError> {
+        let mut names = name_list(slots);
+        let mut params = self.params(UtilitySavedataMode::ListDelete, "");
+        params.save_name_list = names.as_mut_ptr();
+        self.run(&mut params)?;
+        Ok(String::from(utility::read_str(&params.save_name)))
+    }
+}
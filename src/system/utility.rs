@@ -0,0 +1,81 @@
+use core::mem::size_of;
+
+use psp::sys::{
+    sceUtilityGetSystemParamInt, SystemParamId, SystemParamLanguage, UtilityDialogButtonAccept,
+    UtilityDialogCommon,
+};
+
+use crate::graphics::canvas::Canvas;
+
+/// Status of a utility dialog, as returned by its `GetStatus` function. A dialog is done once it is back to
+/// `STATUS_NONE` after being shut down.
+const STATUS_NONE: i32 = 0;
+const STATUS_VISIBLE: i32 = 2;
+const STATUS_QUIT: i32 = 3;
+
+/// Returns the common parameters of a utility dialog whose parameters take `T`, in the language and with the
+/// confirm button the user chose in the system settings.
+pub(crate) fn dialog_common<T>() -> UtilityDialogCommon {
+    let mut language = 1;
+    let mut button = 1;
+    unsafe {
+        sceUtilityGetSystemParamInt(SystemParamId::Language, &mut language);
+        sceUtilityGetSystemParamInt(SystemParamId::Unknown, &mut button);
+    }
+
+    UtilityDialogCommon {
+        size: size_of::<T>() as u32,
+        language: match language {
+            0 => SystemParamLanguage::Japanese,
+            2 => SystemParamLanguage::French,
+            3 => SystemParamLanguage::Spanish,
+            4 => SystemParamLanguage::German,
+            5 => SystemParamLanguage::Italian,
+            6 => SystemParamLanguage::Dutch,
+            8 => SystemParamLanguage::Russian,
+            9 => SystemParamLanguage::Korean,
+            _ => SystemParamLanguage::English,
+        },
+        button_accept: match button {
+            0 => UtilityDialogButtonAccept::Circle,
+            _ => UtilityDialogButtonAccept::Cross,
+        },
+        // Priorities of the dialog's threads, as the PSP's own samples set them.
+        graphics_thread: 0x11,
+        access_thread: 0x13,
+        font_thread: 0x12,
+        sound_thread: 0x10,
+        result: 0,
+        reserved: [0; 4],
+    }
+}
+
+/// Copies `text` into a NUL-terminated field, cutting it to fit on a character boundary.
+pub(crate) fn copy_str(field: &mut [u8], text: &str) {
+    let mut len = text.len().min(field.len().saturating_sub(1));
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+    field[len..].fill(0);
+}
+
+/// Returns the text of a NUL-terminated field.
+pub(crate) fn read_str(field: &[u8]) -> &str {
+    let len = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    core::str::from_utf8(&field[..len]).unwrap_or("")
+}
+
+/// Steps an open dialog for the current frame, given its `status`, returning whether it is done. `update` is
+/// given to the canvas while the dialog is visible, and `shutdown` called once the user closed it.
+pub(crate) fn step(canvas: &mut Canvas, status: i32, update: fn(), shutdown: fn()) -> bool {
+    match status {
+        STATUS_VISIBLE => canvas.set_dialog_update(update),
+        STATUS_QUIT => shutdown(),
+        _ => {}
+    }
+    status == STATUS_NONE
+}
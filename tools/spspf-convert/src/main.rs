@@ -0,0 +1,310 @@
+//! Converts PNG, BMP and TGA images into texture blobs, loaded on the PSP with `Texture::from_blob` or
+//! `Texture::load_blob`. The decoding, conversion and swizzling are the very same code the framework runs.
+
+use std::{env, fs, process};
+
+#[allow(dead_code)]
+#[path = "../../../src/graphics/texture/blob.rs"]
+mod blob;
+#[allow(dead_code)]
+#[path = "../../../src/graphics/texture/convert.rs"]
+mod convert;
+#[allow(dead_code)]
+#[path = "../../../src/graphics/image/mod.rs"]
+mod image;
+#[allow(dead_code)]
+#[path = "../../../src/graphics/texture/swizzle.rs"]
+mod swizzle;
+
+use blob::BlobHeader;
+use convert::{Dither, PixelFormat};
+
+/// Largest width and height the PSP can sample from.
+const MAX_TEXTURE_SIZE: u32 = 512;
+
+const USAGE: &str = "Usage: spspf-convert [OPTIONS] <INPUT> <OUTPUT>
+
+Converts a PNG, BMP or TGA image into a texture blob for `Texture::from_blob`.
+
+Options:
+  -f, --format <FORMAT>  8888 (default), 5650, 5551, 4444, clut4 or clut8
+  -d, --dither           Dither when reducing the colors
+  -s, --swizzle          Store the pixels swizzled, which the PSP samples faster
+  -h, --help             Print this message";
+
+#[derive(Debug)]
+struct Options {
+    input: String,
+    output: String,
+    format: PixelFormat,
+    dither: Dither,
+    swizzle: bool,
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(message) = run(&options) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut paths = Vec::new();
+    let mut format = PixelFormat::Psm8888;
+    let mut dither = Dither::None;
+    let mut swizzle = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--format" => {
+                let name = args.next().ok_or("missing value for --format")?;
+                format = parse_format(&name).ok_or(format!("unknown format `{}`", name))?;
+            }
+            "-d" | "--dither" => dither = Dither::Ordered,
+            "-s" | "--swizzle" => swizzle = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        return Err("expected an input and an output path".into());
+    }
+    let output = paths.pop().unwrap();
+    let input = paths.pop().unwrap();
+    Ok(Some(Options {
+        input,
+        output,
+        format,
+        dither,
+        swizzle,
+    }))
+}
+
+fn parse_format(name: &str) -> Option<PixelFormat> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "8888" | "rgba8888" => PixelFormat::Psm8888,
+        "5650" | "rgb565" => PixelFormat::Psm5650,
+        "5551" | "rgba5551" => PixelFormat::Psm5551,
+        "4444" | "rgba4444" => PixelFormat::Psm4444,
+        "clut4" | "t4" => PixelFormat::Clut4,
+        "clut8" | "t8" => PixelFormat::Clut8,
+        _ => return None,
+    })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let data = fs::read(&options.input)
+        .map_err(|error| format!("couldn't read `{}`: {}", options.input, error))?;
+    let image = image::decode(&data)
+        .map_err(|error| format!("couldn't decode `{}`: {:?}", options.input, error))?;
+    let blob = build_blob(&image, options)?;
+    fs::write(&options.output, &blob)
+        .map_err(|error| format!("couldn't write `{}`: {}", options.output, error))?;
+
+    println!(
+        "{}: {}x{} ({}x{} padded), {:?}{}, {} bytes",
+        options.output,
+        image.width,
+        image.height,
+        image.width.next_power_of_two(),
+        image.height.next_power_of_two(),
+        options.format,
+        if options.swizzle { ", swizzled" } else { "" },
+        blob.len()
+    );
+    Ok(())
+}
+
+/// Converts `image` into a blob as `options` say.
+fn build_blob(image: &image::Image, options: &Options) -> Result<Vec<u8>, String> {
+    if image.width > MAX_TEXTURE_SIZE || image.height > MAX_TEXTURE_SIZE {
+        return Err(format!(
+            "{}x{} is larger than the {}x{} the PSP can sample",
+            image.width, image.height, MAX_TEXTURE_SIZE, MAX_TEXTURE_SIZE
+        ));
+    }
+
+    let header = BlobHeader {
+        format: options.format,
+        width: image.width,
+        height: image.height,
+        swizzled: options.swizzle,
+        palette_colors: options.format.palette_size() as u32,
+    };
+    let converted = convert::convert(
+        &image.pixels,
+        image.width,
+        image.height,
+        options.format,
+        options.dither,
+    );
+
+    // Pads every row to the stride and the rows to a power of two, as the PSP samples them.
+    let row = options.format.bytes_for(image.width);
+    let row_bytes = options.format.bytes_for(header.stride());
+    let rows = header.rows() as usize;
+    let mut pixels = vec![0; header.pixels_size()];
+    for (src, dst) in converted
+        .pixels
+        .chunks_exact(row)
+        .zip(pixels.chunks_exact_mut(row_bytes))
+    {
+        dst[..row].copy_from_slice(src);
+    }
+
+    if options.swizzle {
+        if !swizzle::can_swizzle(row_bytes, rows) {
+            return Err(format!(
+                "{}x{} can't be swizzled: rows must be a multiple of 16 bytes and the height a multiple of 8",
+                header.stride(),
+                rows
+            ));
+        }
+        pixels = swizzle::swizzle(&pixels, row_bytes, rows);
+    }
+
+    // The whole palette is stored, keeping the pixels after it aligned to 16 bytes.
+    let mut palette = converted.palette;
+    palette.resize(options.format.palette_size(), [0; 4]);
+
+    Ok(blob::build(&header, &palette, &pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Option<Options>, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    fn options(format: PixelFormat, swizzle: bool) -> Options {
+        Options {
+            input: String::new(),
+            output: String::new(),
+            format,
+            dither: Dither::None,
+            swizzle,
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let options = args("in.png out.sptx").unwrap().unwrap();
+        assert_eq!(
+            (options.input.as_str(), options.output.as_str()),
+            ("in.png", "out.sptx")
+        );
+        assert_eq!(options.format, PixelFormat::Psm8888);
+        assert_eq!(options.dither, Dither::None);
+        assert!(!options.swizzle);
+    }
+
+    #[test]
+    fn flags() {
+        let options = args("-f CLUT8 in.png --dither out.sptx -s")
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.format, PixelFormat::Clut8);
+        assert_eq!(options.dither, Dither::Ordered);
+        assert!(options.swizzle);
+        assert_eq!(
+            args("--format rgb565 a b").unwrap().unwrap().format,
+            PixelFormat::Psm5650
+        );
+        assert!(args("a -h").unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(args("-f").unwrap_err(), "missing value for --format");
+        assert_eq!(args("-f 1234 a b").unwrap_err(), "unknown format `1234`");
+        assert_eq!(args("-x a b").unwrap_err(), "unknown option `-x`");
+        assert_eq!(
+            args("a").unwrap_err(),
+            "expected an input and an output path"
+        );
+        assert!(args("a b c").is_err());
+    }
+
+    #[test]
+    fn blob_round_trip() {
+        let image = image::decode(include_bytes!(
+            "../../../src/graphics/image/fixtures/rgb24.bmp"
+        ))
+        .unwrap();
+        let data = build_blob(&image, &options(PixelFormat::Psm8888, false)).unwrap();
+        let blob = blob::parse(&data).unwrap();
+        assert_eq!(
+            blob.header,
+            BlobHeader {
+                format: PixelFormat::Psm8888,
+                width: 3,
+                height: 2,
+                swizzled: false,
+                palette_colors: 0,
+            }
+        );
+        assert!(blob.palette.is_empty());
+        // Rows padded from 3 to 4 pixels.
+        assert_eq!(blob.pixels.len(), 4 * 2 * 4);
+        for y in 0..2 {
+            assert_eq!(
+                blob.pixels[y * 16..y * 16 + 12],
+                image.pixels[y * 12..y * 12 + 12]
+            );
+            assert_eq!(blob.pixels[y * 16 + 12..y * 16 + 16], [0; 4]);
+        }
+    }
+
+    #[test]
+    fn swizzled_palette_round_trip() {
+        let image = image::Image {
+            width: 32,
+            height: 8,
+            pixels: (0..32 * 8)
+                .flat_map(|i| [(i % 3 * 100) as u8, 0, 0, 255])
+                .collect(),
+        };
+        let data = build_blob(&image, &options(PixelFormat::Clut8, true)).unwrap();
+        let blob = blob::parse(&data).unwrap();
+        assert!(blob.header.swizzled);
+        assert_eq!(blob.header.palette_colors, 256);
+        assert_eq!(blob.palette.len(), 256 * 4);
+        let pixels = swizzle::unswizzle(blob.pixels, 32, 8);
+        for (index, &entry) in pixels.iter().enumerate() {
+            let color = &blob.palette[entry as usize * 4..entry as usize * 4 + 4];
+            assert_eq!(color, &image.pixels[index * 4..index * 4 + 4]);
+        }
+    }
+
+    #[test]
+    fn rejects_unswizzlable_and_large_images() {
+        let small = image::Image {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 16],
+        };
+        assert!(build_blob(&small, &options(PixelFormat::Psm8888, true)).is_err());
+        let large = image::Image {
+            width: MAX_TEXTURE_SIZE * 2,
+            height: 1,
+            pixels: vec![0; (MAX_TEXTURE_SIZE * 2 * 4) as usize],
+        };
+        assert!(build_blob(&large, &options(PixelFormat::Psm8888, false)).is_err());
+    }
+}